@@ -2,6 +2,8 @@
 //!
 //! 演示 NSON 支持的所有数字类型
 
+#![allow(clippy::approx_constant)]
+
 use nson::{Array, Map, Value, m};
 
 fn main() {
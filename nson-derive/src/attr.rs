@@ -0,0 +1,113 @@
+//! `#[nson(...)]` field attributes
+
+use syn::{Error, Expr, ExprPath, Field, Ident, LitStr, Result};
+
+const MAX_KEY_LEN: usize = u8::MAX as usize - 1;
+
+/// How a field is stored.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Value,
+    Id,
+    TimeStamp,
+    Binary,
+}
+
+pub(crate) enum Default {
+    None,
+    Trait,
+    Path(ExprPath),
+}
+
+pub(crate) struct FieldAttrs {
+    pub ident: Ident,
+    pub key: String,
+    pub kind: Kind,
+    pub default: Default,
+    pub skip: bool,
+    pub flatten: bool,
+}
+
+impl FieldAttrs {
+    pub(crate) fn parse(field: &Field) -> Result<FieldAttrs> {
+        let ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| Error::new_spanned(field, "expected a named field"))?;
+
+        let mut rename: Option<LitStr> = None;
+        let mut kind = Kind::Value;
+        let mut default = Default::None;
+        let mut skip = false;
+        let mut flatten = false;
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("nson")) {
+            attr.parse_nested_meta(|meta| {
+                let mut set_kind = |k: Kind| {
+                    if kind != Kind::Value {
+                        return Err(meta.error("only one of `id`, `timestamp` or `binary`"));
+                    }
+                    kind = k;
+                    Ok(())
+                };
+
+                if meta.path.is_ident("rename") {
+                    rename = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("id") {
+                    set_kind(Kind::Id)?;
+                } else if meta.path.is_ident("timestamp") {
+                    set_kind(Kind::TimeStamp)?;
+                } else if meta.path.is_ident("binary") {
+                    set_kind(Kind::Binary)?;
+                } else if meta.path.is_ident("default") {
+                    default = if meta.input.peek(syn::Token![=]) {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        match lit.parse::<Expr>()? {
+                            Expr::Path(path) => Default::Path(path),
+                            _ => return Err(Error::new(lit.span(), "expected a function path")),
+                        }
+                    } else {
+                        Default::Trait
+                    };
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    flatten = true;
+                } else {
+                    return Err(meta.error("unknown nson attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        if flatten && (rename.is_some() || kind != Kind::Value) {
+            return Err(Error::new_spanned(
+                &ident,
+                "`flatten` can not be combined with `rename`, `id`, `timestamp` or `binary`",
+            ));
+        }
+
+        let key = match &rename {
+            Some(lit) => lit.value(),
+            None => ident.to_string().trim_start_matches("r#").to_string(),
+        };
+
+        if !flatten && !skip && (key.is_empty() || key.len() > MAX_KEY_LEN) {
+            let message = format!("key len must be between 1 and {} bytes", MAX_KEY_LEN);
+            return Err(match &rename {
+                Some(lit) => Error::new(lit.span(), message),
+                None => Error::new_spanned(&ident, message),
+            });
+        }
+
+        Ok(FieldAttrs {
+            ident,
+            key,
+            kind,
+            default,
+            skip,
+            flatten,
+        })
+    }
+}
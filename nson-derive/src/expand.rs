@@ -0,0 +1,298 @@
+//! Code generation
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, GenericParam, Generics, Result, Type, parse_quote};
+
+use crate::attr::{Default, FieldAttrs, Kind};
+
+struct Field<'a> {
+    attrs: FieldAttrs,
+    ty: &'a Type,
+}
+
+fn fields(input: &DeriveInput) -> Result<Vec<Field<'_>>> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            Fields::Unit => return Ok(Vec::new()),
+            Fields::Unnamed(_) => {
+                return Err(Error::new_spanned(
+                    input,
+                    "nson derive only supports structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "nson derive only supports structs with named fields",
+            ));
+        }
+    };
+
+    let mut fields: Vec<Field> = Vec::new();
+
+    for field in named {
+        let attrs = FieldAttrs::parse(field)?;
+
+        if !attrs.flatten
+            && !attrs.skip
+            && fields
+                .iter()
+                .any(|f| !f.attrs.flatten && !f.attrs.skip && f.attrs.key == attrs.key)
+        {
+            return Err(Error::new_spanned(
+                &attrs.ident,
+                format!("duplicate key `{}`", attrs.key),
+            ));
+        }
+
+        fields.push(Field {
+            attrs,
+            ty: &field.ty,
+        });
+    }
+
+    Ok(fields)
+}
+
+/// Add `bound` to every type parameter.
+fn with_bound(generics: &Generics, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(#bound));
+        }
+    }
+
+    generics
+}
+
+pub(crate) fn to_nson(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let fields = fields(input)?;
+
+    let generics = with_bound(&input.generics, quote!(::nson::codec::ToNson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut sizes = Vec::new();
+    let mut writes = Vec::new();
+
+    for field in fields.iter().filter(|f| !f.attrs.skip) {
+        let ident = &field.attrs.ident;
+        let key = &field.attrs.key;
+
+        if field.attrs.flatten {
+            sizes.push(quote! {
+                ::nson::codec::ToNsonMap::entries_size(&self.#ident)
+            });
+            writes.push(quote! {
+                ::nson::codec::ToNsonMap::write_entries(&self.#ident, writer)?;
+            });
+            continue;
+        }
+
+        let value = match field.attrs.kind {
+            Kind::Value => quote! { &self.#ident },
+            Kind::Id => quote! { &::nson::codec::AsId::to_id(&self.#ident) },
+            Kind::TimeStamp => quote! { &::nson::codec::AsTimeStamp::to_timestamp(&self.#ident) },
+            Kind::Binary => quote! {
+                &::nson::codec::BinaryRef(::nson::codec::AsBinary::binary_bytes(&self.#ident))
+            },
+        };
+
+        sizes.push(quote! { __p::field_size(#key, #value) });
+        writes.push(quote! { __p::write_field(writer, #key, #value)?; });
+    }
+
+    Ok(quote! {
+        const _: () = {
+            use ::nson::codec::__private as __p;
+
+            impl #impl_generics ::nson::codec::ToNsonMap for #name #ty_generics #where_clause {
+                fn entries_size(&self) -> usize {
+                    0 #(+ #sizes)*
+                }
+
+                fn write_entries<__W: __p::Write>(
+                    &self,
+                    writer: &mut __W,
+                ) -> ::nson::encode::EncodeResult<()> {
+                    #(#writes)*
+                    __p::Ok(())
+                }
+            }
+
+            impl #impl_generics ::nson::codec::ToNson for #name #ty_generics #where_clause {
+                fn nson_type(&self) -> ::nson::spec::DataType {
+                    ::nson::spec::DataType::Map
+                }
+
+                fn nson_size(&self) -> usize {
+                    __p::map_size(self)
+                }
+
+                fn write_nson<__W: __p::Write>(
+                    &self,
+                    writer: &mut __W,
+                ) -> ::nson::encode::EncodeResult<()> {
+                    __p::write_map(writer, self)
+                }
+            }
+        };
+    })
+}
+
+fn default_value(default: &Default) -> Option<TokenStream> {
+    match default {
+        Default::None => None,
+        Default::Trait => Some(quote! { __p::Default::default() }),
+        Default::Path(path) => Some(quote! { #path() }),
+    }
+}
+
+pub(crate) fn from_nson(input: &DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let fields = fields(input)?;
+
+    let generics = with_bound(&input.generics, quote!(::nson::codec::FromNson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let builder = format_ident!("__NsonBuilder");
+
+    let mut builder_fields = Vec::new();
+    let mut builder_defaults = Vec::new();
+    let mut reads = Vec::new();
+    let mut flattened = Vec::new();
+    let mut builds = Vec::new();
+
+    for field in &fields {
+        let ident = &field.attrs.ident;
+        let ty = field.ty;
+        let key = &field.attrs.key;
+
+        if field.attrs.skip {
+            let value = default_value(&field.attrs.default)
+                .unwrap_or_else(|| quote! { __p::Default::default() });
+            builds.push(quote! { #ident: #value });
+            continue;
+        }
+
+        if field.attrs.flatten {
+            builder_fields.push(quote! {
+                #ident: <#ty as ::nson::codec::FromNsonMap>::Builder
+            });
+            builder_defaults.push(quote! { #ident: __p::Default::default() });
+            flattened.push(quote! {
+                if <#ty as ::nson::codec::FromNsonMap>::read_entry(
+                    &mut builder.#ident,
+                    key,
+                    tag,
+                    reader,
+                )? {
+                    return __p::Ok(true);
+                }
+            });
+            builds.push(quote! {
+                #ident: <#ty as ::nson::codec::FromNsonMap>::build(builder.#ident)?
+            });
+            continue;
+        }
+
+        builder_fields.push(quote! { #ident: __p::Option<#ty> });
+        builder_defaults.push(quote! { #ident: __p::None });
+
+        let read = match field.attrs.kind {
+            Kind::Value => quote! { <#ty as ::nson::codec::FromNson>::read_nson(reader, tag) },
+            Kind::Id => quote! { __p::read_id::<__R, #ty>(reader, tag) },
+            Kind::TimeStamp => quote! { __p::read_timestamp::<__R, #ty>(reader, tag) },
+            Kind::Binary => quote! { __p::read_binary::<__R, #ty>(reader, tag) },
+        };
+
+        reads.push(quote! {
+            #key => {
+                let value = #read.map_err(|e| __p::within(e, #key))?;
+                builder.#ident = __p::Some(value);
+                return __p::Ok(true);
+            }
+        });
+
+        let missing = match default_value(&field.attrs.default) {
+            Some(value) => value,
+            None if field.attrs.kind == Kind::Value => quote! {
+                match <#ty as ::nson::codec::FromNson>::nson_missing() {
+                    __p::Some(value) => value,
+                    __p::None => return __p::Err(__p::missing(#key)),
+                }
+            },
+            None => quote! { return __p::Err(__p::missing(#key)) },
+        };
+
+        builds.push(quote! {
+            #ident: match builder.#ident {
+                __p::Some(value) => value,
+                __p::None => #missing,
+            }
+        });
+    }
+
+    Ok(quote! {
+        const _: () = {
+            use ::nson::codec::__private as __p;
+
+            #[doc(hidden)]
+            pub struct #builder #impl_generics #where_clause {
+                #(#builder_fields,)*
+                __marker: __p::PhantomData<fn() -> #name #ty_generics>,
+            }
+
+            impl #impl_generics __p::Default for #builder #ty_generics #where_clause {
+                fn default() -> Self {
+                    #builder {
+                        #(#builder_defaults,)*
+                        __marker: __p::PhantomData,
+                    }
+                }
+            }
+
+            impl #impl_generics ::nson::codec::FromNsonMap for #name #ty_generics #where_clause {
+                type Builder = #builder #ty_generics;
+
+                #[allow(unused_variables)]
+                fn read_entry<__R: __p::Read>(
+                    builder: &mut Self::Builder,
+                    key: &str,
+                    tag: ::nson::spec::DataType,
+                    reader: &mut __R,
+                ) -> ::nson::decode::DecodeResult<bool> {
+                    match key {
+                        #(#reads)*
+                        _ => {}
+                    }
+
+                    #(#flattened)*
+
+                    __p::Ok(false)
+                }
+
+                #[allow(unused_variables)]
+                fn build(builder: Self::Builder) -> ::nson::decode::DecodeResult<Self> {
+                    __p::Ok(#name {
+                        #(#builds,)*
+                    })
+                }
+            }
+
+            impl #impl_generics ::nson::codec::FromNson for #name #ty_generics #where_clause {
+                fn read_nson<__R: __p::Read>(
+                    reader: &mut __R,
+                    tag: ::nson::spec::DataType,
+                ) -> ::nson::decode::DecodeResult<Self> {
+                    __p::read_map(reader, tag)
+                }
+            }
+        };
+    })
+}
@@ -0,0 +1,36 @@
+//! Derive macros for NSON.
+//!
+//! `#[derive(ToNson, FromNson)]` implements the `nson::codec` traits for
+//! structs with named fields, which are encoded as maps. These are
+//! re-exported by the `nson` crate behind the `derive` feature.
+//!
+//! Field attributes:
+//!
+//! - `#[nson(rename = "name")]` uses a different key.
+//! - `#[nson(default)]` or `#[nson(default = "path")]` fills a missing field.
+//! - `#[nson(skip)]` never writes the field and reads it as its default.
+//! - `#[nson(flatten)]` writes the entries of a nested map type inline.
+//! - `#[nson(id)]`, `#[nson(timestamp)]` and `#[nson(binary)]` store the
+//!   field as an `Id`, `TimeStamp` or `Binary`.
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+mod attr;
+mod expand;
+
+#[proc_macro_derive(ToNson, attributes(nson))]
+pub fn derive_to_nson(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand::to_nson(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromNson, attributes(nson))]
+pub fn derive_from_nson(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand::from_nson(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
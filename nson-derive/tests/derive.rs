@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use nson::codec;
+use nson::decode::DecodeError;
+use nson::map::Error;
+use nson::spec::DataType;
+use nson::{Binary, FromNson, Id, Map, TimeStamp, ToNson, m};
+
+#[derive(ToNson, FromNson, Debug, PartialEq)]
+struct Device {
+    #[nson(rename = "_id")]
+    id: Id,
+    name: String,
+    port: u16,
+    enabled: bool,
+    ratio: f32,
+    tags: Vec<String>,
+    note: Option<String>,
+}
+
+fn device() -> Device {
+    Device {
+        id: Id::with_bytes([1; 12]),
+        name: "sensor".into(),
+        port: 8080,
+        enabled: true,
+        ratio: 0.5,
+        tags: vec!["a".into(), "b".into()],
+        note: None,
+    }
+}
+
+#[test]
+fn matches_map_encoding() {
+    let map = m! {
+        "_id": Id::with_bytes([1; 12]),
+        "name": "sensor",
+        "port": 8080u16,
+        "enabled": true,
+        "ratio": 0.5f32,
+        "tags": ["a", "b"],
+        "note": null,
+    };
+
+    let device = device();
+    let bytes = codec::to_vec(&device).unwrap();
+
+    assert_eq!(bytes, map.to_bytes().unwrap());
+    assert_eq!(device.nson_size(), bytes.len());
+    assert_eq!(codec::from_slice::<Device>(&bytes).unwrap(), device);
+}
+
+#[test]
+fn lenient_numbers_and_unknown_keys() {
+    let map = m! {
+        "_id": Id::with_bytes([1; 12]),
+        "extra": {"nested": [1, 2, 3]},
+        "name": "sensor",
+        "port": 8080u32,
+        "enabled": true,
+        "ratio": 0.5f64,
+        "tags": ["a", "b"],
+        "blob": Binary(vec![0; 100]),
+    };
+
+    let device: Device = codec::from_slice(&map.to_bytes().unwrap()).unwrap();
+    assert_eq!(device, self::device());
+}
+
+#[test]
+fn errors() {
+    let map = m! {"_id": Id::zero(), "name": 1};
+    let err = codec::from_slice::<Device>(&map.to_bytes().unwrap()).unwrap_err();
+    assert!(matches!(
+        err,
+        DecodeError::Field(Error::UnexpectedType {
+            ref key,
+            expected: DataType::String,
+            found: DataType::I32,
+        }) if key == "name"
+    ));
+
+    let map = m! {"_id": Id::zero(), "name": "x", "port": 70000};
+    let err = codec::from_slice::<Device>(&map.to_bytes().unwrap()).unwrap_err();
+    assert!(matches!(err, DecodeError::Field(Error::OutOfRange { ref key, .. }) if key == "port"));
+
+    let map = m! {"_id": Id::zero(), "name": "x"};
+    let err = codec::from_slice::<Device>(&map.to_bytes().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "Key `port` not present");
+
+    let map =
+        m! {"_id": Id::zero(), "name": "x", "port": 1, "enabled": true, "ratio": 1.0, "tags": [1]};
+    let err = codec::from_slice::<Device>(&map.to_bytes().unwrap()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unexpected type for key `tags.0`: expected String, found I32"
+    );
+}
+
+fn default_level() -> u8 {
+    3
+}
+
+#[derive(ToNson, FromNson, Debug, PartialEq, Default)]
+struct Meta {
+    #[nson(default = "default_level")]
+    level: u8,
+    #[nson(skip)]
+    cached: Option<u32>,
+}
+
+#[derive(ToNson, FromNson, Debug, PartialEq)]
+struct Record {
+    #[nson(id)]
+    id: [u8; 12],
+    #[nson(timestamp)]
+    created: u64,
+    #[nson(binary)]
+    payload: Vec<u8>,
+    #[nson(binary)]
+    key: [u8; 4],
+    #[nson(flatten)]
+    meta: Meta,
+    #[nson(flatten)]
+    rest: Map,
+}
+
+#[test]
+fn attributes() {
+    let record = Record {
+        id: [2; 12],
+        created: 42,
+        payload: vec![1, 2, 3],
+        key: [9; 4],
+        meta: Meta {
+            level: 1,
+            cached: Some(7),
+        },
+        rest: m! {"x": "y"},
+    };
+
+    let bytes = codec::to_vec(&record).unwrap();
+    let map = Map::from_bytes(&bytes).unwrap();
+
+    assert_eq!(
+        map,
+        m! {
+            "id": Id::with_bytes([2; 12]),
+            "created": TimeStamp(42),
+            "payload": Binary(vec![1, 2, 3]),
+            "key": Binary(vec![9; 4]),
+            "level": 1u8,
+            "x": "y",
+        }
+    );
+
+    let decoded: Record = codec::from_slice(&bytes).unwrap();
+    assert_eq!(
+        decoded.meta,
+        Meta {
+            level: 1,
+            cached: None
+        }
+    );
+    assert_eq!(decoded.rest, m! {"x": "y"});
+    assert_eq!(decoded.key, [9; 4]);
+
+    let map = m! {
+        "id": Id::with_bytes([2; 12]),
+        "created": TimeStamp(42),
+        "payload": Binary(vec![]),
+        "key": Binary(vec![1, 2]),
+    };
+    let err = codec::from_slice::<Record>(&map.to_bytes().unwrap()).unwrap_err();
+    assert!(
+        matches!(err, DecodeError::Field(Error::InvalidLength { ref key, expected: 4, found: 2 }) if key == "key")
+    );
+
+    let map = m! {
+        "id": Id::with_bytes([2; 12]),
+        "created": TimeStamp(42),
+        "payload": Binary(vec![]),
+        "key": Binary(vec![1, 2, 3, 4]),
+    };
+    let decoded: Record = codec::from_slice(&map.to_bytes().unwrap()).unwrap();
+    assert_eq!(decoded.meta.level, 3);
+    assert!(decoded.rest.is_empty());
+}
+
+#[derive(ToNson, FromNson, Debug, PartialEq)]
+struct Wrapper<T> {
+    inner: T,
+    children: BTreeMap<String, Meta>,
+}
+
+#[test]
+fn generics_and_nesting() {
+    let mut children = BTreeMap::new();
+    children.insert("a".to_string(), Meta::default());
+
+    let value = Wrapper {
+        inner: device(),
+        children,
+    };
+
+    let bytes = codec::to_vec(&value).unwrap();
+    let decoded: Wrapper<Device> = codec::from_slice(&bytes).unwrap();
+    assert_eq!(decoded, value);
+
+    let map = Map::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        map.get_map("inner").unwrap().get_str("name").unwrap(),
+        "sensor"
+    );
+    assert_eq!(
+        map.get_map("children").unwrap().get_map("a").unwrap(),
+        &m! {"level": 0u8}
+    );
+}
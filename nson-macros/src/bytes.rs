@@ -0,0 +1,386 @@
+//! `nson_bytes!`
+//!
+//! This mirrors `nson::encode` for constant values. The proc macro can not
+//! depend on `nson` itself, so the tags and limits are duplicated here.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{ParseStream, Parser};
+use syn::{
+    Error, Ident, Lit, LitByteStr, LitFloat, LitInt, LitStr, Result, Token, braced, bracketed,
+    parenthesized, token,
+};
+
+const MAX_NSON_SIZE: usize = 64 * 1024 * 1024;
+const MAX_KEY_LEN: usize = u8::MAX as usize - 1;
+
+const BOOL: u8 = 0x01;
+const NULL: u8 = 0x02;
+const F32: u8 = 0x11;
+const F64: u8 = 0x12;
+const I32: u8 = 0x13;
+const I64: u8 = 0x14;
+const U32: u8 = 0x15;
+const U64: u8 = 0x16;
+const I8: u8 = 0x17;
+const U8: u8 = 0x18;
+const I16: u8 = 0x19;
+const U16: u8 = 0x1A;
+const STRING: u8 = 0x21;
+const BINARY: u8 = 0x22;
+const ARRAY: u8 = 0x31;
+const MAP: u8 = 0x32;
+const TIMESTAMP: u8 = 0x41;
+const ID: u8 = 0x42;
+
+pub(crate) fn expand(input: TokenStream) -> Result<TokenStream> {
+    let mut buf = Vec::new();
+
+    let parser = |input: ParseStream| write_map(input, &mut buf);
+    parser.parse2(input)?;
+
+    let bytes = LitByteStr::new(&buf, Span::call_site());
+
+    Ok(quote! { (#bytes as &'static [u8]) })
+}
+
+fn write_map(input: ParseStream, buf: &mut Vec<u8>) -> Result<()> {
+    let start = begin(buf);
+    let mut keys = Vec::new();
+
+    while !input.is_empty() {
+        if input.peek(Token![..]) {
+            return Err(unsupported(input, "spreads (`..map`)"));
+        }
+        if input.peek(token::Bracket) {
+            return Err(unsupported(input, "computed keys (`[expr]: v`)"));
+        }
+
+        let key: LitStr = input.parse()?;
+        let name = key.value();
+
+        if name.is_empty() || name.len() > MAX_KEY_LEN {
+            return Err(Error::new(
+                key.span(),
+                format!("key len must be between 1 and {} bytes", MAX_KEY_LEN),
+            ));
+        }
+
+        if keys.contains(&name) {
+            return Err(Error::new(key.span(), format!("duplicate key `{}`", name)));
+        }
+
+        if input.peek(Token![?]) {
+            return Err(unsupported(input, "optional entries (`\"k\"?: v`)"));
+        }
+
+        if input.peek(Token![=>]) {
+            input.parse::<Token![=>]>()?;
+        } else {
+            input.parse::<Token![:]>()?;
+        }
+
+        buf.push(name.len() as u8 + 1);
+        buf.extend_from_slice(name.as_bytes());
+        write_value(input, buf)?;
+
+        keys.push(name);
+
+        if input.is_empty() {
+            break;
+        }
+
+        input.parse::<Token![,]>()?;
+    }
+
+    finish(input, buf, start)
+}
+
+fn write_array(input: ParseStream, buf: &mut Vec<u8>) -> Result<()> {
+    let start = begin(buf);
+
+    while !input.is_empty() {
+        write_value(input, buf)?;
+
+        if input.is_empty() {
+            break;
+        }
+
+        input.parse::<Token![,]>()?;
+    }
+
+    finish(input, buf, start)
+}
+
+/// The forms of `m!` that need runtime values.
+fn unsupported(input: ParseStream, form: &str) -> Error {
+    input.error(format!(
+        "{} are not supported by `nson_bytes!`, use `m!` instead",
+        form
+    ))
+}
+
+/// Reserve the length prefix of a map or array.
+fn begin(buf: &mut Vec<u8>) -> usize {
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    start
+}
+
+/// Terminate a map or array and backpatch its length.
+fn finish(input: ParseStream, buf: &mut Vec<u8>, start: usize) -> Result<()> {
+    buf.push(0);
+
+    let len = buf.len() - start;
+    if len > MAX_NSON_SIZE {
+        return Err(input.error(format!("encoded len {} exceeds MAX_NSON_SIZE", len)));
+    }
+
+    buf[start..start + 4].copy_from_slice(&(len as u32).to_le_bytes());
+    Ok(())
+}
+
+fn write_value(input: ParseStream, buf: &mut Vec<u8>) -> Result<()> {
+    if input.peek(token::Brace) {
+        let content;
+        braced!(content in input);
+        buf.push(MAP);
+        return write_map(&content, buf);
+    }
+
+    if input.peek(token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        buf.push(ARRAY);
+        return write_array(&content, buf);
+    }
+
+    if input.peek(Token![@]) {
+        input.parse::<Token![@]>()?;
+        let kind: Ident = input.parse()?;
+        let content;
+        parenthesized!(content in input);
+        write_typed(&kind, &content, buf)?;
+
+        if !content.is_empty() {
+            return Err(content.error("unexpected token"));
+        }
+
+        return Ok(());
+    }
+
+    if input.peek(Ident) && !input.peek(syn::LitBool) {
+        let ident: Ident = input.parse()?;
+        if ident == "null" {
+            buf.push(NULL);
+            return Ok(());
+        }
+
+        return Err(not_constant(ident.span()));
+    }
+
+    if input.peek(Token![-]) {
+        return write_number(input, None, buf);
+    }
+
+    match input.parse::<Lit>() {
+        Ok(Lit::Str(s)) => write_string(STRING, s.value().as_bytes(), s.span(), buf),
+        Ok(Lit::ByteStr(b)) => write_string(BINARY, &b.value(), b.span(), buf),
+        Ok(Lit::Bool(b)) => {
+            buf.push(BOOL);
+            buf.push(b.value as u8);
+            Ok(())
+        }
+        Ok(Lit::Int(lit)) => write_int(&lit, false, None, buf),
+        Ok(Lit::Float(lit)) => write_float(&lit, false, None, buf),
+        Ok(lit) => Err(not_constant(lit.span())),
+        Err(err) => Err(Error::new(err.span(), "expected a constant value")),
+    }
+}
+
+fn not_constant(span: Span) -> Error {
+    Error::new(
+        span,
+        "nson_bytes! only accepts literals, `null`, maps, arrays and typed literals",
+    )
+}
+
+fn write_typed(kind: &Ident, input: ParseStream, buf: &mut Vec<u8>) -> Result<()> {
+    match kind.to_string().as_str() {
+        "id" => {
+            let lit: LitStr = input.parse()?;
+            let bytes = decode_hex(&lit)?;
+            if bytes.len() != 12 {
+                return Err(Error::new(
+                    lit.span(),
+                    "id must be a 24 character hexadecimal string",
+                ));
+            }
+
+            buf.push(ID);
+            buf.extend_from_slice(&bytes);
+            Ok(())
+        }
+        "ts" => {
+            let lit: LitInt = input.parse()?;
+            let ts: u64 = lit.base10_parse()?;
+            buf.push(TIMESTAMP);
+            buf.extend_from_slice(&ts.to_le_bytes());
+            Ok(())
+        }
+        "bin" => {
+            let lit: LitStr = input.parse()?;
+            let bytes = decode_hex(&lit)?;
+            write_string(BINARY, &bytes, lit.span(), buf)
+        }
+        "f32" | "f64" | "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" => {
+            write_number(input, Some(kind), buf)
+        }
+        _ => Err(Error::new(
+            kind.span(),
+            format!("unknown typed literal `@{}`", kind),
+        )),
+    }
+}
+
+fn write_string(tag: u8, bytes: &[u8], span: Span, buf: &mut Vec<u8>) -> Result<()> {
+    if bytes.len() > MAX_NSON_SIZE - 4 {
+        return Err(Error::new(span, "len must < MAX_NSON_SIZE - 4"));
+    }
+
+    buf.push(tag);
+    buf.extend_from_slice(&(bytes.len() as u32 + 4).to_le_bytes());
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn write_number(input: ParseStream, kind: Option<&Ident>, buf: &mut Vec<u8>) -> Result<()> {
+    let negative = input.parse::<Option<Token![-]>>()?.is_some();
+
+    match input.parse::<Lit>()? {
+        Lit::Int(lit) => write_int(&lit, negative, kind, buf),
+        Lit::Float(lit) => write_float(&lit, negative, kind, buf),
+        lit => Err(Error::new(lit.span(), "expected a number")),
+    }
+}
+
+/// The type of a number: the `@kind` if there is one, else the suffix.
+fn number_type(suffix: &str, kind: Option<&Ident>, default: &str, span: Span) -> Result<String> {
+    match kind {
+        Some(kind) if !suffix.is_empty() && kind != suffix => Err(Error::new(
+            span,
+            format!("suffix `{}` does not match `@{}`", suffix, kind),
+        )),
+        Some(kind) => Ok(kind.to_string()),
+        None if suffix.is_empty() => Ok(default.to_string()),
+        None => Ok(suffix.to_string()),
+    }
+}
+
+fn write_int(lit: &LitInt, negative: bool, kind: Option<&Ident>, buf: &mut Vec<u8>) -> Result<()> {
+    let ty = number_type(lit.suffix(), kind, "i32", lit.span())?;
+
+    let mut value: i128 = lit.base10_parse()?;
+    if negative {
+        value = -value;
+    }
+
+    let out_of_range = || Error::new(lit.span(), format!("integer out of range for `{}`", ty));
+
+    macro_rules! int {
+        ($tag:expr, $T:ty) => {{
+            let v = <$T>::try_from(value).map_err(|_| out_of_range())?;
+            buf.push($tag);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }};
+    }
+
+    match ty.as_str() {
+        "i8" => int!(I8, i8),
+        "u8" => int!(U8, u8),
+        "i16" => int!(I16, i16),
+        "u16" => int!(U16, u16),
+        "i32" => int!(I32, i32),
+        "u32" => int!(U32, u32),
+        "i64" => int!(I64, i64),
+        "u64" => int!(U64, u64),
+        "f32" | "f64" => {
+            return write_float_digits(&value.to_string(), &ty, lit.span(), buf);
+        }
+        _ => {
+            return Err(Error::new(
+                lit.span(),
+                format!("`{}` is not an nson number type", ty),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn write_float(
+    lit: &LitFloat,
+    negative: bool,
+    kind: Option<&Ident>,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    let ty = number_type(lit.suffix(), kind, "f64", lit.span())?;
+
+    let digits = if negative {
+        format!("-{}", lit.base10_digits())
+    } else {
+        lit.base10_digits().to_string()
+    };
+
+    write_float_digits(&digits, &ty, lit.span(), buf)
+}
+
+fn write_float_digits(digits: &str, ty: &str, span: Span, buf: &mut Vec<u8>) -> Result<()> {
+    let invalid = |_| Error::new(span, format!("invalid `{}` literal", ty));
+
+    match ty {
+        // Parse each width from the digits directly to avoid double rounding.
+        "f32" => {
+            let v: f32 = digits.parse().map_err(invalid)?;
+            buf.push(F32);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        "f64" => {
+            let v: f64 = digits.parse().map_err(invalid)?;
+            buf.push(F64);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        _ => {
+            return Err(Error::new(
+                span,
+                format!("a float can not be encoded as `{}`", ty),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_hex(lit: &LitStr) -> Result<Vec<u8>> {
+    let value = lit.value();
+    let hex = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(&value);
+
+    let invalid = || Error::new(lit.span(), "expected an even length hexadecimal string");
+
+    if !hex.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .filter(|pair| pair.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
@@ -0,0 +1,50 @@
+//! Procedural macros for NSON.
+//!
+//! These are re-exported by the `nson` crate behind the `macros` feature, so
+//! most users should depend on `nson` rather than on this crate directly.
+
+use proc_macro::TokenStream;
+
+mod bytes;
+
+/// Encode a map literal to NSON at compile time.
+///
+/// The macro accepts a subset of the `nson::m!` syntax: string literal keys
+/// followed by `:` or `=>`, and constant values: string, byte string,
+/// integer, float and bool literals, `null`, nested `{...}` maps and `[...]`
+/// arrays, and the typed literals `@id("...")`, `@ts(...)`, `@bin("...")`
+/// and `@u8(...)` ... `@f64(...)`. Optional entries (`"k"?: v`), spreads
+/// (`..map`) and computed keys (`[expr]: v`) depend on runtime values and are
+/// rejected with a compile error naming them.
+///
+/// Integer literals without a suffix are encoded as `I32` and float literals
+/// without a suffix as `F64`, like `m!` does. Keys, sizes and integer ranges
+/// are checked at compile time.
+///
+/// The expansion is a `&'static [u8]`, so it needs neither `std` nor `alloc`.
+///
+/// ```
+/// use nson_macros::nson_bytes;
+///
+/// const HELLO: &[u8] = nson_bytes! {
+///     "cmd": "hello",
+///     "v": 1u8,
+/// };
+///
+/// let map = nson::m! { "cmd": "hello", "v": 1u8 };
+/// assert_eq!(HELLO, &map.to_bytes().unwrap()[..]);
+/// ```
+///
+/// ```compile_fail
+/// let bytes = nson_macros::nson_bytes! { "v": 300u8 };
+/// ```
+///
+/// ```compile_fail
+/// let bytes = nson_macros::nson_bytes! { "v"?: Some(1u8) };
+/// ```
+#[proc_macro]
+pub fn nson_bytes(input: TokenStream) -> TokenStream {
+    bytes::expand(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
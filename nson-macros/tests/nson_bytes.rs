@@ -0,0 +1,96 @@
+use nson::{Map, Value, m};
+use nson_macros::nson_bytes;
+
+const HANDSHAKE: &[u8] = nson_bytes! {
+    "cmd": "hello",
+    "v": 1u8,
+};
+
+#[test]
+fn matches_runtime_encoding() {
+    let map = m! {"cmd": "hello", "v": 1u8};
+    assert_eq!(HANDSHAKE, &map.to_bytes().unwrap()[..]);
+}
+
+#[test]
+fn numbers() {
+    let bytes = nson_bytes! {
+        "i32": 1,
+        "neg": -2,
+        "i8": -128i8,
+        "u16": 65535u16,
+        "i64": 5i64,
+        "u64": 18446744073709551615u64,
+        "f64": 1.5,
+        "f32": -0.1f32,
+        "typed": @u32(7),
+        "int_as_float": @f32(3),
+    };
+
+    let map = m! {
+        "i32": 1,
+        "neg": -2,
+        "i8": -128i8,
+        "u16": 65535u16,
+        "i64": 5i64,
+        "u64": u64::MAX,
+        "f64": 1.5,
+        "f32": -0.1f32,
+        "typed": 7u32,
+        "int_as_float": 3.0f32,
+    };
+
+    assert_eq!(bytes, &map.to_bytes().unwrap()[..]);
+}
+
+#[test]
+fn nested() {
+    let bytes = nson_bytes! {
+        "null": null,
+        "ok" => true,
+        "list": [1u8, "a", [], {}, {"x": false}],
+        "map": {"a": {"b": [null]}},
+        "raw": b"\x00\x01",
+    };
+
+    let map = m! {
+        "null": null,
+        "ok": true,
+        "list": [1u8, "a", nson::Array::new(), {}, {"x": false}],
+        "map": {"a": {"b": [null]}},
+        "raw": nson::Binary(vec![0, 1]),
+    };
+
+    assert_eq!(bytes, &map.to_bytes().unwrap()[..]);
+    assert_eq!(Map::from_bytes(bytes).unwrap(), map);
+}
+
+#[test]
+fn typed_literals() {
+    let bytes = nson_bytes! {
+        "id": @id("016f9dbd9df7f7dc9c86d573"),
+        "ts": @ts(1_700_000_000),
+        "bin": @bin("0xcafe"),
+    };
+
+    let map = Map::from_bytes(bytes).unwrap();
+    assert_eq!(
+        map.get("id"),
+        Some(&Value::Id(
+            nson::Id::with_string("016f9dbd9df7f7dc9c86d573").unwrap()
+        ))
+    );
+    assert_eq!(
+        map.get("ts"),
+        Some(&Value::TimeStamp(nson::TimeStamp(1_700_000_000)))
+    );
+    assert_eq!(
+        map.get("bin"),
+        Some(&Value::Binary(nson::Binary(vec![0xca, 0xfe])))
+    );
+}
+
+#[test]
+fn empty() {
+    assert_eq!(nson_bytes! {}, &Map::new().to_bytes().unwrap()[..]);
+}
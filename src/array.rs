@@ -20,7 +20,7 @@ use super::id::Id;
 use super::map::Map;
 use super::value::Value;
 
-#[derive(Clone, PartialEq, Default, Eq)]
+#[derive(Clone, PartialEq, Default, Eq, PartialOrd, Ord, Hash)]
 pub struct Array {
     inner: Vec<Value>,
 }
@@ -95,6 +95,14 @@ impl fmt::Debug for Array {
     }
 }
 
+/// Prints the [`text`](crate::text) notation, `FromStr` reads it back.
+/// `{:#}` indents maps and arrays.
+impl fmt::Display for Array {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        crate::text::write_array(fmt, self, fmt.alternate().then_some(0))
+    }
+}
+
 impl Deref for Array {
     type Target = Vec<Value>;
     fn deref(&self) -> &Vec<Value> {
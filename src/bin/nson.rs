@@ -0,0 +1,694 @@
+//! The `nson` command line tool, built with the `cli` feature.
+//!
+//! Binary input is a sequence of NSON maps, as written by `Map::to_bytes`.
+//! Files are read from the paths given, or stdin for `-` or none, and
+//! results go to stdout.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use nson::json_text::{self, JsonMode};
+use nson::key::KeyRules;
+use nson::{Map, Value, decode, inspect};
+
+const USAGE: &str = "\
+usage: nson <command> [options] [file]
+
+commands:
+  encode [--from json|text] [--lines] [file]
+      read a JSON or text notation document and write it as NSON,
+      with --lines every non-blank line is a document
+  decode [--to text|pretty|json] [--mode canonical|relaxed|plain] [file]
+      read one NSON document and print it
+  cat [--to text|pretty|json|nson] [--mode ...] [file...]
+      print every document of NSON sequence files, one per line
+  get <path> [--to ...] [--mode ...] [file]
+      print the value at a dotted path, such as `user.tags.0`, of every
+      document that has it, `\\.` is a dot within a key
+  diff [--from nson|json|text] <file> <file>
+      print the paths where two documents differ
+  dump [file]
+      print an annotated hex dump
+  validate [--strict-keys] [file]
+      check that every document decodes, re-encodes to the same bytes and
+      has valid keys, with --strict-keys `$`-prefixed and dotted keys too
+
+--mode picks the JSON form, so it needs JSON input or output.
+The output of `cat --to json` reads back with `encode --from json --lines`.
+Exit status is 1 when validation fails, documents differ or a path is
+missing, and 2 for usage errors.";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let command = args.remove(0);
+    let args = Args(args);
+
+    let result = match command.as_str() {
+        "encode" => encode(args),
+        "decode" => decode(args),
+        "cat" => cat(args),
+        "get" => get(args),
+        "diff" => diff(args),
+        "dump" => dump(args),
+        "validate" => validate(args),
+        _ => Err(usage(format!("unknown command `{}`", command))),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) if err.is::<Usage>() => {
+            eprintln!("nson: {}\nrun `nson --help` for usage", err);
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("nson: {}", err);
+            ExitCode::from(1)
+        }
+    }
+}
+
+/// Whether the command succeeded, `false` exits with status 1.
+type Result<T = bool> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Debug)]
+struct Usage(String);
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for Usage {}
+
+fn usage(message: String) -> Box<dyn Error> {
+    Box::new(Usage(message))
+}
+
+/// The arguments after the command, taken out as they are recognized.
+struct Args(Vec<String>);
+
+impl Args {
+    fn flag(&mut self, name: &str) -> bool {
+        let len = self.0.len();
+        self.0.retain(|arg| arg != name);
+        self.0.len() != len
+    }
+
+    /// Take `--name value` or `--name=value`.
+    fn option(&mut self, name: &str) -> Result<Option<String>> {
+        let prefix = format!("{}=", name);
+
+        if let Some(i) = self.0.iter().position(|arg| arg.starts_with(&prefix)) {
+            return Ok(Some(self.0.remove(i)[prefix.len()..].to_string()));
+        }
+
+        match self.0.iter().position(|arg| arg == name) {
+            Some(i) if i + 1 < self.0.len() => {
+                self.0.remove(i);
+                Ok(Some(self.0.remove(i)))
+            }
+            Some(_) => Err(usage(format!("{} needs a value", name))),
+            None => Ok(None),
+        }
+    }
+
+    /// The arguments left, which must be between `min` and `max` files or
+    /// other operands.
+    fn operands(self, min: usize, max: usize) -> Result<Vec<String>> {
+        if let Some(arg) = self
+            .0
+            .iter()
+            .find(|arg| arg.starts_with('-') && *arg != "-")
+        {
+            return Err(usage(format!("unknown option `{}`", arg)));
+        }
+
+        if self.0.len() < min {
+            return Err(usage("missing operand".to_string()));
+        }
+
+        if self.0.len() > max {
+            return Err(usage(format!("unexpected operand `{}`", self.0[max])));
+        }
+
+        Ok(self.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Nson,
+    Json(JsonMode),
+    Text,
+    Pretty,
+}
+
+impl Format {
+    /// Read `--to` or `--from`, and `--mode` for JSON.
+    fn take(args: &mut Args, name: &str, default: Format, allowed: &[&str]) -> Result<Format> {
+        let mode = match args.option("--mode")?.as_deref() {
+            None => None,
+            Some("canonical") => Some(JsonMode::Canonical),
+            Some("relaxed") => Some(JsonMode::Relaxed),
+            Some("plain") => Some(JsonMode::Plain),
+            Some(other) => return Err(usage(format!("unknown JSON mode `{}`", other))),
+        };
+
+        let format = match args.option(name)? {
+            None => default,
+            Some(format) if allowed.contains(&format.as_str()) => match format.as_str() {
+                "nson" => Format::Nson,
+                "json" => Format::Json(JsonMode::Canonical),
+                "text" => Format::Text,
+                _ => Format::Pretty,
+            },
+            Some(_) => {
+                return Err(usage(format!(
+                    "{} must be one of {}",
+                    name,
+                    allowed.join(", ")
+                )));
+            }
+        };
+
+        match (format, mode) {
+            (Format::Json(_), Some(mode)) => Ok(Format::Json(mode)),
+            (_, Some(_)) => Err(usage(format!("--mode needs {} json", name))),
+            (format, None) => Ok(format),
+        }
+    }
+
+    /// Parse one document.
+    fn parse(self, input: &[u8]) -> Result<Map> {
+        match self {
+            Format::Nson => {
+                let mut documents = documents(input);
+                let map = documents.next().unwrap_or(Err(Box::new(DocumentError {
+                    index: 0,
+                    offset: 0,
+                    message: "no document".to_string(),
+                })))?;
+
+                if documents.rest().is_empty() {
+                    Ok(map)
+                } else {
+                    Err(format!(
+                        "trailing bytes after the document at byte {}",
+                        documents.offset
+                    )
+                    .into())
+                }
+            }
+            Format::Json(_) => match json_text::from_str(std::str::from_utf8(input)?)? {
+                Value::Map(map) => Ok(map),
+                other => Err(format!("expected a JSON object, found {}", other).into()),
+            },
+            Format::Text | Format::Pretty => Ok(std::str::from_utf8(input)?.parse()?),
+        }
+    }
+
+    fn write(self, out: &mut impl Write, value: &Value) -> Result<()> {
+        match self {
+            Format::Nson => out.write_all(&value.to_bytes()?)?,
+            Format::Json(mode) => {
+                let mut text = String::new();
+                json_text::write_with(&mut text, value, mode)?;
+                writeln!(out, "{}", text)?;
+            }
+            Format::Text => writeln!(out, "{}", value)?,
+            Format::Pretty => writeln!(out, "{:#}", value)?,
+        }
+
+        Ok(())
+    }
+
+    /// Write a document, which for NSON is the map without a tag.
+    fn write_map(self, out: &mut impl Write, map: Map) -> Result<()> {
+        match self {
+            Format::Nson => Ok(out.write_all(&map.to_bytes()?)?),
+            _ => self.write(out, &Value::Map(map)),
+        }
+    }
+}
+
+fn read(path: Option<&str>) -> Result<Vec<u8>> {
+    let mut input = Vec::new();
+
+    match path {
+        None | Some("-") => {
+            io::stdin().read_to_end(&mut input)?;
+        }
+        Some(path) => {
+            input = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+        }
+    }
+
+    Ok(input)
+}
+
+/// The name of an input in messages.
+fn name(path: &str) -> &str {
+    if path == "-" { "stdin" } else { path }
+}
+
+fn stdout() -> BufWriter<io::StdoutLock<'static>> {
+    BufWriter::new(io::stdout().lock())
+}
+
+#[derive(Debug)]
+struct DocumentError {
+    index: usize,
+    offset: usize,
+    message: String,
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "document {} at byte {}: {}",
+            self.index, self.offset, self.message
+        )
+    }
+}
+
+impl Error for DocumentError {}
+
+/// The documents of an NSON sequence. It stops after the first one that
+/// can not be split off.
+struct Documents<'a> {
+    input: &'a [u8],
+    offset: usize,
+    index: usize,
+    strict: bool,
+}
+
+fn documents(input: &[u8]) -> Documents<'_> {
+    Documents {
+        input,
+        offset: 0,
+        index: 0,
+        strict: false,
+    }
+}
+
+impl<'a> Documents<'a> {
+    fn rest(&self) -> &'a [u8] {
+        &self.input[self.offset..]
+    }
+
+    fn error(&self, message: impl Into<String>) -> Box<dyn Error> {
+        Box::new(DocumentError {
+            index: self.index,
+            offset: self.offset,
+            message: message.into(),
+        })
+    }
+
+    /// Split off the next document and decode it.
+    fn split(&mut self) -> Result<Result<Map>> {
+        let rest = self.rest();
+        let Some(len) = rest.get(..4) else {
+            return Err(self.error("truncated length"));
+        };
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+
+        if len < nson::MIN_NSON_SIZE as usize || len > nson::MAX_NSON_SIZE as usize {
+            return Err(self.error(format!("invalid length {}", len)));
+        }
+
+        let Some(bytes) = rest.get(..len) else {
+            return Err(self.error(format!("length {} but {} bytes left", len, rest.len())));
+        };
+
+        let mut reader = bytes;
+        let map = match decode::decode_map(&mut reader) {
+            Ok(map) => map,
+            Err(err) => return Ok(Err(self.error(err.to_string()))),
+        };
+
+        if self.strict {
+            if !reader.is_empty() {
+                let message = format!(
+                    "length {} but the map ends after {} bytes",
+                    len,
+                    len - reader.len()
+                );
+                return Ok(Err(self.error(message)));
+            }
+
+            // a repeated key is decoded once, so the map shrinks
+            let encoded = map.to_bytes()?;
+            if encoded.len() != bytes.len() {
+                let message = format!(
+                    "re-encodes to {} bytes instead of {}, duplicate keys?",
+                    encoded.len(),
+                    len
+                );
+                return Ok(Err(self.error(message)));
+            }
+
+            if let Some(i) = encoded.iter().zip(bytes).position(|(a, b)| a != b) {
+                let message = format!(
+                    "re-encoding differs at byte {}, see `nson dump`",
+                    self.offset + i
+                );
+                return Ok(Err(self.error(message)));
+            }
+        }
+
+        Ok(Ok(map))
+    }
+}
+
+impl Iterator for Documents<'_> {
+    type Item = Result<Map>;
+
+    fn next(&mut self) -> Option<Result<Map>> {
+        if self.offset == self.input.len() {
+            return None;
+        }
+
+        let item = match self.split() {
+            // the rest can not be split into documents
+            Err(err) => {
+                self.offset = self.input.len();
+                Err(err)
+            }
+            Ok(item) => {
+                let len = u32::from_le_bytes(self.rest()[..4].try_into().unwrap());
+                self.offset += len as usize;
+                item
+            }
+        };
+
+        self.index += 1;
+        Some(item)
+    }
+}
+
+fn encode(mut args: Args) -> Result {
+    let from = Format::take(
+        &mut args,
+        "--from",
+        Format::Json(JsonMode::Canonical),
+        &["json", "text"],
+    )?;
+    let lines = args.flag("--lines");
+    let files = args.operands(0, 1)?;
+    let input = read(files.first().map(String::as_str))?;
+
+    let mut out = stdout();
+
+    if lines {
+        for (i, line) in input.split(|&b| b == b'\n').enumerate() {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            let map = from
+                .parse(line)
+                .map_err(|err| format!("line {}: {}", i + 1, err))?;
+            out.write_all(&map.to_bytes()?)?;
+        }
+    } else {
+        out.write_all(&from.parse(&input)?.to_bytes()?)?;
+    }
+
+    out.flush()?;
+    Ok(true)
+}
+
+fn decode(mut args: Args) -> Result {
+    let to = Format::take(&mut args, "--to", Format::Text, &["text", "pretty", "json"])?;
+    let files = args.operands(0, 1)?;
+    let map = Format::Nson.parse(&read(files.first().map(String::as_str))?)?;
+
+    let mut out = stdout();
+    to.write_map(&mut out, map)?;
+    out.flush()?;
+    Ok(true)
+}
+
+fn cat(mut args: Args) -> Result {
+    let to = Format::take(
+        &mut args,
+        "--to",
+        Format::Text,
+        &["text", "pretty", "json", "nson"],
+    )?;
+    let mut files = args.operands(0, usize::MAX)?;
+    if files.is_empty() {
+        files.push("-".to_string());
+    }
+
+    let mut out = stdout();
+    for file in &files {
+        for map in documents(&read(Some(file))?) {
+            let map = map.map_err(|err| format!("{}: {}", name(file), err))?;
+            to.write_map(&mut out, map)?;
+        }
+    }
+
+    out.flush()?;
+    Ok(true)
+}
+
+fn get(mut args: Args) -> Result {
+    let to = Format::take(
+        &mut args,
+        "--to",
+        Format::Text,
+        &["text", "pretty", "json", "nson"],
+    )?;
+    let operands = args.operands(1, 2)?;
+    let path = &operands[0];
+
+    let mut out = stdout();
+    let mut found = false;
+    for map in documents(&read(operands.get(1).map(String::as_str))?) {
+        if let Some(value) = lookup(&map?, path) {
+            to.write(&mut out, value)?;
+            found = true;
+        }
+    }
+
+    out.flush()?;
+    Ok(found)
+}
+
+/// Follow a dotted path of map keys and array indexes.
+fn lookup<'a>(map: &'a Map, path: &str) -> Option<&'a Value> {
+    let mut segments = split_path(path).into_iter();
+    let mut value = map.get(&segments.next()?)?;
+
+    for segment in segments {
+        value = match value {
+            Value::Map(map) => map.get(&segment)?,
+            Value::Array(array) => array.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
+
+/// Split a path on dots, reading `\.` as a dot within a key and `\\` as a
+/// backslash.
+fn split_path(path: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => segments.push(String::new()),
+            '\\' => {
+                let next = match chars.next() {
+                    Some(next @ ('.' | '\\')) => next,
+                    Some(other) => {
+                        segments.last_mut().unwrap().push('\\');
+                        other
+                    }
+                    None => '\\',
+                };
+                segments.last_mut().unwrap().push(next);
+            }
+            c => segments.last_mut().unwrap().push(c),
+        }
+    }
+
+    segments
+}
+
+fn diff(mut args: Args) -> Result {
+    let from = Format::take(&mut args, "--from", Format::Nson, &["nson", "json", "text"])?;
+    let files = args.operands(2, 2)?;
+
+    let parse = |file: &str| -> Result<Map> {
+        let input = read(Some(file))?;
+        from.parse(&input)
+            .map_err(|err| format!("{}: {}", name(file), err).into())
+    };
+    let (a, b) = (parse(&files[0])?, parse(&files[1])?);
+
+    let mut out = stdout();
+    let mut changes = Vec::new();
+    diff_maps(&a, &b, &mut String::new(), &mut changes);
+
+    for change in &changes {
+        writeln!(out, "{}", change)?;
+    }
+
+    out.flush()?;
+    Ok(changes.is_empty())
+}
+
+fn diff_maps(a: &Map, b: &Map, path: &mut String, changes: &mut Vec<String>) {
+    for (key, left) in a {
+        let len = push(path, key);
+        match b.get(key) {
+            Some(right) => diff_values(left, right, path, changes),
+            None => changes.push(format!("- {}: {}", path, left)),
+        }
+        path.truncate(len);
+    }
+
+    for (key, right) in b {
+        if !a.contains_key(key) {
+            let len = push(path, key);
+            changes.push(format!("+ {}: {}", path, right));
+            path.truncate(len);
+        }
+    }
+}
+
+fn diff_values(a: &Value, b: &Value, path: &mut String, changes: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Map(a), Value::Map(b)) => diff_maps(a, b, path, changes),
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let len = push(path, &i.to_string());
+                match (a.get(i), b.get(i)) {
+                    (Some(left), Some(right)) => diff_values(left, right, path, changes),
+                    (Some(left), None) => changes.push(format!("- {}: {}", path, left)),
+                    (None, Some(right)) => changes.push(format!("+ {}: {}", path, right)),
+                    (None, None) => unreachable!(),
+                }
+                path.truncate(len);
+            }
+        }
+        _ if a == b => (),
+        _ => {
+            changes.push(format!("- {}: {}", path, a));
+            changes.push(format!("+ {}: {}", path, b));
+        }
+    }
+}
+
+/// Append a segment to `path`, escaped the way `split_path` reads it, returning
+/// the length to truncate back to.
+fn push(path: &mut String, segment: &str) -> usize {
+    let len = path.len();
+    if len > 0 {
+        path.push('.');
+    }
+    for c in segment.chars() {
+        if c == '.' || c == '\\' {
+            path.push('\\');
+        }
+        path.push(c);
+    }
+    len
+}
+
+fn dump(args: Args) -> Result {
+    let files = args.operands(0, 1)?;
+    let input = read(files.first().map(String::as_str))?;
+
+    let mut out = stdout();
+    out.write_all(inspect::annotate(&input).as_bytes())?;
+    out.flush()?;
+    Ok(true)
+}
+
+fn validate(mut args: Args) -> Result {
+    let rules = if args.flag("--strict-keys") {
+        KeyRules::strict()
+    } else {
+        KeyRules::new()
+    };
+    let files = args.operands(0, 1)?;
+    let input = read(files.first().map(String::as_str))?;
+
+    let mut documents = documents(&input);
+    documents.strict = true;
+
+    let mut count = 0;
+    let mut failed = 0;
+    for map in documents {
+        count += 1;
+
+        let result = map.and_then(|map| {
+            let mut path = String::new();
+            check_keys(&map, &rules, &mut path)
+                .map_err(|err| format!("document {}: `{}`: {}", count - 1, path, err).into())
+        });
+
+        if let Err(err) = result {
+            println!("{}", err);
+            failed += 1;
+        }
+    }
+
+    if failed == 0 {
+        println!("{} documents ok", count);
+    }
+
+    Ok(failed == 0)
+}
+
+/// Check every key, leaving `path` at the first bad one.
+fn check_keys(
+    map: &Map,
+    rules: &KeyRules,
+    path: &mut String,
+) -> std::result::Result<(), nson::key::KeyError> {
+    for (key, value) in map {
+        let len = push(path, key);
+        rules.check(key)?;
+        check_value_keys(value, rules, path)?;
+        path.truncate(len);
+    }
+
+    Ok(())
+}
+
+fn check_value_keys(
+    value: &Value,
+    rules: &KeyRules,
+    path: &mut String,
+) -> std::result::Result<(), nson::key::KeyError> {
+    match value {
+        Value::Map(map) => check_keys(map, rules, path),
+        Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                let len = push(path, &i.to_string());
+                check_value_keys(value, rules, path)?;
+                path.truncate(len);
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
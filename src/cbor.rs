@@ -0,0 +1,830 @@
+//! Cbor
+//!
+//! With the `cbor` feature, a `Value` converts to `ciborium::Value` and back,
+//! and [`nson_to_cbor`] and [`cbor_to_nson`] convert encoded bytes. Every
+//! type has a fixed mapping:
+//!
+//! | NSON                          | CBOR                                      |
+//! |-------------------------------|-------------------------------------------|
+//! | `Null`, `Bool`                | null, true and false                      |
+//! | `String`, `Binary`            | text string, byte string                  |
+//! | `Array`, `Map`                | array, map with text keys                 |
+//! | `I8` to `U64`                 | integer                                   |
+//! | `F32`, `F64`                  | float, in the shortest width that is exact |
+//! | `TimeStamp`                   | tag 1 on an integer, epoch seconds        |
+//! | `Id`                          | tag [`ID_TAG`] on a 12 byte string        |
+//!
+//! An untagged integer reads back as `I32` when it fits, as `I64` or `U64`
+//! otherwise, and an untagged float as `F32` when that is exact, as `F64`
+//! otherwise, like plain JSON numbers. [`CborMode::Typed`], the default,
+//! tags the other widths with [`type_tag`] so that every value reads back
+//! the same. [`CborMode::Plain`] leaves those tags out, for peers that only
+//! want the numbers.
+//!
+//! Reading also takes tag 0 date strings as `TimeStamp`. CBOR that NSON can
+//! not express is an error rather than being changed: map keys that are not
+//! text, bignums, integers below `i64::MIN`, undefined and other simple
+//! values, and tags not listed here.
+//!
+//! ```
+//! use nson::cbor::{self, CborMode};
+//! use nson::{Map, TimeStamp, m};
+//!
+//! let map = m! {"temp": 21.5f32, "at": TimeStamp(1732694400), "n": 3u8};
+//!
+//! let value = ciborium::Value::from(map.clone());
+//! assert_eq!(Map::try_from(value).unwrap(), map);
+//!
+//! let bytes = cbor::nson_to_cbor(&map.to_bytes().unwrap(), CborMode::Plain).unwrap();
+//! let back = Map::from_bytes(&cbor::cbor_to_nson(&bytes).unwrap()).unwrap();
+//! assert_eq!(back, m! {"temp": 21.5f32, "at": TimeStamp(1732694400), "n": 3});
+//! ```
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use ciborium::value::{Integer, Value as Cbor};
+use ciborium_ll::tag::{BIGNEG, BIGPOS};
+use ciborium_ll::{Decoder, Header, simple};
+
+use crate::array::Array;
+use crate::decode::{self, DecodeError};
+use crate::encode::{self, EncodeError};
+use crate::id::Id;
+use crate::map::Map;
+use crate::spec::DataType;
+use crate::value::{TimeStamp, Value};
+
+pub use crate::json_text::{Error, Result};
+
+/// Standard date and time string, read as `TimeStamp`.
+const DATE_TAG: u64 = 0;
+
+/// Standard epoch time, used for `TimeStamp`.
+pub const TIMESTAMP_TAG: u64 = 1;
+
+/// First of the tags naming an NSON type, in the first come first served
+/// range of the CBOR tag registry. These tags are not registered.
+pub const TYPE_TAG_BASE: u64 = 0x4e53_0000;
+
+const TYPE_TAG_END: u64 = TYPE_TAG_BASE + 0xff;
+
+/// Tag on the 12 byte string of an `Id`.
+pub const ID_TAG: u64 = type_tag(DataType::Id);
+
+/// Nesting allowed when reading CBOR bytes, where each level can cost a
+/// single byte.
+const MAX_DEPTH: usize = 256;
+
+/// The tag naming `data_type`, [`TYPE_TAG_BASE`] plus its NSON type byte.
+pub const fn type_tag(data_type: DataType) -> u64 {
+    TYPE_TAG_BASE + data_type as u64
+}
+
+/// How numbers are written as CBOR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CborMode {
+    /// Tag every number that would not read back as the same type, such as
+    /// `U8`, `I64` or `F64`.
+    #[default]
+    Typed,
+    /// Write numbers without tags, losing their width. `TimeStamp` and `Id`
+    /// are still tagged.
+    Plain,
+}
+
+/// Error returned by [`nson_to_cbor`] and [`cbor_to_nson`].
+#[derive(Debug)]
+pub enum TranscodeError {
+    /// The NSON input is malformed.
+    Decode(DecodeError),
+    /// The CBOR input can not be written as NSON, such as an empty key.
+    Encode(EncodeError),
+    /// The CBOR input is malformed, offsets count from the start of it.
+    Syntax {
+        offset: usize,
+        message: &'static str,
+    },
+    /// The CBOR input holds something NSON can not express.
+    Cbor(Error),
+}
+
+pub type TranscodeResult<T> = core::result::Result<T, TranscodeError>;
+
+impl TranscodeError {
+    fn within(self, segment: &str) -> TranscodeError {
+        match self {
+            TranscodeError::Cbor(err) => TranscodeError::Cbor(err.within(segment)),
+            err => err,
+        }
+    }
+}
+
+impl From<DecodeError> for TranscodeError {
+    fn from(err: DecodeError) -> TranscodeError {
+        TranscodeError::Decode(err)
+    }
+}
+
+impl From<EncodeError> for TranscodeError {
+    fn from(err: EncodeError) -> TranscodeError {
+        TranscodeError::Encode(err)
+    }
+}
+
+impl From<Error> for TranscodeError {
+    fn from(err: Error) -> TranscodeError {
+        TranscodeError::Cbor(err)
+    }
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TranscodeError::Decode(ref inner) => inner.fmt(fmt),
+            TranscodeError::Encode(ref inner) => inner.fmt(fmt),
+            TranscodeError::Syntax { offset, message } => {
+                write!(fmt, "{} at offset {}", message, offset)
+            }
+            TranscodeError::Cbor(ref inner) => inner.fmt(fmt),
+        }
+    }
+}
+
+impl core::error::Error for TranscodeError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match *self {
+            TranscodeError::Decode(ref inner) => Some(inner),
+            TranscodeError::Encode(ref inner) => Some(inner),
+            TranscodeError::Syntax { .. } => None,
+            TranscodeError::Cbor(ref inner) => Some(inner),
+        }
+    }
+}
+
+/// A short description of `value` for error messages.
+fn describe(value: &Cbor) -> String {
+    match value {
+        Cbor::Integer(i) => i128::from(*i).to_string(),
+        Cbor::Float(f) => format!("{:?}", f),
+        Cbor::Bytes(b) => format!("{} bytes", b.len()),
+        Cbor::Text(s) => format!("{:?}", s),
+        Cbor::Bool(b) => b.to_string(),
+        Cbor::Null => "null".into(),
+        Cbor::Tag(t, _) => format!("tag {}", t),
+        Cbor::Array(_) => "array".into(),
+        Cbor::Map(_) => "map".into(),
+        _ => "value".into(),
+    }
+}
+
+fn to_cbor(value: &Value, mode: CborMode) -> Cbor {
+    let typed = |data_type, value| match mode {
+        CborMode::Typed => Cbor::Tag(type_tag(data_type), Box::new(value)),
+        CborMode::Plain => value,
+    };
+
+    match *value {
+        Value::F32(v) => Cbor::Float(v as f64),
+        Value::F64(v) => typed(DataType::F64, Cbor::Float(v)),
+        Value::I32(v) => Cbor::Integer(v.into()),
+        Value::I64(v) => typed(DataType::I64, Cbor::Integer(v.into())),
+        Value::U32(v) => typed(DataType::U32, Cbor::Integer(v.into())),
+        Value::U64(v) => typed(DataType::U64, Cbor::Integer(v.into())),
+        Value::I8(v) => typed(DataType::I8, Cbor::Integer(v.into())),
+        Value::U8(v) => typed(DataType::U8, Cbor::Integer(v.into())),
+        Value::I16(v) => typed(DataType::I16, Cbor::Integer(v.into())),
+        Value::U16(v) => typed(DataType::U16, Cbor::Integer(v.into())),
+        Value::String(ref v) => Cbor::Text(v.clone()),
+        Value::Array(ref v) => Cbor::Array(v.iter().map(|v| to_cbor(v, mode)).collect()),
+        Value::Map(ref v) => to_cbor_map(v, mode),
+        Value::Bool(v) => Cbor::Bool(v),
+        Value::Null => Cbor::Null,
+        Value::Binary(ref v) => Cbor::Bytes(v.0.clone()),
+        Value::TimeStamp(v) => Cbor::Tag(TIMESTAMP_TAG, Box::new(Cbor::Integer(v.0.into()))),
+        Value::Id(ref v) => Cbor::Tag(ID_TAG, Box::new(Cbor::Bytes(v.bytes().to_vec()))),
+    }
+}
+
+fn to_cbor_map(map: &Map, mode: CborMode) -> Cbor {
+    Cbor::Map(
+        map.iter()
+            .map(|(k, v)| (Cbor::Text(k.clone()), to_cbor(v, mode)))
+            .collect(),
+    )
+}
+
+/// An untagged integer, in the narrowest type that holds it.
+fn integer(i: Integer) -> Result<Value> {
+    let i = i128::from(i);
+
+    if let Ok(i) = i32::try_from(i) {
+        Ok(Value::I32(i))
+    } else if let Ok(i) = i64::try_from(i) {
+        Ok(Value::I64(i))
+    } else if let Ok(u) = u64::try_from(i) {
+        Ok(Value::U64(u))
+    } else {
+        Err(Error::OutOfRange {
+            path: String::new(),
+            expected: DataType::I64,
+            found: i.to_string(),
+        })
+    }
+}
+
+/// An untagged float, as `F32` when that is exact.
+fn float(f: f64) -> Value {
+    if f.is_nan() || (f as f32) as f64 == f {
+        Value::F32(f as f32)
+    } else {
+        Value::F64(f)
+    }
+}
+
+/// Read the value under tag `tag`.
+fn tagged(tag: u64, value: Cbor) -> Result<Value> {
+    let invalid = |expected| Error::InvalidValue {
+        path: String::new(),
+        expected,
+        found: describe(&value),
+    };
+
+    let out_of_range = |expected| Error::OutOfRange {
+        path: String::new(),
+        expected,
+        found: describe(&value),
+    };
+
+    macro_rules! integer {
+        ($variant:ident) => {
+            match value {
+                Cbor::Integer(i) => i128::from(i)
+                    .try_into()
+                    .map(Value::$variant)
+                    .map_err(|_| out_of_range(DataType::$variant)),
+                _ => Err(invalid(DataType::$variant)),
+            }
+        };
+    }
+
+    let data_type = match tag {
+        BIGPOS | BIGNEG => return Err(Error::unsupported("bignum".into())),
+        DATE_TAG => {
+            return match value {
+                Cbor::Text(ref s) => TimeStamp::from_rfc3339(s)
+                    .map(Value::TimeStamp)
+                    .ok_or_else(|| invalid(DataType::TimeStamp)),
+                _ => Err(invalid(DataType::TimeStamp)),
+            };
+        }
+        TIMESTAMP_TAG => DataType::TimeStamp,
+        TYPE_TAG_BASE..=TYPE_TAG_END => match DataType::from((tag - TYPE_TAG_BASE) as u8) {
+            Some(data_type) => data_type,
+            None => return Err(Error::unsupported(format!("tag {}", tag))),
+        },
+        _ => return Err(Error::unsupported(format!("tag {}", tag))),
+    };
+
+    match data_type {
+        DataType::F32 => match value {
+            Cbor::Float(f) => {
+                let v = f as f32;
+                if f.is_finite() && !v.is_finite() {
+                    Err(out_of_range(DataType::F32))
+                } else {
+                    Ok(Value::F32(v))
+                }
+            }
+            _ => Err(invalid(DataType::F32)),
+        },
+        DataType::F64 => match value {
+            Cbor::Float(f) => Ok(Value::F64(f)),
+            _ => Err(invalid(DataType::F64)),
+        },
+        DataType::I32 => integer!(I32),
+        DataType::I64 => integer!(I64),
+        DataType::U32 => integer!(U32),
+        DataType::U64 => integer!(U64),
+        DataType::I8 => integer!(I8),
+        DataType::U8 => integer!(U8),
+        DataType::I16 => integer!(I16),
+        DataType::U16 => integer!(U16),
+        DataType::TimeStamp => match value {
+            Cbor::Integer(i) => u64::try_from(i)
+                .map(|u| Value::TimeStamp(u.into()))
+                .map_err(|_| out_of_range(DataType::TimeStamp)),
+            _ => Err(invalid(DataType::TimeStamp)),
+        },
+        DataType::Id => match value {
+            Cbor::Bytes(ref b) => <[u8; 12]>::try_from(b.as_slice())
+                .map(|b| Value::Id(Id::with_bytes(b)))
+                .map_err(|_| invalid(DataType::Id)),
+            _ => Err(invalid(DataType::Id)),
+        },
+        _ => Err(Error::unsupported(format!("tag {}", tag))),
+    }
+}
+
+fn from_cbor(cbor: Cbor) -> Result<Value> {
+    let value = match cbor {
+        Cbor::Integer(i) => integer(i)?,
+        Cbor::Float(f) => float(f),
+        Cbor::Bytes(v) => v.into(),
+        Cbor::Text(v) => v.into(),
+        Cbor::Bool(v) => v.into(),
+        Cbor::Null => Value::Null,
+        Cbor::Tag(tag, value) => tagged(tag, *value)?,
+        Cbor::Array(v) => {
+            let array = v
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| from_cbor(v).map_err(|e| e.within(&i.to_string())))
+                .collect::<Result<Vec<Value>>>()?;
+
+            Value::Array(Array::from_vec(array))
+        }
+        Cbor::Map(entries) => {
+            let mut map = Map::with_capacity(entries.len());
+            for (key, value) in entries {
+                let Cbor::Text(key) = key else {
+                    return Err(Error::unsupported(format!("map key {}", describe(&key))));
+                };
+
+                let value = from_cbor(value).map_err(|e| e.within(&key))?;
+                map.insert(key, value);
+            }
+
+            Value::Map(map)
+        }
+        ref other => return Err(Error::unsupported(describe(other))),
+    };
+
+    Ok(value)
+}
+
+impl Value {
+    /// Convert to CBOR, tagging numbers as `mode` says. `CborMode::Typed`
+    /// is the same as `From`.
+    pub fn to_cbor_with(&self, mode: CborMode) -> Cbor {
+        to_cbor(self, mode)
+    }
+}
+
+impl From<Value> for Cbor {
+    fn from(value: Value) -> Self {
+        to_cbor(&value, CborMode::Typed)
+    }
+}
+
+impl TryFrom<Cbor> for Value {
+    type Error = Error;
+
+    fn try_from(cbor: Cbor) -> Result<Value> {
+        from_cbor(cbor)
+    }
+}
+
+impl Map {
+    /// See [`Value::to_cbor_with`].
+    pub fn to_cbor_with(&self, mode: CborMode) -> Cbor {
+        to_cbor_map(self, mode)
+    }
+}
+
+impl From<Map> for Cbor {
+    fn from(map: Map) -> Self {
+        to_cbor_map(&map, CborMode::Typed)
+    }
+}
+
+impl TryFrom<Cbor> for Map {
+    type Error = Error;
+
+    fn try_from(cbor: Cbor) -> Result<Map> {
+        match from_cbor(cbor)? {
+            Value::Map(map) => Ok(map),
+            value => Err(Error::InvalidValue {
+                path: String::new(),
+                expected: DataType::Map,
+                found: format!("{:?}", value.element_type()),
+            }),
+        }
+    }
+}
+
+/// Convert a sequence of NSON maps, as written by `Map::to_bytes`, to a
+/// sequence of CBOR maps.
+pub fn nson_to_cbor(bytes: &[u8], mode: CborMode) -> TranscodeResult<Vec<u8>> {
+    let mut reader = bytes;
+    let mut out = Vec::with_capacity(bytes.len());
+
+    while !reader.is_empty() {
+        let map = decode::decode_map(&mut reader)?;
+        ciborium::into_writer(&to_cbor_map(&map, mode), &mut out)
+            .expect("a CBOR value always writes into a Vec");
+    }
+
+    Ok(out)
+}
+
+/// Convert a sequence of CBOR maps to a sequence of NSON maps.
+///
+/// Bignums are an error here even when they are small, rather than being
+/// read as integers.
+pub fn cbor_to_nson(bytes: &[u8]) -> TranscodeResult<Vec<u8>> {
+    let mut reader = CborReader {
+        decoder: Decoder::from(bytes),
+        len: bytes.len(),
+    };
+    let mut out = Vec::with_capacity(bytes.len());
+
+    while reader.decoder.offset() < bytes.len() {
+        let map = Map::try_from(reader.item(0)?)?;
+        encode::encode_map(&mut out, &map)?;
+    }
+
+    Ok(out)
+}
+
+/// Reads CBOR items keeping every tag, which `ciborium::from_reader` does
+/// not do for small bignums.
+struct CborReader<'a> {
+    decoder: Decoder<&'a [u8]>,
+    len: usize,
+}
+
+impl CborReader<'_> {
+    fn syntax(&mut self, message: &'static str) -> TranscodeError {
+        TranscodeError::Syntax {
+            offset: self.decoder.offset(),
+            message,
+        }
+    }
+
+    fn pull(&mut self) -> TranscodeResult<Header> {
+        let len = self.len;
+        self.decoder.pull().map_err(|err| syntax(err, len))
+    }
+
+    fn item(&mut self, depth: usize) -> TranscodeResult<Cbor> {
+        if depth > MAX_DEPTH {
+            return Err(self.syntax("nested too deeply"));
+        }
+
+        let value = match self.pull()? {
+            Header::Positive(u) => Cbor::Integer(u.into()),
+            // the bits of a negative integer are inverted, -1 is 0
+            Header::Negative(u) => Cbor::Integer(
+                Integer::try_from(u as i128 ^ !0).expect("CBOR integers fit an Integer"),
+            ),
+            Header::Float(f) => Cbor::Float(f),
+            Header::Simple(simple::FALSE) => Cbor::Bool(false),
+            Header::Simple(simple::TRUE) => Cbor::Bool(true),
+            Header::Simple(simple::NULL) => Cbor::Null,
+            Header::Simple(simple::UNDEFINED) => {
+                return Err(Error::unsupported("undefined".into()).into());
+            }
+            Header::Simple(x) => {
+                return Err(Error::unsupported(format!("simple value {}", x)).into());
+            }
+            Header::Tag(tag) => Cbor::Tag(tag, Box::new(self.item(depth + 1)?)),
+            Header::Break => return Err(self.syntax("unexpected break")),
+            Header::Bytes(len) => Cbor::Bytes(self.bytes(len)?),
+            Header::Text(len) => Cbor::Text(self.text(len)?),
+            Header::Array(len) => {
+                let mut array = Vec::new();
+                while self.more(len, array.len())? {
+                    let value = self
+                        .item(depth + 1)
+                        .map_err(|e| e.within(&array.len().to_string()))?;
+                    array.push(value);
+                }
+
+                Cbor::Array(array)
+            }
+            Header::Map(len) => {
+                let mut entries = Vec::new();
+                while self.more(len, entries.len())? {
+                    let key = self.item(depth + 1)?;
+                    let value = match key {
+                        Cbor::Text(ref key) => self.item(depth + 1).map_err(|e| e.within(key))?,
+                        _ => self.item(depth + 1)?,
+                    };
+                    entries.push((key, value));
+                }
+
+                Cbor::Map(entries)
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Whether an array or a map of `len` items, `None` for one ended by a
+    /// break, has more after `read`.
+    fn more(&mut self, len: Option<usize>, read: usize) -> TranscodeResult<bool> {
+        match len {
+            Some(len) => Ok(read < len),
+            None => match self.pull()? {
+                Header::Break => Ok(false),
+                header => {
+                    self.decoder.push(header);
+                    Ok(true)
+                }
+            },
+        }
+    }
+
+    fn bytes(&mut self, len: Option<usize>) -> TranscodeResult<Vec<u8>> {
+        let mut buffer = [0u8; 4096];
+        let mut out = Vec::new();
+
+        let mut segments = self.decoder.bytes(len);
+        while let Some(mut segment) = segments.pull().map_err(|e| syntax(e, self.len))? {
+            while let Some(chunk) = segment.pull(&mut buffer).map_err(|e| syntax(e, self.len))? {
+                out.extend_from_slice(chunk);
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn text(&mut self, len: Option<usize>) -> TranscodeResult<String> {
+        let mut buffer = [0u8; 4096];
+        let mut out = String::new();
+
+        let mut segments = self.decoder.text(len);
+        while let Some(mut segment) = segments.pull().map_err(|e| syntax(e, self.len))? {
+            while let Some(chunk) = segment.pull(&mut buffer).map_err(|e| syntax(e, self.len))? {
+                out.push_str(chunk);
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// A read error, running out of input shows as an error at its end.
+fn syntax<T>(err: ciborium_ll::Error<T>, len: usize) -> TranscodeError {
+    match err {
+        ciborium_ll::Error::Io(_) => TranscodeError::Syntax {
+            offset: len,
+            message: "unexpected end of input",
+        },
+        ciborium_ll::Error::Syntax(offset) => TranscodeError::Syntax {
+            offset,
+            message: "malformed CBOR",
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use ciborium::value::Value as Cbor;
+
+    use super::{CborMode, Error, TranscodeError, cbor_to_nson, nson_to_cbor, type_tag};
+    use crate::spec::DataType;
+    use crate::{Binary, Id, Map, TimeStamp, Value, m};
+
+    use crate::vec as __vec;
+
+    fn text(s: &str) -> Cbor {
+        Cbor::Text(s.into())
+    }
+
+    #[test]
+    fn convert_cbor() {
+        let id = Id::with_string("0171253e54db9aef760d5fbd").unwrap();
+        let map = m! {
+            "a": 1i32,
+            "b": -2i64,
+            "c": 3u32,
+            "d": u64::MAX,
+            "e": 5.6f32,
+            "f": 7.8f64,
+            "g": TimeStamp(456),
+            "h": id,
+            "i": Binary(__vec![1, 2, 3]),
+            "j": [-1i8, 2u8, -3i16, 4u16],
+            "k": {"s": "x", "n": null, "b": true},
+            "l": f32::NAN,
+        };
+
+        let cbor = Cbor::from(map.clone());
+        let entries = cbor.as_map().unwrap();
+        assert_eq!(entries[0], (text("a"), Cbor::Integer(1.into())));
+        assert_eq!(
+            entries[2].1,
+            Cbor::Tag(type_tag(DataType::U32), Box::new(Cbor::Integer(3.into())))
+        );
+        assert_eq!(entries[4].1, Cbor::Float(5.6f32 as f64));
+        assert_eq!(
+            entries[6].1,
+            Cbor::Tag(1, Box::new(Cbor::Integer(456.into())))
+        );
+        assert_eq!(
+            entries[7].1,
+            Cbor::Tag(0x4e53_0042, Box::new(Cbor::Bytes(id.bytes().to_vec())))
+        );
+
+        let back = Map::try_from(cbor).unwrap();
+        assert!(matches!(back.get("l"), Some(Value::F32(f)) if f.is_nan()));
+        let mut back = back;
+        let mut map = map;
+        back.remove("l");
+        map.remove("l");
+        assert_eq!(back, map);
+    }
+
+    #[test]
+    fn plain() {
+        let map = m! {
+            "a": 1u8,
+            "b": 0.5f64,
+            "c": 0.1f64,
+            "d": -5_000_000_000i64,
+            "e": 5_000_000_000u64,
+            "f": TimeStamp(7),
+        };
+
+        let back = Map::try_from(map.to_cbor_with(CborMode::Plain)).unwrap();
+        assert_eq!(
+            back,
+            m! {
+                "a": 1,
+                "b": 0.5f32,
+                "c": 0.1f64,
+                "d": -5_000_000_000i64,
+                "e": 5_000_000_000i64,
+                "f": TimeStamp(7),
+            }
+        );
+
+        let date = Cbor::Tag(0, Box::new(text("1970-01-01T00:00:07Z")));
+        assert_eq!(Value::try_from(date).unwrap(), TimeStamp(7).into());
+    }
+
+    #[test]
+    fn errors() {
+        let cases: Vec<(Cbor, Error)> = __vec![
+            (
+                Cbor::Map(__vec![(Cbor::Integer(1.into()), Cbor::Null)]),
+                Error::Unsupported {
+                    path: "".into(),
+                    found: "map key 1".into(),
+                },
+            ),
+            (
+                Cbor::Map(__vec![(
+                    text("a"),
+                    Cbor::Array(__vec![Cbor::Tag(2, Box::new(Cbor::Bytes(__vec![1])))])
+                )]),
+                Error::Unsupported {
+                    path: "a.0".into(),
+                    found: "bignum".into(),
+                },
+            ),
+            (
+                Cbor::Tag(type_tag(DataType::U8), Box::new(Cbor::Integer(300.into())),),
+                Error::OutOfRange {
+                    path: "".into(),
+                    expected: DataType::U8,
+                    found: "300".into(),
+                },
+            ),
+            (
+                Cbor::Tag(type_tag(DataType::I16), Box::new(text("1"))),
+                Error::InvalidValue {
+                    path: "".into(),
+                    expected: DataType::I16,
+                    found: "\"1\"".into(),
+                },
+            ),
+            (
+                Cbor::Tag(type_tag(DataType::Id), Box::new(Cbor::Bytes(__vec![0; 11]))),
+                Error::InvalidValue {
+                    path: "".into(),
+                    expected: DataType::Id,
+                    found: "11 bytes".into(),
+                },
+            ),
+            (
+                Cbor::Tag(1, Box::new(Cbor::Integer((-1).into()))),
+                Error::OutOfRange {
+                    path: "".into(),
+                    expected: DataType::TimeStamp,
+                    found: "-1".into(),
+                },
+            ),
+            (
+                Cbor::Integer((-(1i128 << 64)).try_into().unwrap()),
+                Error::OutOfRange {
+                    path: "".into(),
+                    expected: DataType::I64,
+                    found: "-18446744073709551616".into(),
+                },
+            ),
+            (
+                Cbor::Tag(type_tag(DataType::String), Box::new(text("x"))),
+                Error::Unsupported {
+                    path: "".into(),
+                    found: "tag 1314062369".into(),
+                },
+            ),
+            (
+                Cbor::Tag(37, Box::new(Cbor::Bytes(__vec![0; 16]))),
+                Error::Unsupported {
+                    path: "".into(),
+                    found: "tag 37".into(),
+                },
+            ),
+        ];
+
+        for (cbor, err) in cases {
+            assert_eq!(Value::try_from(cbor), Err(err));
+        }
+
+        let err = Map::try_from(Cbor::Array(__vec![])).unwrap_err();
+        assert_eq!(err.to_string(), "invalid value, expected Map, found Array");
+    }
+
+    #[test]
+    fn transcode() {
+        let mut nson = m! {"id": 1u8, "t": TimeStamp(5)}.to_bytes().unwrap();
+        nson.extend(m! {"list": [1, "a"]}.to_bytes().unwrap());
+
+        for mode in [CborMode::Typed, CborMode::Plain] {
+            let cbor = nson_to_cbor(&nson, mode).unwrap();
+            let back = cbor_to_nson(&cbor).unwrap();
+
+            let mut reader = back.as_slice();
+            let first = crate::decode::decode_map(&mut reader).unwrap();
+            let second = crate::decode::decode_map(&mut reader).unwrap();
+            assert!(reader.is_empty());
+            assert_eq!(second, m! {"list": [1, "a"]});
+
+            match mode {
+                CborMode::Typed => assert_eq!(back, nson),
+                CborMode::Plain => assert_eq!(first, m! {"id": 1, "t": TimeStamp(5)}),
+            }
+        }
+
+        // {"s": (_ "ab" "c"), "l": [_ 1, 2]}
+        let cbor = [
+            0xbf, 0x61, 0x73, 0x7f, 0x62, 0x61, 0x62, 0x61, 0x63, 0xff, 0x61, 0x6c, 0x9f, 0x01,
+            0x02, 0xff, 0xff,
+        ];
+        let nson = cbor_to_nson(&cbor).unwrap();
+        assert_eq!(
+            Map::from_bytes(&nson).unwrap(),
+            m! {"s": "abc", "l": [1, 2]}
+        );
+    }
+
+    #[test]
+    fn transcode_errors() {
+        let message = |bytes: &[u8]| cbor_to_nson(bytes).unwrap_err().to_string();
+
+        // a bignum small enough for an integer is still a bignum
+        assert_eq!(
+            message(&[0xa1, 0x61, 0x61, 0xc2, 0x41, 0x01]),
+            "a: unsupported bignum"
+        );
+        assert_eq!(
+            message(&[0xa1, 0x61, 0x75, 0xf7]),
+            "u: unsupported undefined"
+        );
+        assert_eq!(message(&[0x80]), "invalid value, expected Map, found Array");
+        assert!(matches!(
+            cbor_to_nson(&[0xa1, 0x61, 0x61]),
+            Err(TranscodeError::Syntax {
+                offset: 3,
+                message: "unexpected end of input"
+            })
+        ));
+        assert!(matches!(
+            cbor_to_nson(&[0xa1, 0x60, 0x01]),
+            Err(TranscodeError::Encode(_))
+        ));
+
+        let mut deep = __vec![0xa1, 0x61, 0x64];
+        deep.extend([0x81; 300]);
+        deep.push(0x00);
+        assert_eq!(message(&deep), "nested too deeply at offset 259");
+
+        assert!(matches!(
+            nson_to_cbor(&[5, 0, 0], CborMode::Typed),
+            Err(TranscodeError::Decode(_))
+        ));
+        assert!(nson_to_cbor(&[], CborMode::Typed).unwrap().is_empty());
+        assert!(cbor_to_nson(&[]).unwrap().is_empty());
+    }
+}
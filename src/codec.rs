@@ -0,0 +1,989 @@
+//! Codec
+//!
+//! [`ToNson`] and [`FromNson`] encode and decode Rust types directly against
+//! a reader or writer, without building a [`Value`] tree and without serde.
+//! They are usually derived with the `derive` feature:
+//!
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use nson::{FromNson, Id, ToNson, codec};
+//!
+//! #[derive(ToNson, FromNson, Debug, PartialEq)]
+//! struct Device {
+//!     #[nson(rename = "_id")]
+//!     id: Id,
+//!     name: String,
+//!     #[nson(default)]
+//!     tags: Vec<String>,
+//!     #[nson(timestamp)]
+//!     seen: u64,
+//! }
+//!
+//! let device = Device {
+//!     id: Id::with_bytes([1; 12]),
+//!     name: "sensor".into(),
+//!     tags: vec![],
+//!     seen: 1_700_000_000,
+//! };
+//!
+//! let bytes = codec::to_vec(&device).unwrap();
+//! assert_eq!(codec::from_slice::<Device>(&bytes).unwrap(), device);
+//! # }
+//! ```
+//!
+//! Structs are encoded as maps. Integer targets accept any numeric tag whose
+//! value fits, like [`FromValue`](crate::FromValue) does, and `Vec<T>` is an
+//! `Array`; use `Binary` or the `binary` attribute for bytes.
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::BuildHasher;
+#[cfg(feature = "std")]
+use std::io::{Cursor, Read, Write};
+
+#[cfg(not(feature = "std"))]
+use crate::io::{Cursor, Read, Write};
+
+use crate::array::Array;
+use crate::decode::{self, DecodeError, DecodeResult};
+use crate::encode::{self, EncodeError, EncodeResult};
+use crate::id::Id;
+use crate::map::{Error, Map};
+use crate::number::Number;
+use crate::spec::DataType;
+use crate::value::{Binary, TimeStamp, Value};
+
+use __private::{field_size, within, write_field};
+
+/// A type that can be written as an NSON value.
+pub trait ToNson {
+    /// The tag written before the payload.
+    fn nson_type(&self) -> DataType;
+
+    /// The size of the payload in bytes, without the tag.
+    fn nson_size(&self) -> usize;
+
+    /// Write the payload, without the tag.
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()>;
+}
+
+/// A type that can be read from an NSON value.
+pub trait FromNson: Sized {
+    /// Read the payload of a value tagged `tag`.
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self>;
+
+    /// The value of a missing struct field, `None` makes it an error.
+    fn nson_missing() -> Option<Self> {
+        None
+    }
+}
+
+/// A type that is written as the entries of a map. This is what
+/// `#[nson(flatten)]` requires.
+pub trait ToNsonMap {
+    /// The size of the entries, without the length prefix and terminator.
+    fn entries_size(&self) -> usize;
+
+    fn write_entries<W: Write>(&self, writer: &mut W) -> EncodeResult<()>;
+}
+
+/// A type that is read from the entries of a map, one entry at a time.
+pub trait FromNsonMap: Sized {
+    /// The state collected while reading the entries.
+    type Builder: Default;
+
+    /// Read the entry `key` if it belongs to this type. Returns `false`,
+    /// without reading the payload, if it does not.
+    fn read_entry<R: Read>(
+        builder: &mut Self::Builder,
+        key: &str,
+        tag: DataType,
+        reader: &mut R,
+    ) -> DecodeResult<bool>;
+
+    fn build(builder: Self::Builder) -> DecodeResult<Self>;
+}
+
+/// Encode a map type to bytes, the same as [`Map::to_bytes`] would.
+pub fn to_vec<T: ToNsonMap + ?Sized>(value: &T) -> EncodeResult<Vec<u8>> {
+    let mut buf = Vec::with_capacity(map_size(value));
+    to_writer(&mut buf, value)?;
+    Ok(buf)
+}
+
+/// Encode a map type to a writer.
+pub fn to_writer<W: Write, T: ToNsonMap + ?Sized>(writer: &mut W, value: &T) -> EncodeResult<()> {
+    let len = map_size(value);
+
+    if len > crate::MAX_NSON_SIZE as usize {
+        return Err(EncodeError::InvalidValueLen(
+            len,
+            "map len must < MAX_NSON_SIZE".into(),
+        ));
+    }
+
+    encode::write_u32(writer, len as u32)?;
+    value.write_entries(writer)?;
+    writer.write_all(&[0])?;
+    Ok(())
+}
+
+/// Decode a map type from bytes.
+pub fn from_slice<T: FromNsonMap>(slice: &[u8]) -> DecodeResult<T> {
+    let mut reader = Cursor::new(slice);
+    from_reader(&mut reader)
+}
+
+/// Decode a map type from a reader.
+pub fn from_reader<R: Read, T: FromNsonMap>(reader: &mut R) -> DecodeResult<T> {
+    decode::read_map_len(reader)?;
+
+    let mut builder = T::Builder::default();
+
+    while let Some(key) = decode::read_key(reader)? {
+        let tag = decode::read_tag(reader)?;
+
+        if !T::read_entry(&mut builder, &key, tag, reader)? {
+            decode::skip_value(reader, tag)?;
+        }
+    }
+
+    T::build(builder)
+}
+
+fn map_size<T: ToNsonMap + ?Sized>(value: &T) -> usize {
+    4 + value.entries_size() + 1
+}
+
+fn unexpected(expected: DataType, found: DataType) -> DecodeError {
+    DecodeError::Field(Error::UnexpectedType {
+        key: String::new(),
+        expected,
+        found,
+    })
+}
+
+fn expect(expected: DataType, found: DataType) -> DecodeResult<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(unexpected(expected, found))
+    }
+}
+
+fn read_number<R: Read>(reader: &mut R, expected: DataType, tag: DataType) -> DecodeResult<Number> {
+    Ok(match tag {
+        DataType::F32 => Number::F32(decode::read_f32(reader)?),
+        DataType::F64 => Number::F64(decode::read_f64(reader)?),
+        DataType::I32 => Number::I32(decode::read_i32(reader)?),
+        DataType::I64 => Number::I64(decode::read_i64(reader)?),
+        DataType::U32 => Number::U32(decode::read_u32(reader)?),
+        DataType::U64 => Number::U64(decode::read_u64(reader)?),
+        DataType::I8 => Number::I8(decode::read_i8(reader)?),
+        DataType::U8 => Number::U8(decode::read_u8(reader)?),
+        DataType::I16 => Number::I16(decode::read_i16(reader)?),
+        DataType::U16 => Number::U16(decode::read_u16(reader)?),
+        _ => return Err(unexpected(expected, tag)),
+    })
+}
+
+macro_rules! number_impls {
+    ($($T:ident => $write:ident, $to:ident, $V:ident, $size:expr;)+) => {
+        $(
+            impl ToNson for $T {
+                fn nson_type(&self) -> DataType {
+                    DataType::$V
+                }
+
+                fn nson_size(&self) -> usize {
+                    $size
+                }
+
+                fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+                    encode::$write(writer, *self)
+                }
+            }
+
+            impl FromNson for $T {
+                fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+                    let number = read_number(reader, DataType::$V, tag)?;
+                    number.$to().ok_or_else(|| {
+                        DecodeError::Field(Error::OutOfRange {
+                            key: String::new(),
+                            expected: DataType::$V,
+                            found: tag,
+                        })
+                    })
+                }
+            }
+        )+
+    };
+}
+
+number_impls! {
+    i8 => write_i8, to_i8, I8, 1;
+    u8 => write_u8, to_u8, U8, 1;
+    i16 => write_i16, to_i16, I16, 2;
+    u16 => write_u16, to_u16, U16, 2;
+    i32 => write_i32, to_i32, I32, 4;
+    u32 => write_u32, to_u32, U32, 4;
+    i64 => write_i64, to_i64, I64, 8;
+    u64 => write_u64, to_u64, U64, 8;
+    f32 => write_f32, to_f32, F32, 4;
+    f64 => write_f64, to_f64, F64, 8;
+}
+
+impl ToNson for bool {
+    fn nson_type(&self) -> DataType {
+        DataType::Bool
+    }
+
+    fn nson_size(&self) -> usize {
+        1
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        encode::write_u8(writer, *self as u8)
+    }
+}
+
+impl FromNson for bool {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::Bool, tag)?;
+        Ok(decode::read_u8(reader)? != 0)
+    }
+}
+
+impl ToNson for str {
+    fn nson_type(&self) -> DataType {
+        DataType::String
+    }
+
+    fn nson_size(&self) -> usize {
+        4 + self.len()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        encode::write_string(writer, self)
+    }
+}
+
+impl ToNson for String {
+    fn nson_type(&self) -> DataType {
+        DataType::String
+    }
+
+    fn nson_size(&self) -> usize {
+        self.as_str().nson_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        self.as_str().write_nson(writer)
+    }
+}
+
+impl FromNson for String {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::String, tag)?;
+        decode::read_string(reader)
+    }
+}
+
+/// Borrowed bytes written as a `Binary`.
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryRef<'a>(pub &'a [u8]);
+
+impl ToNson for BinaryRef<'_> {
+    fn nson_type(&self) -> DataType {
+        DataType::Binary
+    }
+
+    fn nson_size(&self) -> usize {
+        4 + self.0.len()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        encode::write_bytes(writer, self.0)
+    }
+}
+
+impl ToNson for Binary {
+    fn nson_type(&self) -> DataType {
+        DataType::Binary
+    }
+
+    fn nson_size(&self) -> usize {
+        BinaryRef(&self.0).nson_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        BinaryRef(&self.0).write_nson(writer)
+    }
+}
+
+impl FromNson for Binary {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::Binary, tag)?;
+        decode::read_binary(reader)
+    }
+}
+
+impl ToNson for Id {
+    fn nson_type(&self) -> DataType {
+        DataType::Id
+    }
+
+    fn nson_size(&self) -> usize {
+        12
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        writer.write_all(&self.bytes()).map_err(From::from)
+    }
+}
+
+impl FromNson for Id {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::Id, tag)?;
+        let mut buf = [0; 12];
+        reader.read_exact(&mut buf)?;
+        Ok(Id::with_bytes(buf))
+    }
+}
+
+impl ToNson for TimeStamp {
+    fn nson_type(&self) -> DataType {
+        DataType::TimeStamp
+    }
+
+    fn nson_size(&self) -> usize {
+        8
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        encode::write_u64(writer, self.0)
+    }
+}
+
+impl FromNson for TimeStamp {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::TimeStamp, tag)?;
+        decode::read_u64(reader).map(TimeStamp)
+    }
+}
+
+impl ToNson for Value {
+    fn nson_type(&self) -> DataType {
+        self.element_type()
+    }
+
+    fn nson_size(&self) -> usize {
+        self.bytes_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        encode::encode_payload(writer, self)
+    }
+}
+
+impl FromNson for Value {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        decode::decode_value_with_tag(reader, tag as u8)
+    }
+
+    fn nson_missing() -> Option<Self> {
+        Some(Value::Null)
+    }
+}
+
+impl ToNson for Map {
+    fn nson_type(&self) -> DataType {
+        DataType::Map
+    }
+
+    fn nson_size(&self) -> usize {
+        self.bytes_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        encode::encode_map(writer, self)
+    }
+}
+
+impl FromNson for Map {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::Map, tag)?;
+        decode::decode_map(reader)
+    }
+}
+
+impl ToNsonMap for Map {
+    fn entries_size(&self) -> usize {
+        self.bytes_size() - 5
+    }
+
+    fn write_entries<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        for (key, value) in self {
+            write_field(writer, key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects every entry, so a flattened `Map` receives the unknown keys.
+impl FromNsonMap for Map {
+    type Builder = Map;
+
+    fn read_entry<R: Read>(
+        builder: &mut Map,
+        key: &str,
+        tag: DataType,
+        reader: &mut R,
+    ) -> DecodeResult<bool> {
+        let value = Value::read_nson(reader, tag)?;
+        builder.insert(key, value);
+        Ok(true)
+    }
+
+    fn build(builder: Map) -> DecodeResult<Map> {
+        Ok(builder)
+    }
+}
+
+impl ToNson for Array {
+    fn nson_type(&self) -> DataType {
+        DataType::Array
+    }
+
+    fn nson_size(&self) -> usize {
+        self.bytes_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        encode::encode_array(writer, self)
+    }
+}
+
+impl FromNson for Array {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::Array, tag)?;
+        decode::decode_array(reader)
+    }
+}
+
+impl<T: ToNson + ?Sized> ToNson for &T {
+    fn nson_type(&self) -> DataType {
+        (**self).nson_type()
+    }
+
+    fn nson_size(&self) -> usize {
+        (**self).nson_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        (**self).write_nson(writer)
+    }
+}
+
+impl<T: ToNson + ?Sized> ToNson for Box<T> {
+    fn nson_type(&self) -> DataType {
+        (**self).nson_type()
+    }
+
+    fn nson_size(&self) -> usize {
+        (**self).nson_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        (**self).write_nson(writer)
+    }
+}
+
+impl<T: FromNson> FromNson for Box<T> {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        T::read_nson(reader, tag).map(Box::new)
+    }
+
+    fn nson_missing() -> Option<Self> {
+        T::nson_missing().map(Box::new)
+    }
+}
+
+/// `None` is written as `Null`; `Null` and missing fields read as `None`.
+impl<T: ToNson> ToNson for Option<T> {
+    fn nson_type(&self) -> DataType {
+        match self {
+            Some(v) => v.nson_type(),
+            None => DataType::Null,
+        }
+    }
+
+    fn nson_size(&self) -> usize {
+        self.as_ref().map_or(0, ToNson::nson_size)
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        match self {
+            Some(v) => v.write_nson(writer),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: FromNson> FromNson for Option<T> {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        match tag {
+            DataType::Null => Ok(None),
+            _ => T::read_nson(reader, tag).map(Some),
+        }
+    }
+
+    fn nson_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+fn elements_size<'a, T: ToNson + 'a>(iter: impl Iterator<Item = &'a T>) -> usize {
+    iter.map(|v| 1 + v.nson_size()).sum::<usize>()
+}
+
+impl<T: ToNson> ToNson for [T] {
+    fn nson_type(&self) -> DataType {
+        DataType::Array
+    }
+
+    fn nson_size(&self) -> usize {
+        4 + elements_size(self.iter()) + 1
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        let len = self.nson_size();
+
+        if len > crate::MAX_NSON_SIZE as usize {
+            return Err(EncodeError::InvalidValueLen(
+                len,
+                "array len must < MAX_NSON_SIZE".into(),
+            ));
+        }
+
+        encode::write_u32(writer, len as u32)?;
+
+        for v in self {
+            encode::write_u8(writer, v.nson_type() as u8)?;
+            v.write_nson(writer)?;
+        }
+
+        writer.write_all(&[0])?;
+        Ok(())
+    }
+}
+
+impl<T: ToNson> ToNson for Vec<T> {
+    fn nson_type(&self) -> DataType {
+        DataType::Array
+    }
+
+    fn nson_size(&self) -> usize {
+        self.as_slice().nson_size()
+    }
+
+    fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+        self.as_slice().write_nson(writer)
+    }
+}
+
+impl<T: FromNson> FromNson for Vec<T> {
+    fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+        expect(DataType::Array, tag)?;
+        decode::read_array_len(reader)?;
+
+        let mut vec = Vec::new();
+
+        loop {
+            let tag = match decode::read_u8(reader)? {
+                0 => break,
+                tag => DataType::from(tag).ok_or(DecodeError::UnrecognizedElementType(tag))?,
+            };
+
+            let index = vec.len();
+            let v = T::read_nson(reader, tag).map_err(|e| within(e, &index.to_string()))?;
+            vec.push(v);
+        }
+
+        Ok(vec)
+    }
+}
+
+macro_rules! string_map_impls {
+    ($Map:ident<String, T $(, $S:ident)?> $(where $($bound:tt)+)?) => {
+        impl<T: ToNson $(, $S)?> ToNson for $Map<String, T $(, $S)?> {
+            fn nson_type(&self) -> DataType {
+                DataType::Map
+            }
+
+            fn nson_size(&self) -> usize {
+                map_size(self)
+            }
+
+            fn write_nson<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+                to_writer(writer, self)
+            }
+        }
+
+        impl<T: ToNson $(, $S)?> ToNsonMap for $Map<String, T $(, $S)?> {
+            fn entries_size(&self) -> usize {
+                self.iter().map(|(k, v)| field_size(k, v)).sum()
+            }
+
+            fn write_entries<W: Write>(&self, writer: &mut W) -> EncodeResult<()> {
+                for (key, value) in self {
+                    write_field(writer, key, value)?;
+                }
+
+                Ok(())
+            }
+        }
+
+        impl<T: FromNson $(, $S)?> FromNson for $Map<String, T $(, $S)?>
+        $(where $($bound)+)?
+        {
+            fn read_nson<R: Read>(reader: &mut R, tag: DataType) -> DecodeResult<Self> {
+                expect(DataType::Map, tag)?;
+                from_reader(reader)
+            }
+        }
+
+        impl<T: FromNson $(, $S)?> FromNsonMap for $Map<String, T $(, $S)?>
+        $(where $($bound)+)?
+        {
+            type Builder = Self;
+
+            fn read_entry<R: Read>(
+                builder: &mut Self,
+                key: &str,
+                tag: DataType,
+                reader: &mut R,
+            ) -> DecodeResult<bool> {
+                let value = T::read_nson(reader, tag).map_err(|e| within(e, key))?;
+                builder.insert(key.to_owned(), value);
+                Ok(true)
+            }
+
+            fn build(builder: Self) -> DecodeResult<Self> {
+                Ok(builder)
+            }
+        }
+    };
+}
+
+string_map_impls!(BTreeMap<String, T>);
+
+#[cfg(feature = "std")]
+string_map_impls!(HashMap<String, T, S> where S: BuildHasher + Default);
+
+/// Types that can be stored as an `Id`, with `#[nson(id)]`.
+pub trait AsId: Sized {
+    fn to_id(&self) -> Id;
+    fn from_id(id: Id) -> DecodeResult<Self>;
+}
+
+impl AsId for Id {
+    fn to_id(&self) -> Id {
+        *self
+    }
+
+    fn from_id(id: Id) -> DecodeResult<Self> {
+        Ok(id)
+    }
+}
+
+impl AsId for [u8; 12] {
+    fn to_id(&self) -> Id {
+        Id::with_bytes(*self)
+    }
+
+    fn from_id(id: Id) -> DecodeResult<Self> {
+        Ok(id.bytes())
+    }
+}
+
+/// Types that can be stored as a `TimeStamp`, with `#[nson(timestamp)]`.
+pub trait AsTimeStamp: Sized {
+    fn to_timestamp(&self) -> TimeStamp;
+    fn from_timestamp(timestamp: TimeStamp) -> DecodeResult<Self>;
+}
+
+impl AsTimeStamp for TimeStamp {
+    fn to_timestamp(&self) -> TimeStamp {
+        *self
+    }
+
+    fn from_timestamp(timestamp: TimeStamp) -> DecodeResult<Self> {
+        Ok(timestamp)
+    }
+}
+
+impl AsTimeStamp for u64 {
+    fn to_timestamp(&self) -> TimeStamp {
+        TimeStamp(*self)
+    }
+
+    fn from_timestamp(timestamp: TimeStamp) -> DecodeResult<Self> {
+        Ok(timestamp.0)
+    }
+}
+
+/// Types that can be stored as a `Binary`, with `#[nson(binary)]`.
+pub trait AsBinary: Sized {
+    fn binary_bytes(&self) -> &[u8];
+    fn from_binary(binary: Binary) -> DecodeResult<Self>;
+}
+
+impl AsBinary for Binary {
+    fn binary_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn from_binary(binary: Binary) -> DecodeResult<Self> {
+        Ok(binary)
+    }
+}
+
+impl AsBinary for Vec<u8> {
+    fn binary_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn from_binary(binary: Binary) -> DecodeResult<Self> {
+        Ok(binary.0)
+    }
+}
+
+impl<const N: usize> AsBinary for [u8; N] {
+    fn binary_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn from_binary(binary: Binary) -> DecodeResult<Self> {
+        binary.0.as_slice().try_into().map_err(|_| {
+            DecodeError::Field(Error::InvalidLength {
+                key: String::new(),
+                expected: N,
+                found: binary.0.len(),
+            })
+        })
+    }
+}
+
+/// Used by the derive macros.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "std")]
+    pub use std::io::{Read, Write};
+
+    #[cfg(not(feature = "std"))]
+    pub use crate::io::{Read, Write};
+
+    pub use core::default::Default;
+    pub use core::marker::PhantomData;
+    pub use core::option::Option::{self, None, Some};
+    pub use core::result::Result::{Err, Ok};
+
+    use super::*;
+
+    pub fn field_size<T: ToNson + ?Sized>(key: &str, value: &T) -> usize {
+        1 + key.len() + 1 + value.nson_size()
+    }
+
+    pub fn write_field<W: Write, T: ToNson + ?Sized>(
+        writer: &mut W,
+        key: &str,
+        value: &T,
+    ) -> EncodeResult<()> {
+        encode::write_key(writer, key)?;
+        encode::write_u8(writer, value.nson_type() as u8)?;
+        value.write_nson(writer)
+    }
+
+    pub fn within(err: DecodeError, key: &str) -> DecodeError {
+        match err {
+            DecodeError::Field(err) => DecodeError::Field(err.within(key)),
+            err => err,
+        }
+    }
+
+    pub fn map_size<T: ToNsonMap + ?Sized>(value: &T) -> usize {
+        super::map_size(value)
+    }
+
+    pub fn write_map<W: Write, T: ToNsonMap + ?Sized>(
+        writer: &mut W,
+        value: &T,
+    ) -> EncodeResult<()> {
+        to_writer(writer, value)
+    }
+
+    pub fn read_map<R: Read, T: FromNsonMap>(reader: &mut R, tag: DataType) -> DecodeResult<T> {
+        expect(DataType::Map, tag)?;
+        from_reader(reader)
+    }
+
+    pub fn missing(key: &str) -> DecodeError {
+        DecodeError::Field(Error::NotPresent { key: key.into() })
+    }
+
+    pub fn read_id<R: Read, T: AsId>(reader: &mut R, tag: DataType) -> DecodeResult<T> {
+        T::from_id(Id::read_nson(reader, tag)?)
+    }
+
+    pub fn read_timestamp<R: Read, T: AsTimeStamp>(
+        reader: &mut R,
+        tag: DataType,
+    ) -> DecodeResult<T> {
+        T::from_timestamp(TimeStamp::read_nson(reader, tag)?)
+    }
+
+    pub fn read_binary<R: Read, T: AsBinary>(reader: &mut R, tag: DataType) -> DecodeResult<T> {
+        T::from_binary(Binary::read_nson(reader, tag)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::{__private, FromNson, FromNsonMap, Read, ToNson, from_slice, to_vec};
+    use crate::decode::{DecodeError, DecodeResult};
+    use crate::encode::{EncodeError, EncodeResult};
+    use crate::map::{Error, Map};
+    use crate::spec::DataType;
+    use crate::value::Value;
+
+    fn round_trip<T: ToNson + FromNson>(value: &T) -> T {
+        let mut buf = Vec::new();
+        value.write_nson(&mut buf).unwrap();
+        assert_eq!(buf.len(), value.nson_size());
+
+        let mut reader = super::Cursor::new(&buf[..]);
+        T::read_nson(&mut reader, value.nson_type()).unwrap()
+    }
+
+    #[test]
+    fn primitives() {
+        assert_eq!(round_trip(&-5i8), -5);
+        assert_eq!(round_trip(&u64::MAX), u64::MAX);
+        assert_eq!(round_trip(&1.5f32), 1.5);
+        assert!(round_trip(&true));
+        assert_eq!(round_trip(&"nson".to_string()), "nson");
+        assert_eq!(round_trip(&Some(1u8)), Some(1));
+        assert_eq!(round_trip(&None::<u8>), None);
+        assert_eq!(round_trip(&vec![vec![1i32], vec![]]), vec![vec![1], vec![]]);
+        assert_eq!(
+            round_trip(&Value::Array(crate::a![1, "a", null])),
+            Value::Array(crate::a![1, "a", null])
+        );
+    }
+
+    #[test]
+    fn array_too_large() {
+        // a zero sized element, so the slice costs no memory
+        struct Empty;
+
+        impl ToNson for Empty {
+            fn nson_type(&self) -> DataType {
+                DataType::Null
+            }
+
+            fn nson_size(&self) -> usize {
+                0
+            }
+
+            fn write_nson<W: super::Write>(&self, _: &mut W) -> EncodeResult<()> {
+                Ok(())
+            }
+        }
+
+        let array: Vec<Empty> = (0..crate::MAX_NSON_SIZE).map(|_| Empty).collect();
+        let mut buf = Vec::new();
+        assert!(matches!(
+            array.write_nson(&mut buf),
+            Err(EncodeError::InvalidValueLen(len, _)) if len == array.nson_size()
+        ));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn string_maps() {
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), 1u32);
+        map.insert("b".to_string(), 2u32);
+
+        let bytes = to_vec(&map).unwrap();
+        assert_eq!(bytes, crate::m! {"a": 1u32, "b": 2u32}.to_bytes().unwrap());
+        assert_eq!(from_slice::<BTreeMap<String, u32>>(&bytes).unwrap(), map);
+
+        let bytes = crate::m! {"a": 1, "b": "x"}.to_bytes().unwrap();
+        assert!(matches!(
+            from_slice::<BTreeMap<String, u32>>(&bytes),
+            Err(DecodeError::Field(Error::UnexpectedType {
+                ref key,
+                expected: DataType::U32,
+                found: DataType::String,
+            })) if key == "b"
+        ));
+    }
+
+    #[test]
+    fn skip_unknown() {
+        let bytes = crate::m! {
+            "a": {"b": [1, 2, {"c": "d"}]},
+            "e": crate::Binary(vec![1, 2, 3]),
+            "f": crate::TimeStamp(1),
+            "g": 1u64,
+        }
+        .to_bytes()
+        .unwrap();
+
+        let map: Map = from_slice(&bytes).unwrap();
+        assert_eq!(map.len(), 4);
+
+        // Only reads `g`, everything else is skipped.
+        struct OnlyG(u64);
+
+        impl FromNsonMap for OnlyG {
+            type Builder = Option<u64>;
+
+            fn read_entry<R: Read>(
+                builder: &mut Option<u64>,
+                key: &str,
+                tag: DataType,
+                reader: &mut R,
+            ) -> DecodeResult<bool> {
+                if key != "g" {
+                    return Ok(false);
+                }
+
+                *builder = Some(u64::read_nson(reader, tag)?);
+                Ok(true)
+            }
+
+            fn build(builder: Option<u64>) -> DecodeResult<OnlyG> {
+                builder.map(OnlyG).ok_or_else(|| __private::missing("g"))
+            }
+        }
+
+        assert_eq!(from_slice::<OnlyG>(&bytes).unwrap().0, 1);
+    }
+}
@@ -0,0 +1,472 @@
+//! Convert
+//!
+//! [`FromValue`] and [`IntoValue`] convert between [`Value`] and Rust types
+//! without going through serde, and back the generic getters
+//! [`Map::get_as`] and [`Array::get_as`].
+//!
+//! ```
+//! use nson::m;
+//!
+//! let map = m! {
+//!     "port": 8080u16,
+//!     "tags": ["a", "b"],
+//!     "point": [1, 2.5f32],
+//!     "note": null,
+//! };
+//!
+//! let port: u32 = map.get_as("port").unwrap();
+//! let tags: Vec<String> = map.get_as("tags").unwrap();
+//! let point: (i64, f32) = map.get_as("point").unwrap();
+//! let note: Option<&str> = map.get_as("note").unwrap();
+//! let missing: Option<bool> = map.get_as("missing").unwrap();
+//!
+//! assert_eq!(port, 8080);
+//! assert_eq!(tags, ["a", "b"]);
+//! assert_eq!(point, (1, 2.5));
+//! assert_eq!(note, None);
+//! assert_eq!(missing, None);
+//! ```
+//!
+//! Integer targets accept any numeric variant whose value fits, so a `u32`
+//! can be read from a `U8`, and `f64` accepts integers that are exactly
+//! representable. `Vec<T>` is read from an `Array`; use `Binary` or
+//! `[u8; N]` for bytes.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::BuildHasher;
+
+use crate::array::Array;
+use crate::id::Id;
+use crate::map::{Error, Map, Result};
+use crate::spec::DataType;
+use crate::value::{Binary, TimeStamp, Value};
+
+/// Conversion from a borrowed [`Value`].
+///
+/// Errors carry an empty key (or the path below the converted value), which
+/// the getters prefix with the key that was looked up.
+pub trait FromValue<'a>: Sized {
+    fn from_value(value: &'a Value) -> Result<Self>;
+
+    /// The result of looking up a missing key, `None` makes it an error.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Conversion into a [`Value`].
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl<'a> FromValue<'a> for Value {
+    fn from_value(value: &'a Value) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl<'a> FromValue<'a> for &'a Value {
+    fn from_value(value: &'a Value) -> Result<Self> {
+        Ok(value)
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+macro_rules! int_impls {
+    ($($T:ident => $to:ident, $V:ident;)+) => {
+        $(
+            impl<'a> FromValue<'a> for $T {
+                fn from_value(value: &'a Value) -> Result<Self> {
+                    match value.as_number() {
+                        Some(n) => n
+                            .$to()
+                            .ok_or_else(|| Error::out_of_range("", DataType::$V, value)),
+                        None => Err(Error::unexpected_type("", DataType::$V, value)),
+                    }
+                }
+            }
+
+            impl IntoValue for $T {
+                fn into_value(self) -> Value {
+                    Value::$V(self)
+                }
+            }
+        )+
+    };
+}
+
+int_impls! {
+    i8 => to_i8, I8;
+    u8 => to_u8, U8;
+    i16 => to_i16, I16;
+    u16 => to_u16, U16;
+    i32 => to_i32, I32;
+    u32 => to_u32, U32;
+    i64 => to_i64, I64;
+    u64 => to_u64, U64;
+    f32 => to_f32, F32;
+    f64 => to_f64, F64;
+}
+
+macro_rules! variant_impls {
+    ($($T:ty => $V:ident, |$v:ident| $get:expr;)+) => {
+        $(
+            impl<'a> FromValue<'a> for $T {
+                fn from_value(value: &'a Value) -> Result<Self> {
+                    match value {
+                        Value::$V($v) => Ok($get),
+                        _ => Err(Error::unexpected_type("", DataType::$V, value)),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+variant_impls! {
+    bool => Bool, |v| *v;
+    String => String, |v| v.clone();
+    &'a str => String, |v| v.as_str();
+    Binary => Binary, |v| v.clone();
+    &'a Binary => Binary, |v| v;
+    &'a [u8] => Binary, |v| v.0.as_slice();
+    Id => Id, |v| *v;
+    TimeStamp => TimeStamp, |v| *v;
+    Map => Map, |v| v.clone();
+    &'a Map => Map, |v| v;
+    Array => Array, |v| v.clone();
+    &'a Array => Array, |v| v;
+}
+
+macro_rules! into_impls {
+    ($($T:ty)+) => {
+        $(
+            impl IntoValue for $T {
+                fn into_value(self) -> Value {
+                    self.into()
+                }
+            }
+        )+
+    };
+}
+
+into_impls! {
+    bool String &str Binary Id TimeStamp Map Array
+}
+
+impl<'a, T: FromValue<'a>> FromValue<'a> for Option<T> {
+    fn from_value(value: &'a Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            _ => T::from_value(value).map(Some),
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(v) => v.into_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<'a, T: FromValue<'a>> FromValue<'a> for Vec<T> {
+    fn from_value(value: &'a Value) -> Result<Self> {
+        match value {
+            Value::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(i, v)| T::from_value(v).map_err(|e| e.within(&i.to_string())))
+                .collect(),
+            _ => Err(Error::unexpected_type("", DataType::Array, value)),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::Array(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<'a, const N: usize> FromValue<'a> for [u8; N] {
+    fn from_value(value: &'a Value) -> Result<Self> {
+        match value {
+            Value::Binary(b) => {
+                b.0.as_slice()
+                    .try_into()
+                    .map_err(|_| Error::invalid_length("", N, b.0.len()))
+            }
+            _ => Err(Error::unexpected_type("", DataType::Binary, value)),
+        }
+    }
+}
+
+impl<const N: usize> IntoValue for [u8; N] {
+    fn into_value(self) -> Value {
+        Value::Binary(Binary(self.to_vec()))
+    }
+}
+
+fn map_entries<'a, T, C>(value: &'a Value) -> Result<C>
+where
+    T: FromValue<'a>,
+    C: FromIterator<(String, T)>,
+{
+    match value {
+        Value::Map(map) => map
+            .iter()
+            .map(|(k, v)| {
+                T::from_value(v)
+                    .map(|v| (k.clone(), v))
+                    .map_err(|e| e.within(k))
+            })
+            .collect(),
+        _ => Err(Error::unexpected_type("", DataType::Map, value)),
+    }
+}
+
+impl<'a, T: FromValue<'a>> FromValue<'a> for BTreeMap<String, T> {
+    fn from_value(value: &'a Value) -> Result<Self> {
+        map_entries(value)
+    }
+}
+
+impl<T: IntoValue> IntoValue for BTreeMap<String, T> {
+    fn into_value(self) -> Value {
+        Value::Map(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: FromValue<'a>, S: BuildHasher + Default> FromValue<'a> for HashMap<String, T, S> {
+    fn from_value(value: &'a Value) -> Result<Self> {
+        map_entries(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: IntoValue, S> IntoValue for HashMap<String, T, S> {
+    fn into_value(self) -> Value {
+        Value::Map(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+macro_rules! tuple_impls {
+    ($($len:expr => ($($n:tt $T:ident)+))+) => {
+        $(
+            impl<'a, $($T: FromValue<'a>),+> FromValue<'a> for ($($T,)+) {
+                fn from_value(value: &'a Value) -> Result<Self> {
+                    match value {
+                        Value::Array(array) if array.len() == $len => Ok(($(
+                            $T::from_value(&array[$n])
+                                .map_err(|e| e.within(stringify!($n)))?,
+                        )+)),
+                        Value::Array(array) => Err(Error::invalid_length("", $len, array.len())),
+                        _ => Err(Error::unexpected_type("", DataType::Array, value)),
+                    }
+                }
+            }
+
+            impl<$($T: IntoValue),+> IntoValue for ($($T,)+) {
+                fn into_value(self) -> Value {
+                    let mut array = Array::with_capacity($len);
+                    $(array.push_value(self.$n.into_value());)+
+                    Value::Array(array)
+                }
+            }
+        )+
+    };
+}
+
+tuple_impls! {
+    1 => (0 A)
+    2 => (0 A 1 B)
+    3 => (0 A 1 B 2 C)
+    4 => (0 A 1 B 2 C 3 D)
+    5 => (0 A 1 B 2 C 3 D 4 E)
+    6 => (0 A 1 B 2 C 3 D 4 E 5 F)
+}
+
+impl Map {
+    /// Get the value of `key` converted with [`FromValue`].
+    pub fn get_as<'a, T: FromValue<'a>>(&'a self, key: &str) -> Result<T> {
+        match self.get(key) {
+            Some(value) => T::from_value(value).map_err(|e| e.within(key)),
+            None => T::from_missing().ok_or_else(|| Error::not_present(key)),
+        }
+    }
+}
+
+impl Array {
+    /// Get the element at `index` converted with [`FromValue`].
+    pub fn get_as<'a, T: FromValue<'a>>(&'a self, index: usize) -> Result<T> {
+        let key = index.to_string();
+
+        match self.get(index) {
+            Some(value) => T::from_value(value).map_err(|e| e.within(&key)),
+            None => T::from_missing().ok_or_else(|| Error::not_present(&key)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::{FromValue, IntoValue};
+    use crate::map::Error;
+    use crate::spec::DataType;
+    use crate::value::{Binary, TimeStamp, Value};
+    use crate::{Id, a, m};
+
+    #[test]
+    fn scalars() {
+        let map = m! {
+            "a": 1u8,
+            "b": "x",
+            "c": TimeStamp(5),
+            "d": Id::zero(),
+            "e": true,
+        };
+
+        assert_eq!(map.get_as::<i64>("a"), Ok(1));
+        assert_eq!(map.get_as::<f32>("a"), Ok(1.0));
+        assert_eq!(map.get_as::<&str>("b"), Ok("x"));
+        assert_eq!(map.get_as::<String>("b"), Ok("x".to_string()));
+        assert_eq!(map.get_as::<TimeStamp>("c"), Ok(TimeStamp(5)));
+        assert_eq!(map.get_as::<Id>("d"), Ok(Id::zero()));
+        assert_eq!(map.get_as::<bool>("e"), Ok(true));
+    }
+
+    #[test]
+    fn errors() {
+        let map = m! {
+            "a": 300u16,
+            "b": "x",
+            "c": [1, "two"],
+            "d": {"e": [1, 2, 3]},
+        };
+
+        assert_eq!(
+            map.get_as::<u8>("a"),
+            Err(Error::OutOfRange {
+                key: "a".to_string(),
+                expected: DataType::U8,
+                found: DataType::U16,
+            })
+        );
+        assert_eq!(
+            map.get_as::<i32>("b"),
+            Err(Error::UnexpectedType {
+                key: "b".to_string(),
+                expected: DataType::I32,
+                found: DataType::String,
+            })
+        );
+        assert_eq!(
+            map.get_as::<Vec<i32>>("c"),
+            Err(Error::UnexpectedType {
+                key: "c.1".to_string(),
+                expected: DataType::I32,
+                found: DataType::String,
+            })
+        );
+        assert_eq!(
+            map.get_as::<BTreeMap<String, (i32, i32)>>("d"),
+            Err(Error::InvalidLength {
+                key: "d.e".to_string(),
+                expected: 2,
+                found: 3,
+            })
+        );
+        assert_eq!(
+            map.get_as::<bool>("z"),
+            Err(Error::NotPresent {
+                key: "z".to_string()
+            })
+        );
+        assert_eq!(
+            map.get_as::<i32>("b").unwrap_err().to_string(),
+            "Unexpected type for key `b`: expected I32, found String"
+        );
+    }
+
+    #[test]
+    fn containers() {
+        let map = m! {
+            "tuple": [1, "a", true],
+            "nested": [[1, 2], [3]],
+            "map": {"x": 1, "y": 2},
+            "bytes": Binary(vec![1, 2, 3, 4]),
+            "null": null,
+        };
+
+        assert_eq!(map.get_as::<(u8, &str, bool)>("tuple"), Ok((1, "a", true)));
+        assert_eq!(
+            map.get_as::<Vec<Vec<i32>>>("nested"),
+            Ok(vec![vec![1, 2], vec![3]])
+        );
+        assert_eq!(map.get_as::<[u8; 4]>("bytes"), Ok([1, 2, 3, 4]));
+        assert!(map.get_as::<[u8; 3]>("bytes").is_err());
+        assert_eq!(map.get_as::<Option<i32>>("null"), Ok(None));
+        assert_eq!(map.get_as::<Option<i32>>("missing"), Ok(None));
+
+        let m: BTreeMap<String, i64> = map.get_as("map").unwrap();
+        assert_eq!(m.get("y"), Some(&2));
+
+        #[cfg(feature = "std")]
+        {
+            let m: std::collections::HashMap<String, u8> = map.get_as("map").unwrap();
+            assert_eq!(m.get("x"), Some(&1));
+        }
+    }
+
+    #[test]
+    fn array_get_as() {
+        let array = a![1, "a"];
+
+        assert_eq!(array.get_as::<i32>(0), Ok(1));
+        assert_eq!(array.get_as::<&str>(1), Ok("a"));
+        assert_eq!(
+            array.get_as::<&str>(2),
+            Err(Error::NotPresent {
+                key: "2".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn into_value() {
+        let value = (1u8, "a", vec![Some(1.5f64), None]).into_value();
+
+        assert_eq!(value, Value::Array(a![1u8, "a", [1.5f64, null]]));
+        assert_eq!(
+            <(u8, String, Vec<Option<f64>>)>::from_value(&value)
+                .unwrap()
+                .2[1],
+            None
+        );
+        assert_eq!([1u8, 2].into_value(), Value::Binary(Binary(vec![1, 2])));
+    }
+}
@@ -12,9 +12,11 @@ use std::io::{self, Cursor, Read};
 use crate::io::{self, Cursor, Read};
 
 #[cfg(feature = "serde")]
-use crate::serde::decode::Decoder;
+use crate::serde::bytes::BytesDecoder;
 #[cfg(feature = "serde")]
-use serde::de::Deserialize;
+use crate::serde::decode::{Decoder, DecoderOptions};
+#[cfg(feature = "serde")]
+use serde::de::{Deserialize, DeserializeOwned};
 
 use crate::array::Array;
 use crate::id::Id;
@@ -29,6 +31,8 @@ pub enum DecodeError {
     UnrecognizedElementType(u8),
     InvalidLength(usize, String),
     Unknown(String),
+    /// A value has the wrong type or is missing, from the [`codec`](crate::codec) traits.
+    Field(crate::map::Error),
     #[cfg(feature = "serde")]
     Serde(crate::serde::DecodeError),
 }
@@ -45,6 +49,19 @@ impl From<FromUtf8Error> for DecodeError {
     }
 }
 
+impl From<crate::map::Error> for DecodeError {
+    fn from(err: crate::map::Error) -> DecodeError {
+        DecodeError::Field(err)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::DecodeErrorKind> for DecodeError {
+    fn from(kind: crate::serde::DecodeErrorKind) -> DecodeError {
+        DecodeError::Serde(kind.into())
+    }
+}
+
 #[cfg(feature = "serde")]
 impl From<crate::serde::DecodeError> for DecodeError {
     fn from(err: crate::serde::DecodeError) -> DecodeError {
@@ -52,6 +69,62 @@ impl From<crate::serde::DecodeError> for DecodeError {
     }
 }
 
+#[cfg(feature = "serde")]
+impl DecodeError {
+    /// Prefix the path of a serde error with `segment`, as
+    /// `serde::DecodeError::within` does.
+    pub(crate) fn within(self, segment: &str) -> DecodeError {
+        match self {
+            DecodeError::Serde(err) => DecodeError::Serde(err.within(segment)),
+            err => err,
+        }
+    }
+
+    /// Record the type of the element a serde error comes from, unless a
+    /// nested value already did.
+    pub(crate) fn found_in(self, found: crate::spec::DataType) -> DecodeError {
+        match self {
+            DecodeError::Serde(err) => DecodeError::Serde(err.found_in(found)),
+            err => err,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for DecodeError {
+    fn custom<T: fmt::Display>(msg: T) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::custom(msg))
+    }
+
+    fn invalid_type(unexp: serde::de::Unexpected, exp: &dyn serde::de::Expected) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::invalid_type(unexp, exp))
+    }
+
+    fn invalid_value(unexp: serde::de::Unexpected, exp: &dyn serde::de::Expected) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::invalid_value(unexp, exp))
+    }
+
+    fn invalid_length(len: usize, exp: &dyn serde::de::Expected) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::invalid_length(len, exp))
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::unknown_variant(variant, expected))
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::unknown_field(field, expected))
+    }
+
+    fn missing_field(field: &'static str) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::missing_field(field))
+    }
+
+    fn duplicate_field(field: &'static str) -> DecodeError {
+        DecodeError::Serde(serde::de::Error::duplicate_field(field))
+    }
+}
+
 impl fmt::Display for DecodeError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -64,6 +137,7 @@ impl fmt::Display for DecodeError {
                 write!(fmt, "Expecting length {}, {}", len, desc)
             }
             DecodeError::Unknown(ref inner) => inner.fmt(fmt),
+            DecodeError::Field(ref inner) => inner.fmt(fmt),
             #[cfg(feature = "serde")]
             DecodeError::Serde(ref inner) => inner.fmt(fmt),
         }
@@ -75,6 +149,7 @@ impl core::error::Error for DecodeError {
         match *self {
             DecodeError::IoError(ref inner) => Some(inner),
             DecodeError::FromUtf8Error(ref inner) => Some(inner),
+            DecodeError::Field(ref inner) => Some(inner),
             #[cfg(feature = "serde")]
             DecodeError::Serde(ref inner) => Some(inner),
             _ => None,
@@ -206,9 +281,7 @@ pub(crate) fn read_binary(reader: &mut impl Read) -> DecodeResult<Binary> {
     Ok(Binary(data))
 }
 
-pub fn decode_array(reader: &mut impl Read) -> DecodeResult<Array> {
-    let mut arr = Array::new();
-
+pub(crate) fn read_array_len(reader: &mut impl Read) -> DecodeResult<u32> {
     let len = read_u32(reader)?;
 
     if len < crate::MIN_NSON_SIZE {
@@ -225,6 +298,14 @@ pub fn decode_array(reader: &mut impl Read) -> DecodeResult<Array> {
         ));
     }
 
+    Ok(len)
+}
+
+pub fn decode_array(reader: &mut impl Read) -> DecodeResult<Array> {
+    let mut arr = Array::new();
+
+    read_array_len(reader)?;
+
     loop {
         let tag = read_u8(reader)?;
         if tag == 0 {
@@ -238,12 +319,12 @@ pub fn decode_array(reader: &mut impl Read) -> DecodeResult<Array> {
     Ok(arr)
 }
 
-pub fn decode_map(reader: &mut impl Read) -> DecodeResult<Map> {
-    let mut map = Map::new();
-
-    // disregard the length: using Read::take causes infinite type recursion
-    let len = read_u32(reader)?;
+pub(crate) fn read_map_len(reader: &mut impl Read) -> DecodeResult<u32> {
+    check_map_len(read_u32(reader)?)
+}
 
+/// Check the length a map starts with.
+pub(crate) fn check_map_len(len: u32) -> DecodeResult<u32> {
     if len < crate::MIN_NSON_SIZE {
         return Err(DecodeError::InvalidLength(
             len as usize,
@@ -258,21 +339,71 @@ pub fn decode_map(reader: &mut impl Read) -> DecodeResult<Map> {
         ));
     }
 
-    loop {
-        let key = {
-            let len = read_u8(reader)?;
-            if len == 0 {
-                break;
+    Ok(len)
+}
+
+/// Read the key of the next map entry, `None` at the end of the map.
+pub(crate) fn read_key(reader: &mut impl Read) -> DecodeResult<Option<String>> {
+    let len = read_u8(reader)?;
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let len = len - 1;
+
+    let mut buf = alloc::vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+
+    Ok(Some(String::from_utf8(buf)?))
+}
+
+pub(crate) fn read_tag(reader: &mut impl Read) -> DecodeResult<DataType> {
+    let tag = read_u8(reader)?;
+    DataType::from(tag).ok_or(DecodeError::UnrecognizedElementType(tag))
+}
+
+/// Read past a value without decoding it.
+pub(crate) fn skip_value(reader: &mut impl Read, tag: DataType) -> DecodeResult<()> {
+    let len = match tag {
+        DataType::Null => 0,
+        DataType::Bool | DataType::I8 | DataType::U8 => 1,
+        DataType::I16 | DataType::U16 => 2,
+        DataType::F32 | DataType::I32 | DataType::U32 => 4,
+        DataType::F64 | DataType::I64 | DataType::U64 | DataType::TimeStamp => 8,
+        DataType::Id => 12,
+        DataType::String | DataType::Binary => {
+            let len = read_u32(reader)?;
+            if !(crate::MIN_NSON_SIZE - 1..=crate::MAX_NSON_SIZE).contains(&len) {
+                return Err(DecodeError::InvalidLength(
+                    len as usize,
+                    format!("Invalid {:?} length of {}", tag, len),
+                ));
             }
+            len as usize - 4
+        }
+        DataType::Map => read_map_len(reader)? as usize - 4,
+        DataType::Array => read_array_len(reader)? as usize - 4,
+    };
 
-            let len = len - 1;
+    let mut buf = [0u8; 64];
+    let mut remaining = len;
 
-            let mut buf = alloc::vec![0u8; len as usize];
-            reader.read_exact(&mut buf)?;
+    while remaining > 0 {
+        let n = remaining.min(buf.len());
+        reader.read_exact(&mut buf[..n])?;
+        remaining -= n;
+    }
+
+    Ok(())
+}
 
-            String::from_utf8(buf)?
-        };
+pub fn decode_map(reader: &mut impl Read) -> DecodeResult<Map> {
+    let mut map = Map::new();
 
+    // disregard the length: using Read::take causes infinite type recursion
+    read_map_len(reader)?;
+
+    while let Some(key) = read_key(reader)? {
         let val = decode_value(reader)?;
 
         map.insert(key, val);
@@ -286,7 +417,7 @@ pub fn decode_value(reader: &mut impl Read) -> DecodeResult<Value> {
     decode_value_with_tag(reader, tag)
 }
 
-fn decode_value_with_tag(reader: &mut impl Read, tag: u8) -> DecodeResult<Value> {
+pub(crate) fn decode_value_with_tag(reader: &mut impl Read, tag: u8) -> DecodeResult<Value> {
     match DataType::from(tag) {
         Some(DataType::F32) => read_f32(reader).map(Value::F32),
         Some(DataType::F64) => read_f64(reader).map(Value::F64),
@@ -321,12 +452,44 @@ pub fn from_nson<'de, T: Deserialize<'de>>(value: Value) -> DecodeResult<T> {
     Deserialize::deserialize(de).map_err(DecodeError::Serde)
 }
 
+/// Deserialize with the given options instead of the defaults.
+#[cfg(feature = "serde")]
+pub fn from_nson_with<'de, T: Deserialize<'de>>(
+    value: Value,
+    options: DecoderOptions,
+) -> DecodeResult<T> {
+    let de = Decoder::with_options(value, options);
+    Deserialize::deserialize(de).map_err(DecodeError::Serde)
+}
+
+/// Deserialize from a borrowed value, without cloning it.
+#[cfg(feature = "serde")]
+pub fn from_nson_ref<'de, T: Deserialize<'de>>(value: &'de Value) -> DecodeResult<T> {
+    Deserialize::deserialize(value).map_err(DecodeError::Serde)
+}
+
 #[cfg(feature = "serde")]
 pub fn from_bytes<'de, T: Deserialize<'de>>(bytes: &[u8]) -> DecodeResult<T> {
     let value = Value::from_bytes(bytes)?;
     from_nson(value)
 }
 
+/// Deserialize straight from bytes, without decoding a [`Value`] first.
+///
+/// Strings and bytes borrow from `bytes` where `T` allows it.
+#[cfg(feature = "serde")]
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> DecodeResult<T> {
+    let mut de = BytesDecoder::from_slice(bytes);
+    T::deserialize(&mut de)
+}
+
+/// Deserialize a value from a reader, like [`from_slice`].
+#[cfg(feature = "serde")]
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> DecodeResult<T> {
+    let mut de = BytesDecoder::from_reader(reader);
+    T::deserialize(&mut de)
+}
+
 impl Value {
     pub fn from_bytes(bytes: &[u8]) -> DecodeResult<Value> {
         let mut reader = Cursor::new(bytes);
@@ -12,7 +12,9 @@ use std::io::{self, Write};
 use crate::io::{self, Write};
 
 #[cfg(feature = "serde")]
-use crate::serde::encode::Encoder;
+use crate::serde::bytes::BytesEncoder;
+#[cfg(feature = "serde")]
+use crate::serde::encode::{Encoder, EncoderOptions};
 #[cfg(feature = "serde")]
 use serde::ser::Serialize;
 
@@ -44,6 +46,13 @@ impl From<crate::serde::EncodeError> for EncodeError {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::ser::Error for EncodeError {
+    fn custom<T: fmt::Display>(msg: T) -> EncodeError {
+        EncodeError::Serde(crate::serde::EncodeError::Unknown(msg.to_string()))
+    }
+}
+
 impl fmt::Display for EncodeError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -125,7 +134,7 @@ pub(crate) fn write_f64(writer: &mut impl Write, val: f64) -> EncodeResult<()> {
 }
 
 pub(crate) fn write_key(writer: &mut impl Write, s: &str) -> EncodeResult<()> {
-    if s.is_empty() || s.len() >= u8::MAX as usize {
+    if crate::key::validate(s).is_err() {
         return Err(EncodeError::InvalidKeyLen(
             s.len(),
             "key len must > 0 and < 255".to_string(),
@@ -151,15 +160,19 @@ pub(crate) fn write_string(writer: &mut impl Write, s: &str) -> EncodeResult<()>
 }
 
 pub(crate) fn write_binary(writer: &mut impl Write, binary: &Binary) -> EncodeResult<()> {
-    if binary.0.len() > crate::MAX_NSON_SIZE as usize - 4 {
+    write_bytes(writer, &binary.0)
+}
+
+pub(crate) fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> EncodeResult<()> {
+    if bytes.len() > crate::MAX_NSON_SIZE as usize - 4 {
         return Err(EncodeError::InvalidValueLen(
-            binary.0.len(),
+            bytes.len(),
             "binary len must < MAX_NSON_SIZE - 4".to_string(),
         ));
     }
 
-    write_u32(writer, binary.0.len() as u32 + 4)?;
-    writer.write_all(&binary.0)?;
+    write_u32(writer, bytes.len() as u32 + 4)?;
+    writer.write_all(bytes)?;
     Ok(())
 }
 
@@ -209,7 +222,11 @@ pub fn encode_map(writer: &mut impl Write, map: &Map) -> EncodeResult<()> {
 
 pub fn encode_value(writer: &mut impl Write, val: &Value) -> EncodeResult<()> {
     writer.write_all(&[val.element_type() as u8])?;
+    encode_payload(writer, val)
+}
 
+/// Encode a value without its tag.
+pub(crate) fn encode_payload(writer: &mut impl Write, val: &Value) -> EncodeResult<()> {
     match *val {
         Value::F32(v) => write_f32(writer, v),
         Value::F64(v) => write_f64(writer, v),
@@ -298,10 +315,40 @@ pub fn to_nson<T: Serialize + ?Sized>(value: &T) -> EncodeResult<Value> {
     value.serialize(ser).map_err(EncodeError::Serde)
 }
 
+/// Serialize with the given options instead of the defaults.
+#[cfg(feature = "serde")]
+pub fn to_nson_with<T: Serialize + ?Sized>(
+    value: &T,
+    options: EncoderOptions,
+) -> EncodeResult<Value> {
+    let ser = Encoder::with_options(options);
+    value.serialize(ser).map_err(EncodeError::Serde)
+}
+
 #[cfg(feature = "serde")]
 pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> EncodeResult<Vec<u8>> {
-    let value = to_nson(value)?;
-    value.to_bytes()
+    to_vec(value)
+}
+
+/// Serialize straight to bytes, without building a [`Value`] first.
+///
+/// The output is the same as `to_nson(value)?.to_bytes()`.
+#[cfg(feature = "serde")]
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> EncodeResult<Vec<u8>> {
+    let mut buf = Vec::new();
+    value.serialize(BytesEncoder::new(&mut buf))?;
+    Ok(buf)
+}
+
+/// Serialize to a writer, like [`to_vec`].
+///
+/// Lengths are patched in after each map or array, so the value is
+/// encoded into a buffer and then written in one go.
+#[cfg(feature = "serde")]
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: &mut W, value: &T) -> EncodeResult<()> {
+    let buf = to_vec(value)?;
+    writer.write_all(&buf)?;
+    Ok(())
 }
 
 #[cfg(test)]
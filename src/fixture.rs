@@ -0,0 +1,36 @@
+//! Values shared by the tests of the text notation, JSON and transcoding.
+
+use crate::vec as __vec;
+use crate::{Binary, Id, Map, TimeStamp, m};
+
+/// A map with every type, their limits, non-finite floats, and keys and
+/// strings that need escaping. NaN is left out so that it compares equal.
+pub(crate) fn values() -> Map {
+    m! {
+        "f32": 0.1f32,
+        "f64": 0.1f64,
+        "whole": 1f64,
+        "big": 1e300f64,
+        "tiny": 1e-30f32,
+        "negative_zero": -0f32,
+        "i32": i32::MIN,
+        "i64": i64::MIN,
+        "u32": u32::MAX,
+        "u64": u64::MAX,
+        "i8": i8::MIN,
+        "u8": u8::MAX,
+        "i16": i16::MIN,
+        "u16": u16::MAX,
+        "inf": f64::NEG_INFINITY,
+        "inf32": f32::INFINITY,
+        "string": "quote \" slash \\ tab \t nul \u{0} bell \u{7} é 🦀",
+        "bin": Binary(__vec![0, 255, 1, 2]),
+        "empty": Binary(__vec![]),
+        "tim": TimeStamp(u64::MAX),
+        "id": Id::new_raw(1, 2, 3),
+        "array": [1i32, "a", null, true, crate::Array::new(), [false], [2u8, {"$$u8": 3u8}], {}],
+        "$tim": {"$u8": 1u8},
+        "map": {"a": {"b": TimeStamp(1)}},
+        "a key.with spaces": {},
+    }
+}
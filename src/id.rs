@@ -117,6 +117,15 @@ impl Id {
         const_hex::encode(self.bytes)
     }
 
+    /// Used by the macros to parse literal ids at compile time.
+    #[doc(hidden)]
+    pub const fn literal(hex: &'static str) -> Id {
+        match const_hex::const_decode_to_array::<12>(hex.as_bytes()) {
+            Ok(bytes) => Id { bytes },
+            Err(_) => panic!("nson id must be a 24 character hexadecimal string"),
+        }
+    }
+
     pub fn zero() -> Id {
         Id {
             bytes: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
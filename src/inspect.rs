@@ -0,0 +1,360 @@
+//! Inspect
+//!
+//! An annotated hex dump of encoded NSON, for looking at what a device sent.
+//! Each line is the offset, the bytes and what they are, indented by
+//! nesting. Values are shown in the [`text`](crate::text) notation.
+//!
+//! ```
+//! use nson::{inspect, m};
+//!
+//! let bytes = m! {"n": 7u8}.to_bytes().unwrap();
+//! let text = inspect::annotate(&bytes);
+//! assert!(text.starts_with("0000  09 00 00 00 "));
+//! print!("{}", text);
+//! // 0000  09 00 00 00                                      map length 9
+//! // 0004  02 6e                                              key "n"
+//! // 0006  18                                                 tag U8
+//! // 0007  07                                                 7u8
+//! // 0008  00                                               end of map
+//! ```
+//!
+//! Bad input is annotated up to where decoding would fail, with an `error:`
+//! line. When the length of the enclosing map or array is sound, the dump
+//! carries on after it.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::decode::decode_value_with_tag;
+use crate::spec::DataType;
+use crate::value::Value;
+
+/// Bytes shown on one line, longer ranges continue on the next lines.
+const BYTES_PER_LINE: usize = 16;
+
+/// Annotate a sequence of NSON maps, as written by `Map::to_bytes`.
+pub fn annotate(bytes: &[u8]) -> String {
+    let mut annotator = Annotator {
+        bytes,
+        out: String::new(),
+    };
+
+    // empty input is a map length cut short too
+    let mut pos = 0;
+    while let Some(end) = annotator.container(pos, bytes.len(), 0, true) {
+        pos = end;
+        if pos == bytes.len() {
+            break;
+        }
+    }
+
+    annotator.out
+}
+
+struct Annotator<'a> {
+    bytes: &'a [u8],
+    out: String,
+}
+
+impl Annotator<'_> {
+    /// Write a line for `start..end`, wrapping long ranges.
+    fn line(&mut self, start: usize, end: usize, depth: usize, text: &str) {
+        let width = BYTES_PER_LINE * 3 - 1;
+        let mut chunks = self.bytes[start..end].chunks(BYTES_PER_LINE);
+        let first = chunks.next().unwrap_or(&[]);
+
+        let _ = writeln!(
+            self.out,
+            "{:04x}  {:<width$}  {:indent$}{}",
+            start,
+            hex(first),
+            "",
+            text,
+            width = width,
+            indent = depth * 2,
+        );
+
+        for (i, chunk) in chunks.enumerate() {
+            let offset = start + (i + 1) * BYTES_PER_LINE;
+            let _ = writeln!(self.out, "{:04x}  {}", offset, hex(chunk));
+        }
+    }
+
+    /// Report that fewer than `needed` bytes are left before `limit`,
+    /// showing what is left.
+    fn truncated(
+        &mut self,
+        pos: usize,
+        limit: usize,
+        depth: usize,
+        what: &str,
+        needed: usize,
+    ) -> Option<usize> {
+        let text = format!(
+            "error: {} needs {} bytes, {} left",
+            what,
+            needed,
+            limit - pos
+        );
+        self.line(pos, limit, depth, &text);
+        None
+    }
+
+    /// Annotate a map or an array starting at its length, returning where
+    /// it ends, or `None` when nothing more can be read before `limit`.
+    fn container(&mut self, pos: usize, limit: usize, depth: usize, map: bool) -> Option<usize> {
+        let name = if map { "map" } else { "array" };
+
+        let Some(len) = self.u32_at(pos).filter(|_| pos + 4 <= limit) else {
+            return self.truncated(pos, limit, depth, &format!("{} length", name), 4);
+        };
+        let len = len as usize;
+
+        let valid = (crate::MIN_NSON_SIZE as usize..=crate::MAX_NSON_SIZE as usize).contains(&len);
+        let text = if !valid {
+            format!("{} length {} (error: invalid length)", name, len)
+        } else if pos + len > limit {
+            format!(
+                "{} length {} (error: only {} bytes left)",
+                name,
+                len,
+                limit - pos
+            )
+        } else {
+            format!("{} length {}", name, len)
+        };
+        self.line(pos, pos + 4, depth, &text);
+
+        // where to carry on after an entry that can not be read
+        let declared_end = (valid && pos + len <= limit).then_some(pos + len);
+        let limit = declared_end.unwrap_or(limit);
+
+        let mut cur = pos + 4;
+        loop {
+            if cur == limit {
+                let text = format!("error: {} has no end", name);
+                self.line(cur, cur, depth, &text);
+                return declared_end;
+            }
+
+            if self.bytes[cur] == 0 {
+                let text = match declared_end {
+                    Some(end) if end != cur + 1 => format!(
+                        "end of {} (error: length says {} bytes, found {})",
+                        name,
+                        len,
+                        cur + 1 - pos
+                    ),
+                    _ => format!("end of {}", name),
+                };
+                self.line(cur, cur + 1, depth, &text);
+                return Some(cur + 1);
+            }
+
+            match self.entry(cur, limit, depth + 1, map) {
+                Some(end) => cur = end,
+                None => return declared_end,
+            }
+        }
+    }
+
+    /// Annotate the key of a map entry if `map`, then the tag and the value.
+    fn entry(&mut self, mut pos: usize, limit: usize, depth: usize, map: bool) -> Option<usize> {
+        if map {
+            let len = self.bytes[pos] as usize;
+            let end = pos + len;
+
+            if end > limit {
+                return self.truncated(pos, limit, depth, "key", len);
+            }
+
+            let text = match core::str::from_utf8(&self.bytes[pos + 1..end]) {
+                Ok("") => String::from("key \"\" (error: empty key)"),
+                Ok(key) => format!("key {}", Value::String(key.into())),
+                Err(_) => String::from("key (error: invalid UTF-8)"),
+            };
+            self.line(pos, end, depth, &text);
+
+            pos = end;
+        }
+
+        if pos == limit {
+            return self.truncated(pos, limit, depth, "tag", 1);
+        }
+
+        let tag = self.bytes[pos];
+        let Some(data_type) = DataType::from(tag) else {
+            // the size of the value is unknown, nothing after it can be read
+            let text = format!("error: unknown tag {:#04x}", tag);
+            self.line(pos, limit, depth, &text);
+            return None;
+        };
+
+        let text = format!("tag {:?}", data_type);
+        self.line(pos, pos + 1, depth, &text);
+
+        self.value(pos + 1, limit, depth, data_type)
+    }
+
+    /// Annotate the payload of a value, returning where it ends.
+    fn value(
+        &mut self,
+        pos: usize,
+        limit: usize,
+        depth: usize,
+        data_type: DataType,
+    ) -> Option<usize> {
+        let size = match data_type {
+            DataType::Map => return self.container(pos, limit, depth, true),
+            DataType::Array => return self.container(pos, limit, depth, false),
+            DataType::String | DataType::Binary => {
+                return self.sized(pos, limit, depth, data_type);
+            }
+            DataType::Null => 0,
+            DataType::Bool | DataType::I8 | DataType::U8 => 1,
+            DataType::I16 | DataType::U16 => 2,
+            DataType::F32 | DataType::I32 | DataType::U32 => 4,
+            DataType::F64 | DataType::I64 | DataType::U64 | DataType::TimeStamp => 8,
+            DataType::Id => 12,
+        };
+
+        let end = pos + size;
+        if end > limit {
+            return self.truncated(pos, limit, depth, &format!("{:?}", data_type), size);
+        }
+
+        let mut payload = &self.bytes[pos..end];
+        let text = match decode_value_with_tag(&mut payload, data_type as u8) {
+            Ok(value) => format!("{}", value),
+            Err(err) => format!("error: {}", err),
+        };
+        self.line(pos, end, depth, &text);
+
+        Some(end)
+    }
+
+    /// Annotate a string or a binary, a length then the bytes.
+    fn sized(
+        &mut self,
+        pos: usize,
+        limit: usize,
+        depth: usize,
+        data_type: DataType,
+    ) -> Option<usize> {
+        let Some(len) = self.u32_at(pos).filter(|_| pos + 4 <= limit) else {
+            return self.truncated(pos, limit, depth, "length", 4);
+        };
+        let len = len as usize;
+
+        if !(4..=crate::MAX_NSON_SIZE as usize).contains(&len) {
+            let text = format!("length {} (error: invalid length)", len);
+            self.line(pos, limit, depth, &text);
+            return None;
+        }
+
+        self.line(pos, pos + 4, depth, &format!("length {}", len));
+
+        let start = pos + 4;
+        let end = pos + len;
+        if end > limit {
+            let what = format!("{:?}", data_type);
+            return self.truncated(start, limit, depth, &what, len - 4);
+        }
+
+        let text = match data_type {
+            DataType::String => match core::str::from_utf8(&self.bytes[start..end]) {
+                Ok(s) => format!("{}", Value::String(s.into())),
+                Err(_) => String::from("error: invalid UTF-8"),
+            },
+            _ => format!("{} bytes", len - 4),
+        };
+        if end > start {
+            self.line(start, end, depth, &text);
+        }
+
+        Some(end)
+    }
+
+    fn u32_at(&self, pos: usize) -> Option<u32> {
+        let bytes = self.bytes.get(pos..pos + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 3);
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        let _ = write!(out, "{:02x}", b);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::annotate;
+    use crate::m;
+
+    #[test]
+    fn annotate_map() {
+        let bytes = m! {"n": 7u8, "l": [-1i32], "s": "é"}.to_bytes().unwrap();
+
+        let expected = "\
+0000  1f 00 00 00                                      map length 31
+0004  02 6e                                              key \"n\"
+0006  18                                                 tag U8
+0007  07                                                 7u8
+0008  02 6c                                              key \"l\"
+000a  31                                                 tag Array
+000b  0a 00 00 00                                        array length 10
+000f  13                                                   tag I32
+0010  ff ff ff ff                                          -1
+0014  00                                                 end of array
+0015  02 73                                              key \"s\"
+0017  21                                                 tag String
+0018  06 00 00 00                                        length 6
+001c  c3 a9                                              \"é\"
+001e  00                                               end of map
+";
+        assert_eq!(annotate(&bytes), expected);
+    }
+
+    #[test]
+    fn keep_going() {
+        let bytes = m! {"a": [1u8, 2u8], "b": true}.to_bytes().unwrap();
+
+        // an unknown tag in the array, the map goes on after it
+        let mut bad = bytes.clone();
+        bad[11] = 0x99;
+        let text = annotate(&bad);
+        assert!(text.contains(
+            "000b  99 01 18 02 00                                       error: unknown tag 0x99\n"
+        ));
+        assert!(text.contains("key \"b\""));
+        assert!(text.ends_with("end of map\n"));
+
+        // an array length that cuts its last entry
+        let mut bad = bytes.clone();
+        bad[7] = 8;
+        let text = annotate(&bad);
+        assert!(text.contains("error: array has no end"));
+        assert!(text.contains("end of map (error: length says 21 bytes, found 16)"));
+
+        // a second document, then a cut one
+        let mut seq = bytes.clone();
+        seq.extend(m! {}.to_bytes().unwrap());
+        seq.extend(&bytes[..10]);
+        let text = annotate(&seq);
+        assert_eq!(text.matches("map length").count(), 3);
+        assert!(text.contains("(error: only 10 bytes left)"));
+        assert!(text.ends_with("error: array length needs 4 bytes, 3 left\n"));
+
+        // nothing or less than a length
+        let error = "error: map length needs 4 bytes, 0 left\n";
+        assert_eq!(annotate(&[]), alloc::format!("0000  {:47}  {}", "", error));
+        assert!(annotate(&[9, 0]).ends_with("error: map length needs 4 bytes, 2 left\n"));
+    }
+}
@@ -1,189 +1,245 @@
 //! Json
+//!
+//! A `Value` converts to `serde_json::Value` in canonical extended JSON:
+//! `I32`, `F32`, `String`, `Bool`, `Null`, arrays and maps are plain JSON,
+//! every other type is a single entry map naming it, such as
+//! `{"$i64": 5}`, `{"$tim": 1732694400}` or `{"$bin": "AQID"}`. Non-finite
+//! floats are written as strings, `{"$f32": "NaN"}`, and map keys that read
+//! like a marker are escaped with one more `$`. Reading this back with
+//! `Value::try_from` gives the exact same value.
+//!
+//! Plain JSON numbers are read as `I32` or `F32` when they fit exactly and as
+//! `I64`, `U64` or `F64` otherwise, so no precision is lost, and `-0` is the
+//! `F32` negative zero. A marker with a value out of range or of the wrong
+//! kind, such as `{"$u8": 300}`, is an error rather than being truncated.
+//!
+//! [`Value::to_json_with`] also writes [`JsonMode::Relaxed`] and
+//! [`JsonMode::Plain`] JSON, with fewer or no tags, for clients that want
+//! plain numbers and strings.
 
-use alloc::string::String;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
-use serde_json::{self, Map, json};
+use serde_json::{self, Map, Number, json};
 
 use base64::{Engine, engine::general_purpose};
 
 use crate::array::Array;
-use crate::id::Id;
+use crate::json_text::{MAX_SAFE_INTEGER, extended_map, non_finite};
 use crate::map::Map as NsonMap;
-use crate::value::Value;
+use crate::spec::DataType;
+use crate::value::{Value, escape_key};
+
+pub use crate::json_text::{Error, JsonMode, Result};
+
+fn to_json(value: &Value, mode: JsonMode) -> serde_json::Value {
+    use JsonMode::*;
+
+    let safe = |abs: u64| mode == Plain || (mode == Relaxed && abs <= MAX_SAFE_INTEGER);
+
+    match *value {
+        Value::F32(v) if !v.is_finite() => match mode {
+            Plain => json!(null),
+            _ => json!({"$f32": non_finite(v as f64)}),
+        },
+        Value::F32(v) => json!(v),
+        Value::F64(v) if !v.is_finite() => match mode {
+            Plain => json!(null),
+            _ => json!({"$f64": non_finite(v)}),
+        },
+        Value::F64(v) if mode == Canonical => json!({"$f64": v}),
+        Value::F64(v) => json!(v),
+        Value::I32(v) => json!(v),
+        Value::I64(v) if safe(v.unsigned_abs()) => json!(v),
+        Value::I64(v) => json!({"$i64": v}),
+        Value::U64(v) if safe(v) => json!(v),
+        Value::U64(v) => json!({"$u64": v}),
+        Value::U32(v) if mode == Canonical => json!({"$u32": v}),
+        Value::I8(v) if mode == Canonical => json!({"$i8": v}),
+        Value::U8(v) if mode == Canonical => json!({"$u8": v}),
+        Value::I16(v) if mode == Canonical => json!({"$i16": v}),
+        Value::U16(v) if mode == Canonical => json!({"$u16": v}),
+        Value::U32(v) => json!(v),
+        Value::I8(v) => json!(v),
+        Value::U8(v) => json!(v),
+        Value::I16(v) => json!(v),
+        Value::U16(v) => json!(v),
+        Value::String(ref v) => json!(v),
+        Value::Array(ref v) => {
+            let array: Vec<serde_json::Value> = v.iter().map(|v| to_json(v, mode)).collect();
+            json!(array)
+        }
+        Value::Map(ref v) => to_json_map(v, mode),
+        Value::Bool(v) => json!(v),
+        Value::Null => json!(null),
+        Value::Binary(ref v) => {
+            let base64 = general_purpose::STANDARD.encode(&v.0);
+            match mode {
+                Canonical => json!({"$bin": base64}),
+                _ => json!(base64),
+            }
+        }
+        Value::TimeStamp(v) => match mode {
+            Canonical => json!({"$tim": v.0}),
+            _ => json!(v.to_rfc3339()),
+        },
+        Value::Id(v) => match mode {
+            Canonical => json!({"$mid": v.to_hex()}),
+            _ => json!(v.to_hex()),
+        },
+    }
+}
+
+fn to_json_map(map: &NsonMap, mode: JsonMode) -> serde_json::Value {
+    let map: Map<String, serde_json::Value> = map
+        .iter()
+        .map(|(k, v)| {
+            let key = match mode {
+                JsonMode::Plain => k.clone(),
+                _ => escape_key(k).into_owned(),
+            };
+            (key, to_json(v, mode))
+        })
+        .collect();
+
+    json!(map)
+}
 
 impl From<Value> for serde_json::Value {
     fn from(value: Value) -> Self {
-        match value {
-            Value::F32(v) => json!(v),
-            Value::F64(v) => json!({"$f64": v}),
-            Value::I32(v) => json!(v),
-            Value::I64(v) => json!({"$i64": v}),
-            Value::U32(v) => json!({"$u32": v}),
-            Value::U64(v) => json!({"$u64": v}),
-            Value::I8(v) => json!({"$i8": v}),
-            Value::U8(v) => json!({"$u8": v}),
-            Value::I16(v) => json!({"$i16": v}),
-            Value::U16(v) => json!({"$u16": v}),
-            Value::String(v) => json!(v),
-            Value::Array(v) => {
-                let array: Vec<serde_json::Value> = v.into_iter().map(|v| v.into()).collect();
-                json!(array)
-            }
-            Value::Map(v) => {
-                let map: Map<String, serde_json::Value> =
-                    v.into_iter().map(|(k, v)| (k, v.into())).collect();
-                json!(map)
-            }
-            Value::Bool(v) => json!(v),
-            Value::Null => json!(null),
-            Value::Binary(v) => json!({"$bin": general_purpose::STANDARD.encode(v.0)}),
-            Value::TimeStamp(v) => json!({"$tim": v.0}),
-            Value::Id(v) => json!({"$mid": v.to_hex()}),
+        to_json(&value, JsonMode::Canonical)
+    }
+}
+
+/// A plain JSON number, in the narrowest type that holds it exactly.
+fn number(n: &Number) -> Value {
+    if let Some(i) = n.as_i64() {
+        match i32::try_from(i) {
+            Ok(i) => Value::I32(i),
+            Err(_) => Value::I64(i),
+        }
+    } else if let Some(u) = n.as_u64() {
+        Value::U64(u)
+    } else if let Some(f) = n.as_f64() {
+        if (f as f32) as f64 == f {
+            Value::F32(f as f32)
+        } else {
+            Value::F64(f)
         }
+    } else {
+        unreachable!()
     }
 }
 
-impl From<serde_json::Value> for Value {
-    fn from(value: serde_json::Value) -> Self {
-        match value {
-            serde_json::Value::Number(v) => {
-                if let Some(i) = v.as_i64() {
-                    Value::I32(i as i32)
-                } else if let Some(u) = v.as_u64() {
-                    Value::I32(u as i32)
-                } else if let Some(f) = v.as_f64() {
-                    Value::F32(f as f32)
-                } else {
-                    unreachable!()
-                }
-            }
-            serde_json::Value::String(v) => v.into(),
-            serde_json::Value::Bool(v) => v.into(),
-            serde_json::Value::Array(v) => {
-                let array: Vec<Value> = v.into_iter().map(|v| v.into()).collect();
-                Value::Array(Array::from_vec(array))
+/// Read back the output of `to_json`. `Canonical` and `Relaxed` read the
+/// same way, `Plain` leaves `$type` maps and their keys alone.
+fn from_json(json: serde_json::Value, mode: JsonMode) -> Result<Value> {
+    let value = match json {
+        serde_json::Value::Number(n) => number(&n),
+        serde_json::Value::String(v) => v.into(),
+        serde_json::Value::Bool(v) => v.into(),
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Array(v) => {
+            let array = v
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| from_json(v, mode).map_err(|e| e.within(&i.to_string())))
+                .collect::<Result<Vec<Value>>>()?;
+
+            Value::Array(Array::from_vec(array))
+        }
+        serde_json::Value::Object(map) if mode == JsonMode::Plain => {
+            let mut nson = NsonMap::with_capacity(map.len());
+            for (key, value) in map {
+                let value = from_json(value, mode).map_err(|e| e.within(&key))?;
+                nson.insert(key, value);
             }
-            serde_json::Value::Object(map) => {
-                if map.len() == 1 {
-                    let keys: Vec<_> = map.keys().map(|s| s.as_str()).collect();
-
-                    match keys.as_slice() {
-                        ["$tim"] => {
-                            if let Some(v) = map.get("$tim")
-                                && let Some(u) = v.as_u64()
-                            {
-                                return Value::TimeStamp(u.into());
-                            }
-                        }
-                        ["$bin"] => {
-                            if let Some(v) = map.get("$bin")
-                                && let Some(hex) = v.as_str()
-                                && let Ok(bin) = general_purpose::STANDARD.decode(hex)
-                            {
-                                return bin.into();
-                            }
-                        }
-                        ["$mid"] => {
-                            if let Some(v) = map.get("$mid")
-                                && let Some(hex) = v.as_str()
-                                && let Ok(message_id) = Id::with_string(hex)
-                            {
-                                return message_id.into();
-                            }
-                        }
-                        ["$f64"] => {
-                            if let Some(v) = map.get("$f64")
-                                && let Some(f) = v.as_f64()
-                            {
-                                return Value::F64(f);
-                            }
-                        }
-                        ["$i64"] => {
-                            if let Some(v) = map.get("$i64")
-                                && let Some(i) = v.as_i64()
-                            {
-                                return Value::I64(i);
-                            }
-                        }
-                        ["$u32"] => {
-                            if let Some(v) = map.get("$u32")
-                                && let Some(u) = v.as_u64()
-                            {
-                                return Value::U32(u as u32);
-                            }
-                        }
-                        ["$u64"] => {
-                            if let Some(v) = map.get("$u64")
-                                && let Some(u) = v.as_u64()
-                            {
-                                return Value::U64(u);
-                            }
-                        }
-                        ["$i8"] => {
-                            if let Some(v) = map.get("$i8")
-                                && let Some(i) = v.as_i64()
-                            {
-                                return Value::I8(i as i8);
-                            }
-                        }
-                        ["$u8"] => {
-                            if let Some(v) = map.get("$u8")
-                                && let Some(u) = v.as_u64()
-                            {
-                                return Value::U8(u as u8);
-                            }
-                        }
-                        ["$i16"] => {
-                            if let Some(v) = map.get("$i16")
-                                && let Some(i) = v.as_i64()
-                            {
-                                return Value::I16(i as i16);
-                            }
-                        }
-                        ["$u16"] => {
-                            if let Some(v) = map.get("$u16")
-                                && let Some(u) = v.as_u64()
-                            {
-                                return Value::U16(u as u16);
-                            }
-                        }
-                        _ => (),
-                    }
-                }
-
-                let map: NsonMap = map.into_iter().map(|(k, v)| (k, v.into())).collect();
-
-                Value::Map(map)
+
+            Value::Map(nson)
+        }
+        serde_json::Value::Object(map) => {
+            let mut nson = NsonMap::with_capacity(map.len());
+            for (key, value) in map {
+                let value = from_json(value, mode).map_err(|e| e.within(&key))?;
+                nson.insert(key, value);
             }
-            serde_json::Value::Null => Value::Null,
+
+            extended_map(nson)?
         }
+    };
+
+    Ok(value)
+}
+
+impl Value {
+    /// Convert to JSON, writing the types JSON does not have as `mode`
+    /// says. `JsonMode::Canonical` is the same as `From`.
+    pub fn to_json_with(&self, mode: JsonMode) -> serde_json::Value {
+        to_json(self, mode)
+    }
+
+    /// Convert from JSON written in `mode`. Only `Canonical` gives back the
+    /// exact value, the other modes lose the type of numbers, `Id`,
+    /// `TimeStamp` and `Binary`.
+    pub fn from_json_with(json: serde_json::Value, mode: JsonMode) -> Result<Value> {
+        from_json(json, mode)
+    }
+}
+
+impl TryFrom<serde_json::Value> for Value {
+    type Error = Error;
+
+    fn try_from(json: serde_json::Value) -> Result<Value> {
+        from_json(json, JsonMode::Canonical)
     }
 }
 
 impl From<NsonMap> for serde_json::Value {
     fn from(map: NsonMap) -> Self {
-        Value::Map(map).into()
+        to_json_map(&map, JsonMode::Canonical)
     }
 }
 
-impl From<serde_json::Value> for NsonMap {
-    fn from(json: serde_json::Value) -> Self {
-        let value: Value = json.into();
+impl NsonMap {
+    /// See [`Value::to_json_with`].
+    pub fn to_json_with(&self, mode: JsonMode) -> serde_json::Value {
+        to_json_map(self, mode)
+    }
 
-        match value {
-            Value::Map(map) => map,
-            _ => NsonMap::new(),
+    /// See [`Value::from_json_with`], failing if `json` is not an object.
+    pub fn from_json_with(json: serde_json::Value, mode: JsonMode) -> Result<NsonMap> {
+        match from_json(json, mode)? {
+            Value::Map(map) => Ok(map),
+            value => Err(Error::InvalidValue {
+                path: String::new(),
+                expected: DataType::Map,
+                found: format!("{:?}", value.element_type()),
+            }),
         }
     }
 }
 
+impl TryFrom<serde_json::Value> for NsonMap {
+    type Error = Error;
+
+    fn try_from(json: serde_json::Value) -> Result<NsonMap> {
+        NsonMap::from_json_with(json, JsonMode::Canonical)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{Id, TimeStamp, Value, m};
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{Error, JsonMode};
+    use crate::fixture::values;
+    use crate::spec::DataType;
+    use crate::{Array, Binary, Id, Map, TimeStamp, Value, m};
     use serde_json::{self, json};
 
-    use crate::__vec;
+    use crate::vec as __vec;
 
     #[test]
     fn convert_json() {
@@ -223,8 +279,217 @@ mod test {
 
         assert!(json == value);
 
-        let value2: Value = json.into();
+        let value2 = Value::try_from(json).unwrap();
 
         assert!(nson_value == value2);
     }
+
+    #[test]
+    fn escape_keys() {
+        let message = m! {
+            "a": {"$tim": 5u64},
+            "b": {"$mid": "not an id"},
+            "c": {"$$mid": 1i32, "$set": 2i32, "$$x": 3i32},
+            "d": {"$i64": "x"},
+        };
+
+        let json: serde_json::Value = message.clone().into();
+        assert_eq!(
+            json,
+            json!({
+                "a": {"$$tim": {"$u64": 5}},
+                "b": {"$$mid": "not an id"},
+                "c": {"$$$mid": 1, "$set": 2, "$$x": 3},
+                "d": {"$$i64": "x"},
+            })
+        );
+
+        let value = Value::try_from(json).unwrap();
+        assert_eq!(value, Value::Map(message.clone()));
+
+        // serde_json reads numbers back as 64 bits, only the keys matter
+        let text = serde_json::to_string(&message).unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        let map = value.as_map().unwrap();
+        assert_eq!(map.get("a"), message.get("a"));
+        assert_eq!(map.get("b"), message.get("b"));
+        let keys: Vec<_> = map.get_map("c").unwrap().keys().collect();
+        assert_eq!(keys, ["$$mid", "$set", "$$x"]);
+
+        let value = Value::try_from(json!({"$foo": 1, "$$u8": 2})).unwrap();
+        assert_eq!(value, Value::Map(m! {"$foo": 1i32, "$u8": 2i32}));
+    }
+
+    #[test]
+    fn canonical() {
+        let message = values();
+
+        let json: serde_json::Value = message.clone().into();
+        assert_eq!(json["inf"], json!({"$f64": "-Infinity"}));
+
+        let text = serde_json::to_string(&json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(Map::try_from(json).unwrap(), message);
+
+        let json: serde_json::Value = Value::F32(f32::NAN).into();
+        assert_eq!(json, json!({"$f32": "NaN"}));
+        assert!(matches!(Value::try_from(json), Ok(Value::F32(f)) if f.is_nan()));
+    }
+
+    #[test]
+    fn precision() {
+        let value = Value::try_from(json!([
+            1,
+            3_000_000_000i64,
+            -3_000_000_000i64,
+            u64::MAX,
+            0.5,
+            0.1,
+            1e300
+        ]))
+        .unwrap();
+
+        let expected: Vec<Value> = __vec![
+            Value::I32(1),
+            Value::I64(3_000_000_000),
+            Value::I64(-3_000_000_000),
+            Value::U64(u64::MAX),
+            Value::F32(0.5),
+            Value::F64(0.1),
+            Value::F64(1e300),
+        ];
+        assert_eq!(value, Value::Array(Array::from_vec(expected)));
+    }
+
+    #[test]
+    fn invalid_markers() {
+        let err = Value::try_from(json!({"a": [1, {"$u8": 300}]})).unwrap_err();
+        assert_eq!(
+            err,
+            Error::OutOfRange {
+                path: "a.1".into(),
+                expected: DataType::U8,
+                found: "300".into(),
+            }
+        );
+        assert_eq!(err.to_string(), "a.1: 300 does not fit into U8");
+
+        let err = Value::try_from(json!({"$i8": -129})).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::OutOfRange {
+                expected: DataType::I8,
+                ..
+            }
+        ));
+
+        let err = Value::try_from(json!({"$u32": -1})).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::OutOfRange {
+                expected: DataType::U32,
+                ..
+            }
+        ));
+
+        let err = Value::try_from(json!({"$f32": 1e300})).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::OutOfRange {
+                expected: DataType::F32,
+                ..
+            }
+        ));
+
+        let err = Value::try_from(json!({"t": {"$tim": "now"}})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "t: invalid value, expected TimeStamp, found \"now\""
+        );
+
+        let err = Value::try_from(json!({"$i64": 1.5})).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidValue {
+                expected: DataType::I64,
+                ..
+            }
+        ));
+
+        let err = Value::try_from(json!({"$mid": "xyz"})).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidValue {
+                expected: DataType::Id,
+                ..
+            }
+        ));
+
+        let err = Map::try_from(json!([1])).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidValue {
+                expected: DataType::Map,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn modes() {
+        let message = m! {
+            "u8": 7u8,
+            "f64": 0.5f64,
+            "i64": -5i64,
+            "u64": u64::MAX,
+            "nan": f64::NAN,
+            "bin": Binary(__vec![1, 2, 3]),
+            "tim": TimeStamp(1732694400),
+            "id": Id::with_string("016f9dbd9df7f7dc9c86d573").unwrap(),
+            "$tim": {"$u8": 1u8},
+        };
+
+        let json = message.to_json_with(JsonMode::Canonical);
+        assert_eq!(json, serde_json::Value::from(message.clone()));
+        assert_eq!(
+            Map::from_json_with(json, JsonMode::Canonical).unwrap(),
+            message
+        );
+
+        let json = message.to_json_with(JsonMode::Relaxed);
+        assert_eq!(
+            json,
+            json!({
+                "u8": 7,
+                "f64": 0.5,
+                "i64": -5,
+                "u64": {"$u64": u64::MAX},
+                "nan": {"$f64": "NaN"},
+                "bin": "AQID",
+                "tim": "2024-11-27T08:00:00Z",
+                "id": "016f9dbd9df7f7dc9c86d573",
+                "$$tim": {"$$u8": 1},
+            })
+        );
+
+        let json = message.to_json_with(JsonMode::Plain);
+        assert_eq!(
+            json,
+            json!({
+                "u8": 7,
+                "f64": 0.5,
+                "i64": -5,
+                "u64": u64::MAX,
+                "nan": null,
+                "bin": "AQID",
+                "tim": "2024-11-27T08:00:00Z",
+                "id": "016f9dbd9df7f7dc9c86d573",
+                "$tim": {"$u8": 1},
+            })
+        );
+
+        let value = Value::from_json_with(json!({"$u8": 300}), JsonMode::Plain).unwrap();
+        assert_eq!(value, Value::Map(m! {"$u8": 300i32}));
+        assert!(Value::from_json_with(json!({"$u8": 300}), JsonMode::Relaxed).is_err());
+    }
 }
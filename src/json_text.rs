@@ -0,0 +1,978 @@
+//! JSON text
+//!
+//! Reads and writes JSON text directly, without serde or serde_json, so it
+//! is available with only the `alloc` feature. The output is the canonical
+//! extended JSON of the `json` feature: `I32`, `F32`, strings, bools, null,
+//! arrays and maps are plain JSON, every other type is a single entry map
+//! naming it, such as `{"$u8":7}` or `{"$mid":"016f9dbd9df7f7dc9c86d573"}`,
+//! and map keys that read like one get one more `$`.
+//!
+//! ```
+//! use nson::{json_text, m, Value};
+//!
+//! let value = Value::from(m! {"id": 42u64, "name": "probe"});
+//! let text = json_text::to_string(&value);
+//! assert_eq!(text, r#"{"id":{"$u64":42},"name":"probe"}"#);
+//! assert_eq!(json_text::from_str(&text).unwrap(), value);
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::array::Array;
+use crate::id::Id;
+use crate::key::{self, KeyError};
+use crate::map::Map;
+use crate::spec::DataType;
+use crate::value::{Binary, TimeStamp, Value, escape_key, unescape_key};
+
+/// Error returned by [`from_str`], and by the JSON and CBOR conversions of
+/// the `json` and `cbor` features.
+///
+/// For every variant but `Syntax`, `path` is the dotted path of map keys
+/// and array indexes to the offending value, empty for the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The text is not valid JSON, `offset` is the byte where reading
+    /// stopped.
+    Syntax {
+        offset: usize,
+        message: &'static str,
+    },
+    /// A marker or tag holds a number that does not fit its type.
+    OutOfRange {
+        path: String,
+        expected: DataType,
+        found: String,
+    },
+    /// A marker or tag holds a value of the wrong kind, or a malformed
+    /// string.
+    InvalidValue {
+        path: String,
+        expected: DataType,
+        found: String,
+    },
+    /// Something NSON has no type for, such as a CBOR bignum or integer key.
+    Unsupported { path: String, found: String },
+    /// A map key NSON can not encode, such as an empty one. `offset` is the
+    /// byte where the key starts, `path` that of the map holding it.
+    InvalidKey {
+        path: String,
+        offset: usize,
+        error: KeyError,
+    },
+}
+
+impl Error {
+    pub fn path(&self) -> &str {
+        match self {
+            Error::Syntax { .. } => "",
+            Error::OutOfRange { path, .. }
+            | Error::InvalidValue { path, .. }
+            | Error::Unsupported { path, .. }
+            | Error::InvalidKey { path, .. } => path,
+        }
+    }
+
+    /// Prefix the path with `segment`, used when the error comes from a
+    /// nested value.
+    pub(crate) fn within(mut self, segment: &str) -> Error {
+        if let Error::OutOfRange { path, .. }
+        | Error::InvalidValue { path, .. }
+        | Error::Unsupported { path, .. }
+        | Error::InvalidKey { path, .. } = &mut self
+        {
+            if path.is_empty() {
+                *path = segment.into();
+            } else {
+                path.insert(0, '.');
+                path.insert_str(0, segment);
+            }
+        }
+
+        self
+    }
+
+    #[cfg(feature = "cbor")]
+    pub(crate) fn unsupported(found: String) -> Error {
+        Error::Unsupported {
+            path: String::new(),
+            found,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path().is_empty() {
+            write!(f, "{}: ", self.path())?;
+        }
+
+        match self {
+            Error::Syntax { offset, message } => write!(f, "{} at offset {}", message, offset),
+            Error::OutOfRange {
+                expected, found, ..
+            } => write!(f, "{} does not fit into {:?}", found, expected),
+            Error::InvalidValue {
+                expected, found, ..
+            } => write!(f, "invalid value, expected {:?}, found {}", expected, found),
+            Error::Unsupported { found, .. } => write!(f, "unsupported {}", found),
+            Error::InvalidKey { offset, error, .. } => write!(f, "{} at offset {}", error, offset),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// How the types JSON does not have are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonMode {
+    /// Every type but `I32` and `F32` is tagged, e.g. `{"$u8": 7}`, so the
+    /// output reads back to the exact same value.
+    #[default]
+    Canonical,
+    /// Numbers are plain, except for non-finite floats and integers beyond
+    /// 2^53, which JavaScript can not hold. `Id`, `TimeStamp` and `Binary`
+    /// are hex, RFC 3339 and base64 strings.
+    Relaxed,
+    /// No tags at all: numbers are plain, non-finite floats are `null`, and
+    /// `Id`, `TimeStamp` and `Binary` are strings as in `Relaxed`.
+    Plain,
+}
+
+/// Largest integer a JavaScript number holds exactly.
+pub(crate) const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Nesting of maps and arrays allowed when reading, so that deep input is
+/// an error rather than a stack overflow.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+/// Write `value` as compact canonical JSON text.
+pub fn write<W: Write>(writer: &mut W, value: &Value) -> fmt::Result {
+    write_with(writer, value, JsonMode::Canonical)
+}
+
+/// Write `value` as compact JSON text in the given mode.
+pub fn write_with<W: Write>(writer: &mut W, value: &Value, mode: JsonMode) -> fmt::Result {
+    use JsonMode::*;
+
+    let safe = |abs: u64| mode == Plain || (mode == Relaxed && abs <= MAX_SAFE_INTEGER);
+
+    match *value {
+        Value::F32(v) if !v.is_finite() => match mode {
+            Plain => writer.write_str("null"),
+            _ => tagged(writer, "$f32", |w| write_str(w, non_finite(v as f64))),
+        },
+        // the `f64` digits, so it reads back as the same `f32`
+        Value::F32(v) => write!(writer, "{:?}", v as f64),
+        Value::F64(v) if !v.is_finite() => match mode {
+            Plain => writer.write_str("null"),
+            _ => tagged(writer, "$f64", |w| write_str(w, non_finite(v))),
+        },
+        Value::F64(v) if mode == Canonical => tagged(writer, "$f64", |w| write!(w, "{:?}", v)),
+        Value::F64(v) => write!(writer, "{:?}", v),
+        Value::I32(v) => write!(writer, "{}", v),
+        Value::I64(v) if safe(v.unsigned_abs()) => write!(writer, "{}", v),
+        Value::I64(v) => tagged(writer, "$i64", |w| write!(w, "{}", v)),
+        Value::U64(v) if safe(v) => write!(writer, "{}", v),
+        Value::U64(v) => tagged(writer, "$u64", |w| write!(w, "{}", v)),
+        Value::U32(v) if mode == Canonical => tagged(writer, "$u32", |w| write!(w, "{}", v)),
+        Value::I8(v) if mode == Canonical => tagged(writer, "$i8", |w| write!(w, "{}", v)),
+        Value::U8(v) if mode == Canonical => tagged(writer, "$u8", |w| write!(w, "{}", v)),
+        Value::I16(v) if mode == Canonical => tagged(writer, "$i16", |w| write!(w, "{}", v)),
+        Value::U16(v) if mode == Canonical => tagged(writer, "$u16", |w| write!(w, "{}", v)),
+        Value::U32(v) => write!(writer, "{}", v),
+        Value::I8(v) => write!(writer, "{}", v),
+        Value::U8(v) => write!(writer, "{}", v),
+        Value::I16(v) => write!(writer, "{}", v),
+        Value::U16(v) => write!(writer, "{}", v),
+        Value::String(ref v) => write_str(writer, v),
+        Value::Array(ref v) => {
+            writer.write_char('[')?;
+            for (i, v) in v.iter().enumerate() {
+                if i > 0 {
+                    writer.write_char(',')?;
+                }
+                write_with(writer, v, mode)?;
+            }
+            writer.write_char(']')
+        }
+        Value::Map(ref v) => {
+            writer.write_char('{')?;
+            for (i, (k, v)) in v.iter().enumerate() {
+                if i > 0 {
+                    writer.write_char(',')?;
+                }
+                write_key(writer, k, mode)?;
+                write_with(writer, v, mode)?;
+            }
+            writer.write_char('}')
+        }
+        Value::Bool(v) => write!(writer, "{}", v),
+        Value::Null => writer.write_str("null"),
+        Value::Binary(ref v) => {
+            let base64 = |w: &mut W| {
+                w.write_char('"')?;
+                write_base64(w, &v.0)?;
+                w.write_char('"')
+            };
+            match mode {
+                Canonical => tagged(writer, "$bin", base64),
+                _ => base64(writer),
+            }
+        }
+        Value::TimeStamp(v) => match mode {
+            Canonical => tagged(writer, "$tim", |w| write!(w, "{}", v.0)),
+            _ => write_str(writer, &v.to_rfc3339()),
+        },
+        Value::Id(v) => match mode {
+            Canonical => tagged(writer, "$mid", |w| write_str(w, &v.to_hex())),
+            _ => write_str(writer, &v.to_hex()),
+        },
+    }
+}
+
+/// Write a map key and its `:`, escaped unless `mode` is `Plain`.
+pub(crate) fn write_key<W: Write>(writer: &mut W, key: &str, mode: JsonMode) -> fmt::Result {
+    match mode {
+        JsonMode::Plain => write_str(writer, key)?,
+        _ => write_str(writer, &escape_key(key))?,
+    }
+    writer.write_char(':')
+}
+
+/// Format `value` as compact canonical JSON text.
+pub fn to_string(value: &Value) -> String {
+    let mut text = String::new();
+    write(&mut text, value).expect("a String never fails to write");
+    text
+}
+
+/// Read a value from JSON text, such as the output of [`write`].
+///
+/// Plain numbers are read as `I32` or `F32` when they fit exactly and as
+/// `I64`, `U64` or `F64` otherwise. A marker with a value out of range or of
+/// the wrong kind, such as `{"$u8":300}`, is an error.
+pub fn from_str(text: &str) -> Result<Value> {
+    let mut reader = Reader::new(text.as_bytes());
+    let value = reader.value()?;
+
+    reader.whitespace();
+    if reader.peek().is_some() {
+        return Err(reader.error("trailing characters"));
+    }
+
+    Ok(value)
+}
+
+fn tagged<W: Write>(
+    writer: &mut W,
+    tag: &str,
+    payload: impl FnOnce(&mut W) -> fmt::Result,
+) -> fmt::Result {
+    write!(writer, "{{\"{}\":", tag)?;
+    payload(writer)?;
+    writer.write_char('}')
+}
+
+/// `NaN` and the infinities have no JSON number, they are written as these
+/// strings inside a `$f32` or `$f64` marker.
+pub(crate) fn non_finite(f: f64) -> &'static str {
+    if f.is_nan() {
+        "NaN"
+    } else if f > 0.0 {
+        "Infinity"
+    } else {
+        "-Infinity"
+    }
+}
+
+pub(crate) fn write_str<W: Write>(writer: &mut W, s: &str) -> fmt::Result {
+    writer.write_char('"')?;
+
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{8}' => "\\b",
+            '\u{c}' => "\\f",
+            c if (c as u32) < 0x20 => "",
+            _ => continue,
+        };
+
+        writer.write_str(&s[start..i])?;
+        if escape.is_empty() {
+            write!(writer, "\\u{:04x}", c as u32)?;
+        } else {
+            writer.write_str(escape)?;
+        }
+        start = i + c.len_utf8();
+    }
+
+    writer.write_str(&s[start..])?;
+    writer.write_char('"')
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding, as the `json` feature writes `$bin`.
+fn write_base64<W: Write>(writer: &mut W, bytes: &[u8]) -> fmt::Result {
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                writer.write_char(BASE64[(n >> (18 - 6 * i)) as usize & 63] as char)?;
+            } else {
+                writer.write_char('=')?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_base64(text: &str) -> Option<Vec<u8>> {
+    fn sextet(b: u8) -> Option<u32> {
+        let v = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        Some(v as u32)
+    }
+
+    let bytes = text.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(bytes.len() / 4 * 3);
+    for (i, chunk) in bytes.chunks(4).enumerate() {
+        let pad = chunk.iter().rev().take_while(|&&b| b == b'=').count();
+        if pad > 2 || (pad > 0 && (i + 1) * 4 != bytes.len()) {
+            return None;
+        }
+
+        let mut n = 0;
+        for &b in &chunk[..4 - pad] {
+            n = n << 6 | sextet(b)?;
+        }
+        n <<= 6 * pad as u32;
+
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - pad]);
+    }
+
+    Some(out)
+}
+
+/// Read the value of a `$type` marker, `None` if `key` is not one.
+pub(crate) fn marker(key: &str, value: &Value) -> Option<Result<Value>> {
+    let invalid = |expected| Error::InvalidValue {
+        path: String::new(),
+        expected,
+        found: to_string(value),
+    };
+
+    let out_of_range = |expected| Error::OutOfRange {
+        path: String::new(),
+        expected,
+        found: to_string(value),
+    };
+
+    macro_rules! integer {
+        ($variant:ident) => {
+            match *value {
+                Value::I32(i) => i.try_into().ok(),
+                Value::I64(i) => i.try_into().ok(),
+                Value::U64(u) => u.try_into().ok(),
+                _ => return Some(Err(invalid(DataType::$variant))),
+            }
+            .map(Value::$variant)
+            .ok_or_else(|| out_of_range(DataType::$variant))
+        };
+    }
+
+    let float = |expected| match *value {
+        Value::F32(f) => Ok(f as f64),
+        Value::F64(f) => Ok(f),
+        Value::I32(i) => Ok(i as f64),
+        Value::I64(i) => Ok(i as f64),
+        Value::U64(u) => Ok(u as f64),
+        Value::String(ref s) => match s.as_str() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            _ => Err(invalid(expected)),
+        },
+        _ => Err(invalid(expected)),
+    };
+
+    let value = match key {
+        "$f32" => float(DataType::F32).and_then(|f| {
+            let v = f as f32;
+            if f.is_finite() && !v.is_finite() {
+                Err(out_of_range(DataType::F32))
+            } else {
+                Ok(Value::F32(v))
+            }
+        }),
+        "$f64" => float(DataType::F64).map(Value::F64),
+        "$i64" => integer!(I64),
+        "$u32" => integer!(U32),
+        "$u64" => integer!(U64),
+        "$i8" => integer!(I8),
+        "$u8" => integer!(U8),
+        "$i16" => integer!(I16),
+        "$u16" => integer!(U16),
+        "$tim" => match *value {
+            Value::I32(i) => u64::try_from(i).ok(),
+            Value::I64(i) => u64::try_from(i).ok(),
+            Value::U64(u) => Some(u),
+            _ => None,
+        }
+        .map(|u| Value::TimeStamp(TimeStamp(u)))
+        .ok_or_else(|| invalid(DataType::TimeStamp)),
+        "$bin" => value
+            .as_str()
+            .and_then(read_base64)
+            .map(|bin| Value::Binary(Binary(bin)))
+            .ok_or_else(|| invalid(DataType::Binary)),
+        "$mid" => value
+            .as_str()
+            .and_then(|s| Id::with_string(s).ok())
+            .map(Value::Id)
+            .ok_or_else(|| invalid(DataType::Id)),
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// Finish reading a map: a single `$type` entry is the value it names,
+/// any other map has its keys unescaped.
+pub(crate) fn extended_map(map: Map) -> Result<Value> {
+    if map.len() == 1 {
+        let (key, value) = map.get_index(0).unwrap();
+
+        if let Some(value) = marker(key, value) {
+            return value;
+        }
+    }
+
+    if !map.keys().any(|key| unescape_key(key).len() < key.len()) {
+        return Ok(Value::Map(map));
+    }
+
+    Ok(Value::Map(
+        map.into_iter()
+            .map(|(key, value)| (unescape_key(&key).to_owned(), value))
+            .collect(),
+    ))
+}
+
+/// Bytes of JSON text, read one at a time.
+pub(crate) trait Source {
+    fn peek(&mut self) -> Option<u8>;
+
+    /// Move past the byte returned by `peek`.
+    fn bump(&mut self);
+}
+
+impl Source for &[u8] {
+    fn peek(&mut self) -> Option<u8> {
+        self.first().copied()
+    }
+
+    fn bump(&mut self) {
+        *self = &self[1..];
+    }
+}
+
+/// Reads JSON text from a [`Source`], token by token.
+pub(crate) struct Reader<S> {
+    source: S,
+    offset: usize,
+}
+
+impl<S: Source> Reader<S> {
+    pub(crate) fn new(source: S) -> Reader<S> {
+        Reader { source, offset: 0 }
+    }
+
+    pub(crate) fn source(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    pub(crate) fn error(&self, message: &'static str) -> Error {
+        Error::Syntax {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    pub(crate) fn peek(&mut self) -> Option<u8> {
+        self.source.peek()
+    }
+
+    fn bump(&mut self) {
+        self.source.bump();
+        self.offset += 1;
+    }
+
+    pub(crate) fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.bump();
+        }
+    }
+
+    /// Skip whitespace and consume `byte`.
+    pub(crate) fn expect(&mut self, byte: u8, message: &'static str) -> Result<()> {
+        self.whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(message));
+        }
+        self.bump();
+        Ok(())
+    }
+
+    /// Consume `byte` if it is next.
+    pub(crate) fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn value(&mut self) -> Result<Value> {
+        self.nested(0)
+    }
+
+    /// Read a value inside `depth` maps and arrays.
+    fn nested(&mut self, depth: usize) -> Result<Value> {
+        self.whitespace();
+
+        match self.peek() {
+            Some(b'{' | b'[') if depth == MAX_DEPTH => Err(self.error("nested too deeply")),
+            Some(b'{') => self.map(depth + 1),
+            Some(b'[') => self.array(depth + 1),
+            _ => self.scalar(),
+        }
+    }
+
+    /// Read a value that is not a map or an array.
+    pub(crate) fn scalar(&mut self) -> Result<Value> {
+        self.whitespace();
+
+        match self.peek() {
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value> {
+        for &byte in word.as_bytes() {
+            if self.peek() != Some(byte) {
+                return Err(self.error("expected a value"));
+            }
+            self.bump();
+        }
+        Ok(value)
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value> {
+        self.bump();
+        let mut array = Vec::new();
+
+        self.whitespace();
+        if self.eat(b']') {
+            return Ok(Value::Array(Array::from_vec(array)));
+        }
+
+        loop {
+            let value = self
+                .nested(depth)
+                .map_err(|e| e.within(&array.len().to_string()))?;
+            array.push(value);
+
+            self.whitespace();
+            if self.eat(b']') {
+                return Ok(Value::Array(Array::from_vec(array)));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected `,` or `]`"));
+            }
+        }
+    }
+
+    fn map(&mut self, depth: usize) -> Result<Value> {
+        self.bump();
+        let mut map = Map::new();
+
+        self.whitespace();
+        if self.eat(b'}') {
+            return Ok(Value::Map(map));
+        }
+
+        loop {
+            let key = self.key()?;
+            let value = self.nested(depth).map_err(|e| e.within(&key))?;
+            map.insert(key, value);
+
+            self.whitespace();
+            if self.eat(b'}') {
+                break;
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected `,` or `}`"));
+            }
+        }
+
+        extended_map(map)
+    }
+
+    /// Read a map key, still escaped, and the `:` after it. Keys that can
+    /// not be encoded once unescaped, such as `""`, are an error here rather
+    /// than when writing NSON.
+    pub(crate) fn key(&mut self) -> Result<String> {
+        self.whitespace();
+        if self.peek() != Some(b'"') {
+            return Err(self.error("expected a string key"));
+        }
+        let offset = self.offset;
+        let key = self.string()?;
+        if let Err(error) = key::validate(unescape_key(&key)) {
+            return Err(Error::InvalidKey {
+                path: String::new(),
+                offset,
+                error,
+            });
+        }
+        self.expect(b':', "expected `:`")?;
+        Ok(key)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.bump();
+        let mut bytes = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    self.bump();
+                    let c = match self.peek() {
+                        Some(b'u') => {
+                            self.bump();
+                            self.unicode()?
+                        }
+                        byte => {
+                            let c = match byte {
+                                Some(b'"') => '"',
+                                Some(b'\\') => '\\',
+                                Some(b'/') => '/',
+                                Some(b'b') => '\u{8}',
+                                Some(b'f') => '\u{c}',
+                                Some(b'n') => '\n',
+                                Some(b'r') => '\r',
+                                Some(b't') => '\t',
+                                _ => return Err(self.error("invalid escape")),
+                            };
+                            self.bump();
+                            c
+                        }
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                Some(byte) if byte < b' ' => {
+                    return Err(self.error("control character in string"));
+                }
+                Some(byte) => {
+                    self.bump();
+                    bytes.push(byte);
+                }
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+
+        let string = String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8"))?;
+        self.bump();
+        Ok(string)
+    }
+
+    /// Read the hex digits of a `\u` escape, and of the low surrogate that
+    /// follows a high one.
+    fn unicode(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !(self.eat(b'\\') && self.eat(b'u')) {
+                return Err(self.error("unpaired surrogate"));
+            }
+
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let mut code = 0;
+
+        for _ in 0..4 {
+            let digit = self
+                .peek()
+                .and_then(|b| (b as char).to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            self.bump();
+            code = code << 4 | digit;
+        }
+
+        Ok(code)
+    }
+
+    /// Append the digits at the current position to `text`, returning
+    /// whether there was any.
+    fn digits(&mut self, text: &mut String) -> bool {
+        let len = text.len();
+        while let Some(byte @ b'0'..=b'9') = self.peek() {
+            self.bump();
+            text.push(byte as char);
+        }
+        text.len() > len
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.offset;
+        let mut text = String::new();
+
+        if self.eat(b'-') {
+            text.push('-');
+        }
+
+        match self.peek() {
+            Some(b'0') => {
+                self.bump();
+                text.push('0');
+            }
+            Some(b'1'..=b'9') => {
+                self.digits(&mut text);
+            }
+            _ => return Err(self.error("invalid number")),
+        }
+
+        let mut float = false;
+
+        if self.eat(b'.') {
+            float = true;
+            text.push('.');
+            if !self.digits(&mut text) {
+                return Err(self.error("invalid number"));
+            }
+        }
+
+        if let Some(b'e' | b'E') = self.peek() {
+            self.bump();
+            float = true;
+            text.push('e');
+            if let Some(sign @ (b'+' | b'-')) = self.peek() {
+                self.bump();
+                text.push(sign as char);
+            }
+            if !self.digits(&mut text) {
+                return Err(self.error("invalid number"));
+            }
+        }
+
+        // `-0` is read as the float negative zero, as serde_json does
+        if !float && text != "-0" {
+            if let Ok(i) = text.parse::<i64>() {
+                return Ok(match i32::try_from(i) {
+                    Ok(i) => Value::I32(i),
+                    Err(_) => Value::I64(i),
+                });
+            }
+            if let Ok(u) = text.parse::<u64>() {
+                return Ok(Value::U64(u));
+            }
+        }
+
+        // integers past u64 are read as floats, as serde_json does
+        let f: f64 = text.parse().map_err(|_| Error::Syntax {
+            offset: start,
+            message: "invalid number",
+        })?;
+
+        if !f.is_finite() {
+            return Err(Error::Syntax {
+                offset: start,
+                message: "number out of range",
+            });
+        }
+
+        if (f as f32) as f64 == f {
+            Ok(Value::F32(f as f32))
+        } else {
+            Ok(Value::F64(f))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::ToString;
+
+    use super::{Error, KeyError, from_str, to_string};
+    use crate::fixture;
+    use crate::spec::DataType;
+    use crate::{Value, m};
+
+    fn values() -> Value {
+        Value::Map(fixture::values())
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = values();
+        let text = to_string(&value);
+        assert_eq!(from_str(&text).unwrap(), value);
+
+        let value = Value::F32(f32::NAN);
+        assert_eq!(to_string(&value), r#"{"$f32":"NaN"}"#);
+        assert!(matches!(from_str(r#"{"$f32":"NaN"}"#), Ok(Value::F32(f)) if f.is_nan()));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn same_as_serde_json() {
+        let value = values();
+        let json: serde_json::Value = value.clone().into();
+        let text = serde_json::to_string(&json).unwrap();
+
+        // the digits of floats may differ, such as `1e300` and `1e+300`
+        let ours: serde_json::Value = serde_json::from_str(&to_string(&value)).unwrap();
+        assert_eq!(ours, json);
+        assert_eq!(from_str(&text).unwrap(), value);
+
+        let text = r#"{"a": -0, "b": 0, "c": -0.0}"#;
+        let json: serde_json::Value = serde_json::from_str(text).unwrap();
+        assert_eq!(from_str(text).unwrap(), Value::try_from(json).unwrap());
+        assert_eq!(
+            from_str(text).unwrap(),
+            Value::from(m! {"a": -0f32, "b": 0i32, "c": -0f32})
+        );
+    }
+
+    #[test]
+    fn read() {
+        let value = from_str(
+            r#" { "a" : [ 1 , -3000000000 , 18446744073709551615 , 0.5 , 1e2 , 0.1 ] ,
+                 "b" : "\u00e9\ud83e\udd80\/" } "#,
+        )
+        .unwrap();
+
+        let expected = Value::from(m! {
+            "a": [1i32, -3000000000i64, u64::MAX, 0.5f32, 100f32, 0.1f64],
+            "b": "é🦀/",
+        });
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn errors() {
+        let syntax = |text: &str| match from_str(text) {
+            Err(Error::Syntax { offset, message }) => (offset, message),
+            other => panic!("{:?}", other),
+        };
+
+        assert_eq!(syntax(""), (0, "unexpected end of input"));
+        assert_eq!(syntax("[1,]"), (3, "expected a value"));
+        assert_eq!(syntax("{\"a\" 1}"), (5, "expected `:`"));
+        assert_eq!(syntax("[1 2]"), (3, "expected `,` or `]`"));
+        assert_eq!(syntax("01"), (1, "trailing characters"));
+        assert_eq!(syntax("1."), (2, "invalid number"));
+        assert_eq!(syntax("\"abc"), (4, "unterminated string"));
+        assert_eq!(syntax("\"\\ud800\""), (7, "unpaired surrogate"));
+        assert_eq!(syntax("1e999"), (0, "number out of range"));
+        assert_eq!(syntax("tru"), (3, "expected a value"));
+        assert_eq!(syntax("\"\\x\""), (2, "invalid escape"));
+
+        let deep = "[".repeat(100_000);
+        assert_eq!(syntax(&deep), (256, "nested too deeply"));
+        let deep = "{\"a\":".repeat(300);
+        assert_eq!(syntax(&deep), (256 * 5, "nested too deeply"));
+
+        let err = from_str(r#"{"a":[{"":1}]}"#).unwrap_err();
+        assert_eq!(
+            err,
+            Error::InvalidKey {
+                path: "a.0".into(),
+                offset: 7,
+                error: KeyError::Empty,
+            }
+        );
+        assert_eq!(err.to_string(), "a.0: key must not be empty at offset 7");
+        let long = alloc::format!(r#"{{"{}":1}}"#, "k".repeat(255));
+        assert!(matches!(
+            from_str(&long),
+            Err(Error::InvalidKey {
+                error: KeyError::TooLong(255),
+                ..
+            })
+        ));
+        // an escaped key is checked as it will be written
+        assert!(from_str(r#"{"$$u8":1}"#).is_ok());
+
+        let err = from_str(r#"{"a":[1,{"$u8":300}]}"#).unwrap_err();
+        assert_eq!(
+            err,
+            Error::OutOfRange {
+                path: "a.1".into(),
+                expected: DataType::U8,
+                found: "300".into(),
+            }
+        );
+        assert_eq!(err.to_string(), "a.1: 300 does not fit into U8");
+
+        let err = from_str(r#"{"t":{"$tim":"now"}}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "t: invalid value, expected TimeStamp, found \"now\""
+        );
+
+        let err = from_str(r#"{"$bin":"AQI"}"#).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidValue {
+                expected: DataType::Binary,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+//! Key
+//!
+//! NSON map keys are encoded with a one byte length prefix, so they must be
+//! between 1 and [`MAX_KEY_LEN`] bytes long. [`Key`] checks this when it is
+//! created rather than when the map is encoded.
+//!
+//! ```
+//! use nson::Map;
+//! use nson::key::{Key, KeyError, KeyRules};
+//!
+//! let mut map = Map::new();
+//!
+//! assert!(map.try_insert("name", "nson").is_ok());
+//! assert_eq!(map.try_insert("", 1), Err(KeyError::Empty));
+//!
+//! let key = Key::with_rules("$set", &KeyRules::strict());
+//! assert_eq!(key, Err(KeyError::DollarPrefix));
+//! ```
+//!
+//! String literal keys in [`m!`](crate::m) and [`nson!`](crate::nson) are
+//! checked at compile time:
+//!
+//! ```compile_fail
+//! let map = nson::m! { "": 1 };
+//! ```
+
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
+
+use alloc::string::String;
+
+use crate::map::Map;
+use crate::value::Value;
+
+/// The maximum length of a key in bytes.
+pub const MAX_KEY_LEN: usize = u8::MAX as usize - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyError {
+    /// The key is empty.
+    Empty,
+    /// The key is longer than [`MAX_KEY_LEN`] bytes.
+    TooLong(usize),
+    /// The key starts with `$`, which is reserved for extended types.
+    DollarPrefix,
+    /// The key contains a `.`, which is used as a path separator.
+    Dotted,
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeyError::Empty => write!(fmt, "key must not be empty"),
+            KeyError::TooLong(len) => {
+                write!(fmt, "key len {} exceeds {} bytes", len, MAX_KEY_LEN)
+            }
+            KeyError::DollarPrefix => write!(fmt, "key must not start with `$`"),
+            KeyError::Dotted => write!(fmt, "key must not contain `.`"),
+        }
+    }
+}
+
+impl core::error::Error for KeyError {}
+
+/// Optional restrictions on top of the length check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyRules {
+    pub forbid_dollar_prefix: bool,
+    pub forbid_dots: bool,
+}
+
+impl KeyRules {
+    /// Only check the length.
+    pub const fn new() -> KeyRules {
+        KeyRules {
+            forbid_dollar_prefix: false,
+            forbid_dots: false,
+        }
+    }
+
+    /// Also forbid `$`-prefixed and dotted keys.
+    pub const fn strict() -> KeyRules {
+        KeyRules {
+            forbid_dollar_prefix: true,
+            forbid_dots: true,
+        }
+    }
+
+    pub fn check(&self, key: &str) -> Result<(), KeyError> {
+        validate(key)?;
+
+        if self.forbid_dollar_prefix && key.starts_with('$') {
+            return Err(KeyError::DollarPrefix);
+        }
+
+        if self.forbid_dots && key.contains('.') {
+            return Err(KeyError::Dotted);
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that `key` can be encoded.
+pub fn validate(key: &str) -> Result<(), KeyError> {
+    if key.is_empty() {
+        Err(KeyError::Empty)
+    } else if key.len() > MAX_KEY_LEN {
+        Err(KeyError::TooLong(key.len()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Used by the macros to check literal keys at compile time.
+#[doc(hidden)]
+pub const fn literal_key(key: &'static str) -> &'static str {
+    assert!(!key.is_empty(), "nson key must not be empty");
+    assert!(
+        key.len() <= MAX_KEY_LEN,
+        "nson key must not exceed 254 bytes"
+    );
+    key
+}
+
+/// A map key that is known to be encodable.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key(String);
+
+impl Key {
+    pub fn new(key: impl Into<String>) -> Result<Key, KeyError> {
+        Key::with_rules(key, &KeyRules::new())
+    }
+
+    pub fn with_rules(key: impl Into<String>, rules: &KeyRules) -> Result<Key, KeyError> {
+        let key = key.into();
+        rules.check(&key)?;
+        Ok(Key(key))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for Key {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Key {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> String {
+        key.0
+    }
+}
+
+impl TryFrom<&str> for Key {
+    type Error = KeyError;
+
+    fn try_from(key: &str) -> Result<Key, KeyError> {
+        Key::new(key)
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = KeyError;
+
+    fn try_from(key: String) -> Result<Key, KeyError> {
+        Key::new(key)
+    }
+}
+
+impl FromStr for Key {
+    type Err = KeyError;
+
+    fn from_str(key: &str) -> Result<Key, KeyError> {
+        Key::new(key)
+    }
+}
+
+impl Map {
+    /// Insert a key-value pair, rejecting keys that can not be encoded.
+    pub fn try_insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>, KeyError> {
+        let key = key.into();
+        validate(&key)?;
+        Ok(self.insert(key, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::String;
+
+    use super::{Key, KeyError, KeyRules, MAX_KEY_LEN};
+    use crate::map::Map;
+
+    #[test]
+    fn length() {
+        assert_eq!(Key::new(""), Err(KeyError::Empty));
+        assert_eq!(
+            Key::new("a".repeat(MAX_KEY_LEN + 1)),
+            Err(KeyError::TooLong(255))
+        );
+        assert!(Key::new("a".repeat(MAX_KEY_LEN)).is_ok());
+    }
+
+    #[test]
+    fn rules() {
+        let strict = KeyRules::strict();
+
+        assert!(Key::new("$tim").is_ok());
+        assert!(Key::new("a.b").is_ok());
+        assert_eq!(
+            Key::with_rules("$tim", &strict),
+            Err(KeyError::DollarPrefix)
+        );
+        assert_eq!(Key::with_rules("a.b", &strict), Err(KeyError::Dotted));
+        assert_eq!(Key::with_rules("a", &strict).unwrap().as_str(), "a");
+    }
+
+    #[test]
+    fn try_insert() {
+        let mut map = Map::new();
+
+        assert_eq!(map.try_insert("a", 1), Ok(None));
+        assert_eq!(
+            map.try_insert("a".repeat(300), 1),
+            Err(KeyError::TooLong(300))
+        );
+
+        let key = Key::new("b").unwrap();
+        map.insert(key, 2);
+        assert_eq!(map.get_i32("b").unwrap(), 2);
+        assert_eq!(map.len(), 2);
+
+        let s: String = Key::new("c").unwrap().into();
+        assert_eq!(s, "c");
+    }
+}
@@ -54,26 +54,56 @@ extern crate alloc;
 pub use alloc::vec;
 
 mod macros;
+mod ord;
+
+#[cfg(test)]
+mod fixture;
 
 pub mod decode;
 pub mod encode;
 
 pub use array::Array;
+pub use codec::{FromNson, ToNson};
+pub use convert::{FromValue, IntoValue};
 pub use id::Id;
 pub use map::Map;
+pub use number::Number;
 pub use value::{Binary, TimeStamp, Value};
+
+#[cfg(feature = "serde")]
+pub use decode::{from_reader, from_slice};
+#[cfg(feature = "serde")]
+pub use encode::{to_vec, to_writer};
+
+#[cfg(feature = "macros")]
+pub use nson_macros::nson_bytes;
+
+#[cfg(feature = "derive")]
+pub use nson_derive::{FromNson, ToNson};
 pub mod array;
+pub mod codec;
+pub mod convert;
 
 pub mod id;
+pub mod inspect;
+pub mod json_text;
+pub mod key;
 pub mod map;
+pub mod number;
+pub mod redact;
 pub mod spec;
+pub mod text;
+pub mod transcode;
 pub mod value;
 
 #[cfg(feature = "serde")]
 pub mod serde;
 
 #[cfg(feature = "json")]
-mod json;
+pub mod json;
+
+#[cfg(feature = "cbor")]
+pub mod cbor;
 
 #[cfg(not(feature = "std"))]
 pub mod io;
@@ -168,6 +198,396 @@ mod tests {
         assert_eq!(foo, foo3);
     }
 
+    #[test]
+    fn deserialize_ref() {
+        use serde::de::IntoDeserializer;
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Borrowed<'a> {
+            name: &'a str,
+            #[serde(with = "serde_bytes")]
+            data: &'a [u8],
+            id: Id,
+            e: E,
+        }
+
+        let value = crate::Value::from(m! {
+            "name": "sensor",
+            "data": Binary(alloc::vec![1, 2]),
+            "id": Id::new_raw(1, 2, 3),
+            "e": {"N": 3u8},
+        });
+
+        let borrowed: Borrowed = crate::decode::from_nson_ref(&value).unwrap();
+        assert_eq!(borrowed.name, "sensor");
+        assert_eq!(borrowed.data, &[1, 2]);
+        assert_eq!(borrowed.id, Id::new_raw(1, 2, 3));
+        assert_eq!(borrowed.e, E::N(3));
+
+        let map = value.as_map().unwrap();
+        let name: &str = Deserialize::deserialize(map.get("name").unwrap()).unwrap();
+        assert_eq!(name, "sensor");
+
+        let value = crate::Value::from(m! {"M": "x"});
+        let de = IntoDeserializer::<crate::serde::DecodeError>::into_deserializer(value);
+        assert_eq!(E::deserialize(de).unwrap(), E::M("x".to_string()));
+    }
+
+    #[test]
+    fn error_path() {
+        #[derive(Deserialize, Debug)]
+        struct Network {
+            #[allow(dead_code)]
+            retries: u16,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Config {
+            #[allow(dead_code)]
+            network: Network,
+            #[allow(dead_code)]
+            hosts: Vec<String>,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Root {
+            #[allow(dead_code)]
+            config: Config,
+        }
+
+        let value = crate::Value::from(m! {
+            "config": {
+                "network": {"retries": "three"},
+                "hosts": ["a", "b"],
+            },
+        });
+
+        let err = crate::serde::from_nson::<Root>(value.clone()).unwrap_err();
+        assert_eq!(err.path(), "config.network.retries");
+        assert_eq!(err.found(), Some(crate::spec::DataType::String));
+        assert_eq!(
+            err.to_string(),
+            "config.network.retries: expected u16, found String"
+        );
+
+        let err = crate::serde::from_nson_ref::<Root>(&value).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.network.retries: expected u16, found String"
+        );
+
+        let bytes = value.to_bytes().unwrap();
+        let err = crate::from_slice::<Root>(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.network.retries: expected u16, found String"
+        );
+        let err = crate::from_reader::<_, Root>(&bytes[..]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.network.retries: expected u16, found String"
+        );
+
+        let value = crate::Value::from(m! {
+            "config": {
+                "network": {"retries": 3u16},
+                "hosts": ["a", TimeStamp(1)],
+            },
+        });
+
+        let err = crate::serde::from_nson::<Root>(value.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.hosts.1: expected a string, found TimeStamp"
+        );
+
+        let err = crate::serde::from_nson_ref::<Root>(&value).unwrap_err();
+        assert_eq!(err.path(), "config.hosts.1");
+
+        let err = crate::from_slice::<Root>(&value.to_bytes().unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.hosts.1: expected a string, found TimeStamp"
+        );
+    }
+
+    #[test]
+    fn options() {
+        use crate::serde::{DecoderOptions, EncoderOptions, IntegerWidth, UnitVariant};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Mode {
+            Auto,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Reading {
+            count: u64,
+            offset: i32,
+            note: Option<String>,
+            mode: Mode,
+            raw: Vec<u8>,
+        }
+
+        let reading = Reading {
+            count: 300,
+            offset: -5,
+            note: None,
+            mode: Mode::Auto,
+            raw: alloc::vec![1, 2, 3],
+        };
+
+        let value = crate::serde::to_nson(&reading).unwrap();
+        assert_eq!(
+            value,
+            crate::Value::from(m! {
+                "count": 300u64,
+                "offset": -5i32,
+                "note": null,
+                "mode": "Auto",
+                "raw": [1u8, 2u8, 3u8],
+            })
+        );
+
+        let options = EncoderOptions::new()
+            .skip_nulls(true)
+            .integers(IntegerWidth::Compact)
+            .unit_variants(UnitVariant::Map)
+            .bytes_as_binary(true);
+
+        let value = crate::serde::to_nson_with(&reading, options).unwrap();
+        assert_eq!(
+            value,
+            crate::Value::from(m! {
+                "count": 300u16,
+                "offset": -5i8,
+                "mode": {"Auto": null},
+                "raw": Binary(alloc::vec![1, 2, 3]),
+            })
+        );
+
+        let decoded: Reading = crate::serde::from_nson(value.clone()).unwrap();
+        assert_eq!(decoded, reading);
+
+        let mut extra = value.as_map().unwrap().clone();
+        extra.insert("unit", "C");
+        let decoded: Reading = crate::serde::from_nson(extra.clone().into()).unwrap();
+        assert_eq!(decoded, reading);
+
+        let options = DecoderOptions::new().deny_unknown_fields(true);
+        let err = crate::serde::from_nson_with::<Reading>(extra.into(), options).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown field `unit`");
+        let decoded: Reading = crate::serde::from_nson_with(value, options).unwrap();
+        assert_eq!(decoded, reading);
+
+        // an empty `Vec<u8>` stays an empty `Array` and reads back the same
+        let encode = EncoderOptions::new().bytes_as_binary(true);
+        let empty = Reading {
+            raw: Vec::new(),
+            ..reading
+        };
+        let value = crate::serde::to_nson_with(&empty, encode).unwrap();
+        let raw = value.as_map().unwrap().get("raw").unwrap();
+        assert_eq!(raw, &crate::Value::Array(crate::Array::new()));
+        let decoded: Reading = crate::serde::from_nson(value).unwrap();
+        assert_eq!(decoded, empty);
+
+        // map entries are skipped like struct fields
+        let encode = EncoderOptions::new().skip_nulls(true);
+        let mut notes = alloc::collections::BTreeMap::new();
+        notes.insert("a", Some(1u32));
+        notes.insert("b", None);
+        let value = crate::serde::to_nson_with(&notes, encode).unwrap();
+        assert_eq!(value, crate::Value::from(m! {"a": 1u32}));
+    }
+
+    #[test]
+    fn map_keys() {
+        use alloc::collections::BTreeMap;
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Registry {
+            ports: BTreeMap<u32, String>,
+            offsets: BTreeMap<i64, u8>,
+            devices: BTreeMap<Id, u8>,
+            flags: BTreeMap<bool, u8>,
+            codes: BTreeMap<char, u8>,
+        }
+
+        let id = Id::new_raw(1, 2, 3);
+
+        let registry = Registry {
+            ports: [(80, "http".to_string()), (443, "https".to_string())].into(),
+            offsets: [(-1, 1)].into(),
+            devices: [(id, 7)].into(),
+            flags: [(true, 1), (false, 0)].into(),
+            codes: [('a', 1)].into(),
+        };
+
+        let value = crate::serde::to_nson(&registry).unwrap();
+        let map = value.as_map().unwrap();
+        let ports = map.get_map("ports").unwrap();
+        assert_eq!(ports.get_str("443").unwrap(), "https");
+        let devices = map.get_map("devices").unwrap();
+        assert_eq!(devices.get_u8(&id.to_hex()).unwrap(), 7);
+        assert!(map.get_map("flags").unwrap().contains_key("true"));
+
+        let decoded: Registry = crate::serde::from_nson(value.clone()).unwrap();
+        assert_eq!(decoded, registry);
+        let decoded: Registry = crate::serde::from_nson_ref(&value).unwrap();
+        assert_eq!(decoded, registry);
+
+        let bytes = crate::to_vec(&registry).unwrap();
+        assert_eq!(bytes, value.to_bytes().unwrap());
+        let decoded: Registry = crate::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, registry);
+
+        let value = crate::Value::from(m! {"80": 1u8, "x": 2u8});
+        let err = crate::serde::from_nson::<BTreeMap<u32, u8>>(value).unwrap_err();
+        assert_eq!(err.to_string(), "expected u32, found String");
+    }
+
+    #[test]
+    fn escaped_keys() {
+        use alloc::collections::BTreeMap;
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Update {
+            ops: BTreeMap<String, u64>,
+            tags: BTreeMap<String, String>,
+        }
+
+        let update = Update {
+            ops: [("$tim".to_string(), 5)].into(),
+            tags: [
+                ("$$mid".to_string(), "0171253e54db9aef760d5fbd".to_string()),
+                ("$$a".to_string(), "b".to_string()),
+                ("$set".to_string(), "c".to_string()),
+            ]
+            .into(),
+        };
+
+        // User map keys are written as they are, only `Value` and `Map`
+        // write markers.
+        let value = crate::serde::to_nson(&update).unwrap();
+        let map = value.as_map().unwrap();
+        let ops = map.get_map("ops").unwrap();
+        assert_eq!(ops.get_u64("$tim").unwrap(), 5);
+        let tags = map.get_map("tags").unwrap();
+        assert_eq!(tags.get_str("$$mid").unwrap(), "0171253e54db9aef760d5fbd");
+        assert_eq!(tags.get_str("$$a").unwrap(), "b");
+        assert_eq!(tags.get_str("$set").unwrap(), "c");
+
+        let decoded: Update = crate::serde::from_nson(value.clone()).unwrap();
+        assert_eq!(decoded, update);
+        let decoded: Update = crate::serde::from_nson_ref(&value).unwrap();
+        assert_eq!(decoded, update);
+
+        let bytes = crate::to_vec(&update).unwrap();
+        assert_eq!(bytes, value.to_bytes().unwrap());
+        let decoded: Update = crate::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, update);
+
+        // A `Value` holding such a map keeps it as a map.
+        let value = crate::Value::from(m! {"$tim": 5u64});
+        let decoded: BTreeMap<String, u64> = crate::serde::from_nson(value.clone()).unwrap();
+        assert_eq!(decoded, [("$tim".to_string(), 5)].into());
+        let encoded = crate::serde::to_nson(&value).unwrap();
+        assert_eq!(encoded, value);
+        let decoded: crate::Value = crate::serde::from_nson(value.clone()).unwrap();
+        assert_eq!(decoded, value);
+        let decoded: crate::Value = crate::serde::from_nson_ref(&value).unwrap();
+        assert_eq!(decoded, value);
+        let decoded: crate::Value = crate::from_slice(&value.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, value);
+        let decoded: crate::Map = crate::from_slice(&value.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, m! {"$tim": 5u64});
+        assert_eq!(crate::to_vec(&decoded).unwrap(), value.to_bytes().unwrap());
+
+        // While a `TimeStamp` is written as one.
+        let value = crate::serde::to_nson(&m! {"t": TimeStamp(5)}).unwrap();
+        assert_eq!(
+            value.as_map().unwrap().get_timestamp("t").unwrap(),
+            &TimeStamp(5)
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn human_readable() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Record {
+            id: Id,
+            time: TimeStamp,
+            data: Binary,
+        }
+
+        let record = Record {
+            id: Id::with_string("016f9dbd9df7f7dc9c86d573").unwrap(),
+            time: TimeStamp(1732694400),
+            data: Binary(alloc::vec![1, 2, 3]),
+        };
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "id": "016f9dbd9df7f7dc9c86d573",
+                "time": "2024-11-27T08:00:00Z",
+                "data": "AQID",
+            })
+        );
+        assert_eq!(serde_json::from_value::<Record>(json).unwrap(), record);
+
+        for time in [TimeStamp(253402300800), TimeStamp(u64::MAX)] {
+            let text = serde_json::to_string(&time).unwrap();
+            assert_eq!(serde_json::from_str::<TimeStamp>(&text).unwrap(), time);
+        }
+
+        let nson = to_nson(&record).unwrap();
+        assert_eq!(
+            nson,
+            m! {"id": record.id, "time": record.time, "data": record.data.clone()}.into()
+        );
+        assert_eq!(
+            from_bytes::<Record>(&to_bytes(&record).unwrap()).unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn rfc3339() {
+        for secs in [0, 951782400, 1732694400, 4102444799, 253402300800, u64::MAX] {
+            let ts = TimeStamp(secs);
+            assert_eq!(TimeStamp::from_rfc3339(&ts.to_rfc3339()), Some(ts));
+        }
+
+        assert_eq!(TimeStamp(951782400).to_rfc3339(), "2000-02-29T00:00:00Z");
+        assert_eq!(
+            TimeStamp::from_rfc3339("2024-11-27T10:00:00.5+02:00"),
+            Some(TimeStamp(1732694400))
+        );
+        assert_eq!(TimeStamp::from_rfc3339("1969-12-31T23:59:59Z"), None);
+        assert_eq!(TimeStamp::from_rfc3339("2023-02-29T00:00:00Z"), None);
+        assert_eq!(TimeStamp::from_rfc3339("2024-11-27"), None);
+
+        // years past 9999 take more digits
+        assert_eq!(
+            TimeStamp(253402300800).to_rfc3339(),
+            "10000-01-01T00:00:00Z"
+        );
+        assert_eq!(
+            TimeStamp(u64::MAX).to_rfc3339(),
+            "584554051223-11-09T07:00:15Z"
+        );
+        assert_eq!(
+            TimeStamp::from_rfc3339("584554051223-11-09T07:00:16Z"),
+            None
+        );
+        assert_eq!(TimeStamp::from_rfc3339("01000-01-01T00:00:00Z"), None);
+        assert_eq!(TimeStamp::from_rfc3339("999-01-01T00:00:00Z"), None);
+    }
+
     #[test]
     fn binary() {
         let byte = alloc::vec![1u8, 2, 3, 4];
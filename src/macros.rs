@@ -52,6 +52,11 @@ macro_rules! nson {
         $crate::nson!(@array [$($elems,)* $crate::nson!({$($map)*})] $($rest)*)
     };
 
+    // Next element is a typed literal such as `@id("...")`.
+    (@array [$($elems:expr,)*] @ $kind:ident ($($args:tt)*) $($rest:tt)*) => {
+        $crate::nson!(@array [$($elems,)* $crate::nson!(@ $kind ($($args)*))] $($rest)*)
+    };
+
     // Next element is an expression followed by comma.
     (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
         $crate::nson!(@array [$($elems,)* $crate::nson!($next),] $($rest)*)
@@ -80,13 +85,23 @@ macro_rules! nson {
     // Finished.
     (@object $object:ident () () ()) => {};
 
-    // Insert the current entry followed by trailing comma.
+    // Insert the current entry followed by trailing comma. Literal keys are
+    // checked at compile time.
+    (@object $object:ident [$key:literal] ($value:expr) , $($rest:tt)*) => {
+        $object.insert_value(const { $crate::key::literal_key($key) }, $value);
+        $crate::nson!(@object $object () ($($rest)*) ($($rest)*));
+    };
+
     (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
         $object.insert_value(($($key)+), $value);
         $crate::nson!(@object $object () ($($rest)*) ($($rest)*));
     };
 
     // Insert the last entry without trailing comma.
+    (@object $object:ident [$key:literal] ($value:expr)) => {
+        $object.insert_value(const { $crate::key::literal_key($key) }, $value);
+    };
+
     (@object $object:ident [$($key:tt)+] ($value:expr)) => {
         $object.insert_value(($($key)+), $value);
     };
@@ -118,6 +133,15 @@ macro_rules! nson {
         $crate::nson!(@object $object [$($key)+] ($crate::nson!({$($map)*})) $($rest)*);
     };
 
+    // Next value is a typed literal such as `@id("...")`.
+    (@object $object:ident ($($key:tt)+) (=> @ $kind:ident ($($args:tt)*) $($rest:tt)*) $copy:tt) => {
+        $crate::nson!(@object $object [$($key)+] ($crate::nson!(@ $kind ($($args)*))) $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) (: @ $kind:ident ($($args:tt)*) $($rest:tt)*) $copy:tt) => {
+        $crate::nson!(@object $object [$($key)+] ($crate::nson!(@ $kind ($($args)*))) $($rest)*);
+    };
+
     // Next value is an expression followed by comma.
     (@object $object:ident ($($key:tt)+) (=> $value:expr , $($rest:tt)*) $copy:tt) => {
         $crate::nson!(@object $object [$($key)+] ($crate::nson!($value)) , $($rest)*);
@@ -136,6 +160,28 @@ macro_rules! nson {
         $crate::nson!(@object $object [$($key)+] ($crate::nson!($value)));
     };
 
+    // Optional entry, inserted only if the value is `Some`.
+    (@object $object:ident ($($key:tt)+) (? => $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::nson!(@object $object ($($key)+) (? : $value , $($rest)*) $copy);
+    };
+
+    (@object $object:ident ($($key:tt)+) (? => $value:expr) $copy:tt) => {
+        $crate::nson!(@object $object ($($key)+) (? : $value) $copy);
+    };
+
+    (@object $object:ident ($($key:tt)+) (? : $value:expr , $($rest:tt)*) $copy:tt) => {
+        if let core::option::Option::Some(value) = $value {
+            $crate::nson!(@object $object [$($key)+] ($crate::nson!(value)));
+        }
+        $crate::nson!(@object $object () ($($rest)*) ($($rest)*));
+    };
+
+    (@object $object:ident ($($key:tt)+) (? : $value:expr) $copy:tt) => {
+        if let core::option::Option::Some(value) = $value {
+            $crate::nson!(@object $object [$($key)+] ($crate::nson!(value)));
+        }
+    };
+
     // Missing value for last entry. Trigger a reasonable error message.
     (@object $object:ident ($($key:tt)+) (=>) $copy:tt) => {
         // "unexpected end of macro invocation"
@@ -181,6 +227,21 @@ macro_rules! nson {
         $crate::nson!(@object $object ($key) (: $($rest)*) (: $($rest)*));
     };
 
+    // Key is computed: `[expr]: value`.
+    (@object $object:ident () ([$key:expr] $($rest:tt)*) $copy:tt) => {
+        $crate::nson!(@object $object ($key) ($($rest)*) ($($rest)*));
+    };
+
+    // Spread the entries of another map: `..other`.
+    (@object $object:ident () (.. $spread:expr , $($rest:tt)*) $copy:tt) => {
+        $object.extend($spread);
+        $crate::nson!(@object $object () ($($rest)*) ($($rest)*));
+    };
+
+    (@object $object:ident () (.. $spread:expr) $copy:tt) => {
+        $object.extend($spread);
+    };
+
     // Munch a token into the current key.
     (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
         $crate::nson!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
@@ -196,6 +257,33 @@ macro_rules! nson {
         $crate::value::Value::Null
     };
 
+    // Typed literals. Ids and binaries are checked at compile time.
+    (@id ($id:literal)) => {
+        $crate::value::Value::Id(const { $crate::id::Id::literal($id) })
+    };
+
+    (@ts ($ts:expr)) => {
+        $crate::value::Value::TimeStamp($crate::value::TimeStamp($ts))
+    };
+
+    (@bin ($hex:literal)) => {
+        $crate::value::Value::Binary(
+            $crate::value::Binary::from_hex(const { $crate::value::Binary::literal_hex($hex) })
+                .unwrap(),
+        )
+    };
+
+    (@f32 ($v:expr)) => { $crate::value::Value::F32($v) };
+    (@f64 ($v:expr)) => { $crate::value::Value::F64($v) };
+    (@i8 ($v:expr)) => { $crate::value::Value::I8($v) };
+    (@u8 ($v:expr)) => { $crate::value::Value::U8($v) };
+    (@i16 ($v:expr)) => { $crate::value::Value::I16($v) };
+    (@u16 ($v:expr)) => { $crate::value::Value::U16($v) };
+    (@i32 ($v:expr)) => { $crate::value::Value::I32($v) };
+    (@u32 ($v:expr)) => { $crate::value::Value::U32($v) };
+    (@i64 ($v:expr)) => { $crate::value::Value::I64($v) };
+    (@u64 ($v:expr)) => { $crate::value::Value::U64($v) };
+
     ([]) => {
         $crate::value::Value::Array($crate::vec![].into())
     };
@@ -209,7 +297,7 @@ macro_rules! nson {
     };
 
     ({$($tt:tt)+}) => {
-        $crate::value::Value::Map($crate::m!{$($tt)+});
+        $crate::value::Value::Map($crate::m!{$($tt)+})
     };
 
     // Any Serialize type: numbers, strings, struct literals, variables etc.
@@ -254,6 +342,40 @@ macro_rules! nson {
 /// };
 /// # }
 /// ```
+///
+/// Entries marked with `?` are only inserted if the value is `Some`, `..map`
+/// spreads the entries of another map, and `[expr]` computes a key. Typed
+/// literals choose a `Value` variant without casts:
+///
+/// ```rust
+/// # use nson::{m, Value};
+/// #
+/// # fn main() {
+/// let base = m! { "version": 1 };
+/// let nickname: Option<&str> = None;
+/// let field = "level";
+///
+/// let value = m! {
+///     ..base,
+///     "name": "nson",
+///     "nickname"?: nickname,
+///     [field]: @u8(3),
+///     "id": @id("016f9dbd9df7f7dc9c86d573"),
+///     "created": @ts(1_700_000_000),
+///     "magic": @bin("cafe"),
+/// };
+///
+/// assert_eq!(value.len(), 6);
+/// assert_eq!(value.get("level"), Some(&Value::U8(3)));
+/// assert!(!value.contains_key("nickname"));
+/// # }
+/// ```
+///
+/// Malformed `@id` and `@bin` literals are rejected at compile time:
+///
+/// ```compile_fail
+/// let value = nson::m! { "id": @id("016f9dbd") };
+/// ```
 #[macro_export]
 macro_rules! m {
     () => {{ $crate::map::Map::with_capacity(8) }};
@@ -300,3 +422,85 @@ macro_rules! a {
         $crate::nson!(@array [] $($tt)+)
     }};
 }
+
+#[cfg(test)]
+mod test {
+    use alloc::format;
+    use alloc::vec;
+
+    use crate::Id;
+    use crate::value::{Binary, TimeStamp, Value};
+
+    #[test]
+    fn optional_entries() {
+        let some = Some(1);
+        let none: Option<i32> = None;
+
+        let map = m! {
+            "a"?: some,
+            "b"?: none,
+            "c" => 3,
+            "d"? => Some("x")
+        };
+
+        assert_eq!(map, m! {"a": 1, "c": 3, "d": "x"});
+    }
+
+    #[test]
+    fn spreads() {
+        let base = m! {"a": 1, "b": 2};
+
+        let map = m! {
+            ..base.clone(),
+            "b": 3,
+            "c": 4,
+            ..m! {"d": 5}
+        };
+
+        assert_eq!(map, m! {"a": 1, "b": 3, "c": 4, "d": 5});
+        assert_eq!(map.keys().next().map(|k| k.as_str()), Some("a"));
+    }
+
+    #[test]
+    fn computed_keys() {
+        let key = "a";
+        let n = 2;
+
+        let map = m! {
+            [key]: 1,
+            [format!("k{}", n)]: {"x": [1, 2]},
+            [key.to_uppercase()]?: Some(3),
+        };
+
+        assert_eq!(map, m! {"a": 1, "k2": {"x": [1, 2]}, "A": 3});
+    }
+
+    #[test]
+    fn typed_literals() {
+        let map = m! {
+            "id": @id("016f9dbd9df7f7dc9c86d573"),
+            "ts": @ts(123),
+            "bin": @bin("0x00ff"),
+            "u8": @u8(1),
+            "i64": @i64(1),
+            "f32" => @f32(1.5)
+        };
+
+        assert_eq!(
+            map.get("id"),
+            Some(&Value::Id(
+                Id::with_string("016f9dbd9df7f7dc9c86d573").unwrap()
+            ))
+        );
+        assert_eq!(map.get("ts"), Some(&Value::TimeStamp(TimeStamp(123))));
+        assert_eq!(map.get("bin"), Some(&Value::Binary(Binary(vec![0, 255]))));
+        assert_eq!(map.get("u8"), Some(&Value::U8(1)));
+        assert_eq!(map.get("i64"), Some(&Value::I64(1)));
+        assert_eq!(map.get("f32"), Some(&Value::F32(1.5)));
+
+        let array = a![@u16(1), @i8(-1), null, @u64(2)];
+        assert_eq!(array, a![Value::U16(1), Value::I8(-1), null, Value::U64(2)]);
+
+        assert_eq!(nson!(@u32(7)), Value::U32(7));
+    }
+}
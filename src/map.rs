@@ -26,6 +26,7 @@ use hash32::FnvHasher;
 
 use super::array::Array;
 use super::id::Id;
+use super::spec::DataType;
 use super::value::{Binary, TimeStamp, Value};
 
 #[cfg(feature = "std")]
@@ -40,17 +41,123 @@ pub struct Map {
     inner: IndexMap<String, Value, BuildHasherDefault<FnvHasher>>,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+/// Error returned by the typed getters.
+///
+/// `key` is the key (or array index) that was looked up. For nested
+/// conversions, such as an element of a `Vec<T>`, it is the dotted path to
+/// the offending value.
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Error {
-    NotPresent,
-    UnexpectedType,
+    NotPresent {
+        key: String,
+    },
+    UnexpectedType {
+        key: String,
+        expected: DataType,
+        found: DataType,
+    },
+    InvalidLength {
+        key: String,
+        expected: usize,
+        found: usize,
+    },
+    OutOfRange {
+        key: String,
+        expected: DataType,
+        found: DataType,
+    },
+}
+
+impl Error {
+    pub(crate) fn not_present(key: &str) -> Error {
+        Error::NotPresent { key: key.into() }
+    }
+
+    pub(crate) fn unexpected_type(key: &str, expected: DataType, found: &Value) -> Error {
+        Error::UnexpectedType {
+            key: key.into(),
+            expected,
+            found: found.element_type(),
+        }
+    }
+
+    pub(crate) fn out_of_range(key: &str, expected: DataType, found: &Value) -> Error {
+        Error::OutOfRange {
+            key: key.into(),
+            expected,
+            found: found.element_type(),
+        }
+    }
+
+    pub(crate) fn invalid_length(key: &str, expected: usize, found: usize) -> Error {
+        Error::InvalidLength {
+            key: key.into(),
+            expected,
+            found,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            Error::NotPresent { key }
+            | Error::UnexpectedType { key, .. }
+            | Error::InvalidLength { key, .. }
+            | Error::OutOfRange { key, .. } => key,
+        }
+    }
+
+    /// Prefix the key with `segment`, used when the error comes from a
+    /// nested value.
+    pub(crate) fn within(mut self, segment: &str) -> Error {
+        let key = match &mut self {
+            Error::NotPresent { key }
+            | Error::UnexpectedType { key, .. }
+            | Error::InvalidLength { key, .. }
+            | Error::OutOfRange { key, .. } => key,
+        };
+
+        if key.is_empty() {
+            *key = segment.into();
+        } else {
+            key.insert(0, '.');
+            key.insert_str(0, segment);
+        }
+
+        self
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::NotPresent => write!(f, "Key not present"),
-            Error::UnexpectedType => write!(f, "Unexpected type"),
+            Error::NotPresent { key } => write!(f, "Key `{}` not present", key),
+            Error::UnexpectedType {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Unexpected type for key `{}`: expected {:?}, found {:?}",
+                key, expected, found
+            ),
+            Error::InvalidLength {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Invalid length for key `{}`: expected {}, found {}",
+                key, expected, found
+            ),
+            Error::OutOfRange {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Value of key `{}` out of range: {:?} does not fit into {:?}",
+                key, found, expected
+            ),
         }
     }
 }
@@ -230,112 +337,130 @@ impl Map {
     pub fn get_f32(&self, key: &str) -> Result<f32> {
         match self.get(key) {
             Some(&Value::F32(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::F32, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_f64(&self, key: &str) -> Result<f64> {
         match self.get(key) {
             Some(&Value::F64(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::F64, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_i32(&self, key: &str) -> Result<i32> {
         match self.get(key) {
             Some(&Value::I32(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::I32, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_u32(&self, key: &str) -> Result<u32> {
         match self.get(key) {
             Some(&Value::U32(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::U32, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_i64(&self, key: &str) -> Result<i64> {
         match self.get(key) {
             Some(&Value::I64(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::I64, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_u64(&self, key: &str) -> Result<u64> {
         match self.get(key) {
             Some(&Value::U64(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::U64, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_i8(&self, key: &str) -> Result<i8> {
         match self.get(key) {
             Some(&Value::I8(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::I8, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_u8(&self, key: &str) -> Result<u8> {
         match self.get(key) {
             Some(&Value::U8(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::U8, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_i16(&self, key: &str) -> Result<i16> {
         match self.get(key) {
             Some(&Value::I16(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::I16, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_u16(&self, key: &str) -> Result<u16> {
         match self.get(key) {
             Some(&Value::U16(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::U16, v)),
+            None => Err(Error::not_present(key)),
+        }
+    }
+
+    /// Get any integer (or integral float) that fits into an `i64`,
+    /// regardless of the width it was encoded with.
+    pub fn get_int(&self, key: &str) -> Result<i64> {
+        self.get_as(key)
+    }
+
+    /// Get any number as an `f64`, rounding integers that have no exact
+    /// `f64` representation.
+    pub fn get_float(&self, key: &str) -> Result<f64> {
+        match self.get(key) {
+            Some(v) => v
+                .as_number()
+                .map(|n| n.as_f64_lossy())
+                .ok_or_else(|| Error::unexpected_type(key, DataType::F64, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_str(&self, key: &str) -> Result<&str> {
         match self.get(key) {
             Some(Value::String(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::String, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_array(&self, key: &str) -> Result<&Array> {
         match self.get(key) {
             Some(Value::Array(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::Array, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_map(&self, key: &str) -> Result<&Map> {
         match self.get(key) {
             Some(Value::Map(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::Map, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_bool(&self, key: &str) -> Result<bool> {
         match self.get(key) {
             Some(&Value::Bool(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::Bool, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
@@ -346,24 +471,24 @@ impl Map {
     pub fn get_binary(&self, key: &str) -> Result<&Binary> {
         match self.get(key) {
             Some(Value::Binary(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::Binary, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_id(&self, key: &str) -> Result<&Id> {
         match self.get(key) {
             Some(Value::Id(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::Id, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
     pub fn get_timestamp(&self, key: &str) -> Result<&TimeStamp> {
         match self.get(key) {
             Some(Value::TimeStamp(v)) => Ok(v),
-            Some(_) => Err(Error::UnexpectedType),
-            None => Err(Error::NotPresent),
+            Some(v) => Err(Error::unexpected_type(key, DataType::TimeStamp, v)),
+            None => Err(Error::not_present(key)),
         }
     }
 
@@ -414,24 +539,11 @@ impl fmt::Debug for Map {
     }
 }
 
+/// Prints the [`text`](crate::text) notation, `FromStr` reads it back.
+/// `{:#}` indents maps and arrays.
 impl fmt::Display for Map {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "Map{{")?;
-
-        let mut first = true;
-        for (k, v) in self.iter() {
-            if first {
-                first = false;
-            } else {
-                write!(fmt, ", ")?;
-            }
-
-            write!(fmt, "{}: {}", k, v)?;
-        }
-
-        write!(fmt, "}}")?;
-
-        Ok(())
+        crate::text::write_map(fmt, self, fmt.alternate().then_some(0))
     }
 }
 
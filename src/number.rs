@@ -0,0 +1,321 @@
+//! Number
+//!
+//! A width-agnostic view of the numeric variants of [`Value`].
+//!
+//! ```
+//! use nson::Value;
+//!
+//! let n = Value::U32(5).as_number().unwrap();
+//!
+//! assert_eq!(n.to_i64(), Some(5));
+//! assert_eq!(n.to_i8(), Some(5));
+//! assert_eq!(n, Value::I8(5).as_number().unwrap());
+//! assert_eq!(Value::U64(u64::MAX).as_number().unwrap().to_i64(), None);
+//! ```
+
+use core::cmp::Ordering;
+use core::fmt;
+
+use crate::ord::Num;
+use crate::spec::DataType;
+use crate::value::Value;
+
+/// One of the numeric variants of [`Value`].
+///
+/// The `to_*` accessors are lossless: they return `None` if the number is out
+/// of range for the target type, or if a float has a fractional part (or
+/// an integer has no exact float representation).
+///
+/// `PartialEq` and `Ord` compare the mathematical value, so
+/// `I8(1) == U64(1) == F32(1.0)`. `NaN` is equal to itself and greater than
+/// every other number.
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    F32(f32),
+    F64(f64),
+    I32(i32),
+    I64(i64),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+}
+
+macro_rules! checked_int {
+    ($($name:ident: $T:ty;)+) => {
+        $(
+            /// Convert to
+            #[doc = concat!("`", stringify!($T), "`")]
+            /// if the value fits without loss.
+            pub fn $name(&self) -> Option<$T> {
+                self.to_i128().and_then(|v| <$T>::try_from(v).ok())
+            }
+        )+
+    };
+}
+
+impl Number {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Number::F32(_) => DataType::F32,
+            Number::F64(_) => DataType::F64,
+            Number::I32(_) => DataType::I32,
+            Number::I64(_) => DataType::I64,
+            Number::U32(_) => DataType::U32,
+            Number::U64(_) => DataType::U64,
+            Number::I8(_) => DataType::I8,
+            Number::U8(_) => DataType::U8,
+            Number::I16(_) => DataType::I16,
+            Number::U16(_) => DataType::U16,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Number::F32(_) | Number::F64(_))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !self.is_float()
+    }
+
+    fn num(&self) -> Num {
+        match *self {
+            Number::F32(v) => Num::Float(v as f64),
+            Number::F64(v) => Num::Float(v),
+            Number::I32(v) => Num::Int(v as i128),
+            Number::I64(v) => Num::Int(v as i128),
+            Number::U32(v) => Num::Int(v as i128),
+            Number::U64(v) => Num::Int(v as i128),
+            Number::I8(v) => Num::Int(v as i128),
+            Number::U8(v) => Num::Int(v as i128),
+            Number::I16(v) => Num::Int(v as i128),
+            Number::U16(v) => Num::Int(v as i128),
+        }
+    }
+
+    /// Convert to `i128`, which holds every NSON integer. Floats convert if
+    /// they are integral and in range.
+    pub fn to_i128(&self) -> Option<i128> {
+        match self.num() {
+            Num::Int(i) => Some(i),
+            Num::Float(f) => {
+                let i = f as i128;
+                // `as` saturates and maps NaN to 0, so check the round trip.
+                if Num::Int(i).cmp(Num::Float(f)) == Ordering::Equal {
+                    Some(i)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    checked_int! {
+        to_i64: i64;
+        to_u64: u64;
+        to_i32: i32;
+        to_u32: u32;
+        to_i16: i16;
+        to_u16: u16;
+        to_i8: i8;
+        to_u8: u8;
+    }
+
+    /// Convert to `f64` if the value is exactly representable.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self.num() {
+            Num::Float(f) => Some(f),
+            Num::Int(i) => {
+                let f = i as f64;
+                if Num::Int(i).cmp(Num::Float(f)) == Ordering::Equal {
+                    Some(f)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Convert to `f32` if the value is exactly representable.
+    pub fn to_f32(&self) -> Option<f32> {
+        match *self {
+            Number::F32(v) => Some(v),
+            _ => {
+                let f = self.to_f64()?;
+                let narrowed = f as f32;
+                if (narrowed as f64) == f || f.is_nan() {
+                    Some(narrowed)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Convert to `f64`, rounding to the nearest float if needed.
+    pub fn as_f64_lossy(&self) -> f64 {
+        match self.num() {
+            Num::Float(f) => f,
+            Num::Int(i) => i as f64,
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Number) -> Ordering {
+        self.num().cmp(other.num())
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::F32(v) => v.fmt(fmt),
+            Number::F64(v) => v.fmt(fmt),
+            Number::I32(v) => v.fmt(fmt),
+            Number::I64(v) => v.fmt(fmt),
+            Number::U32(v) => v.fmt(fmt),
+            Number::U64(v) => v.fmt(fmt),
+            Number::I8(v) => v.fmt(fmt),
+            Number::U8(v) => v.fmt(fmt),
+            Number::I16(v) => v.fmt(fmt),
+            Number::U16(v) => v.fmt(fmt),
+        }
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Value {
+        match n {
+            Number::F32(v) => Value::F32(v),
+            Number::F64(v) => Value::F64(v),
+            Number::I32(v) => Value::I32(v),
+            Number::I64(v) => Value::I64(v),
+            Number::U32(v) => Value::U32(v),
+            Number::U64(v) => Value::U64(v),
+            Number::I8(v) => Value::I8(v),
+            Number::U8(v) => Value::U8(v),
+            Number::I16(v) => Value::I16(v),
+            Number::U16(v) => Value::U16(v),
+        }
+    }
+}
+
+impl Value {
+    pub fn as_number(&self) -> Option<Number> {
+        Some(match *self {
+            Value::F32(v) => Number::F32(v),
+            Value::F64(v) => Number::F64(v),
+            Value::I32(v) => Number::I32(v),
+            Value::I64(v) => Number::I64(v),
+            Value::U32(v) => Number::U32(v),
+            Value::U64(v) => Number::U64(v),
+            Value::I8(v) => Number::I8(v),
+            Value::U8(v) => Number::U8(v),
+            Value::I16(v) => Number::I16(v),
+            Value::U16(v) => Number::U16(v),
+            _ => return None,
+        })
+    }
+
+    pub fn is_number(&self) -> bool {
+        self.as_number().is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Number;
+    use crate::m;
+    use crate::map::Error;
+    use crate::spec::DataType;
+    use crate::value::Value;
+
+    #[test]
+    fn widening() {
+        let n = Value::I32(5).as_number().unwrap();
+        assert_eq!(n.to_i64(), Some(5));
+        assert_eq!(n.to_u64(), Some(5));
+        assert_eq!(n.to_f64(), Some(5.0));
+        assert_eq!(n.to_i128(), Some(5));
+
+        assert_eq!(Number::I8(-1).to_u64(), None);
+        assert_eq!(Number::U64(u64::MAX).to_i64(), None);
+        assert_eq!(Number::U64(u64::MAX).to_i128(), Some(u64::MAX as i128));
+        assert_eq!(Number::U16(300).to_u8(), None);
+        assert_eq!(Number::U16(300).to_i16(), Some(300));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(Number::F64(2.0).to_i32(), Some(2));
+        assert_eq!(Number::F64(2.5).to_i32(), None);
+        assert_eq!(Number::F64(f64::NAN).to_i32(), None);
+        assert_eq!(Number::F64(1e300).to_i128(), None);
+        assert_eq!(Number::U64((1 << 53) + 1).to_f64(), None);
+        assert_eq!(Number::U64(1 << 53).to_f64(), Some(9007199254740992.0));
+        assert_eq!(Number::F64(0.1).to_f32(), None);
+        assert_eq!(Number::F64(0.5).to_f32(), Some(0.5));
+        assert_eq!(Number::U64(u64::MAX).as_f64_lossy(), 18446744073709551615.0);
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(Number::I8(1), Number::U64(1));
+        assert_eq!(Number::F32(1.0), Number::I16(1));
+        assert!(Number::I64(-1) < Number::U8(0));
+        assert!(Number::U64(u64::MAX) > Number::I64(i64::MAX));
+        assert!(Number::F32(0.5) < Number::U32(1));
+    }
+
+    #[test]
+    fn map_getters() {
+        let map = m! {
+            "a": 1u8,
+            "b": u64::MAX,
+            "c": 1.5f32,
+            "d": 2.0f64,
+            "e": "x",
+        };
+
+        assert_eq!(map.get_int("a"), Ok(1));
+        assert_eq!(
+            map.get_int("b"),
+            Err(Error::OutOfRange {
+                key: "b".into(),
+                expected: DataType::I64,
+                found: DataType::U64,
+            })
+        );
+        assert!(matches!(map.get_int("c"), Err(Error::OutOfRange { .. })));
+        assert_eq!(map.get_int("d"), Ok(2));
+        assert!(matches!(
+            map.get_int("e"),
+            Err(Error::UnexpectedType { .. })
+        ));
+        assert_eq!(map.get_int("f"), Err(Error::NotPresent { key: "f".into() }));
+
+        assert_eq!(map.get_float("a"), Ok(1.0));
+        assert_eq!(map.get_float("c"), Ok(1.5));
+        assert!(matches!(
+            map.get_float("e"),
+            Err(Error::UnexpectedType { .. })
+        ));
+    }
+}
@@ -0,0 +1,343 @@
+//! Ordering, equality and hashing
+//!
+//! `Value` has a total order so it can be used as a `BTreeMap` key, and
+//! `Eq`/`Hash` agree with it so it can be used as a `HashMap` key.
+//!
+//! Values of different kinds are ordered by the following precedence, which
+//! mirrors the one used by BSON:
+//!
+//! 1. `Null`
+//! 2. numbers (`I8` ... `U64`, `F32`, `F64`)
+//! 3. `String`
+//! 4. `Map`
+//! 5. `Array`
+//! 6. `Binary`
+//! 7. `Id`
+//! 8. `Bool`
+//! 9. `TimeStamp`
+//!
+//! Numbers are compared by their mathematical value regardless of width, so
+//! `I8(-1) < U64(0) < F32(0.5) < I32(1)`. Numbers that are mathematically
+//! equal but of a different type are ordered by their [`DataType`](crate::spec::DataType) tag, so
+//! `I32(1)` and `I64(1)` are still distinct values.
+//!
+//! Floats are normalised before they are compared or hashed: every `NaN` is
+//! equal to every other `NaN` of the same width and is greater than any other
+//! number, and `-0.0` is equal to `0.0`.
+//!
+//! Maps are compared ignoring insertion order, consistent with their
+//! `PartialEq`: entries are compared in key order.
+
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::map::Map;
+use crate::value::Value;
+
+/// A number widened to a common representation for comparisons.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Num {
+    Int(i128),
+    Float(f64),
+}
+
+impl Num {
+    pub(crate) fn of(value: &Value) -> Option<Num> {
+        Some(match *value {
+            Value::F32(v) => Num::Float(v as f64),
+            Value::F64(v) => Num::Float(v),
+            Value::I32(v) => Num::Int(v as i128),
+            Value::I64(v) => Num::Int(v as i128),
+            Value::U32(v) => Num::Int(v as i128),
+            Value::U64(v) => Num::Int(v as i128),
+            Value::I8(v) => Num::Int(v as i128),
+            Value::U8(v) => Num::Int(v as i128),
+            Value::I16(v) => Num::Int(v as i128),
+            Value::U16(v) => Num::Int(v as i128),
+            _ => return None,
+        })
+    }
+
+    /// Compare two numbers by their mathematical value.
+    pub(crate) fn cmp(self, other: Num) -> Ordering {
+        match (self, other) {
+            (Num::Int(a), Num::Int(b)) => a.cmp(&b),
+            (Num::Float(a), Num::Float(b)) => normalize_f64(a).total_cmp(&normalize_f64(b)),
+            (Num::Int(a), Num::Float(b)) => cmp_int_float(a, b),
+            (Num::Float(a), Num::Int(b)) => cmp_int_float(b, a).reverse(),
+        }
+    }
+}
+
+fn cmp_int_float(i: i128, f: f64) -> Ordering {
+    // 2^127, the first float outside of the i128 range.
+    const LIMIT: f64 = 170_141_183_460_469_231_731_687_303_715_884_105_728.0;
+
+    if f.is_nan() || f >= LIMIT {
+        return Ordering::Less;
+    }
+
+    if f < -LIMIT {
+        return Ordering::Greater;
+    }
+
+    // Truncates towards zero, and the truncated value is exactly representable.
+    let t = f as i128;
+
+    match i.cmp(&t) {
+        Ordering::Equal => (t as f64).partial_cmp(&f).unwrap_or(Ordering::Equal),
+        o => o,
+    }
+}
+
+pub(crate) fn normalize_f32(f: f32) -> f32 {
+    if f.is_nan() {
+        f32::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+pub(crate) fn normalize_f64(f: f64) -> f64 {
+    if f.is_nan() {
+        f64::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::F32(_)
+        | Value::F64(_)
+        | Value::I32(_)
+        | Value::I64(_)
+        | Value::U32(_)
+        | Value::U64(_)
+        | Value::I8(_)
+        | Value::U8(_)
+        | Value::I16(_)
+        | Value::U16(_) => 1,
+        Value::String(_) => 2,
+        Value::Map(_) => 3,
+        Value::Array(_) => 4,
+        Value::Binary(_) => 5,
+        Value::Id(_) => 6,
+        Value::Bool(_) => 7,
+        Value::TimeStamp(_) => 8,
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::F32(a), Value::F32(b)) => {
+                normalize_f32(*a).to_bits() == normalize_f32(*b).to_bits()
+            }
+            (Value::F64(a), Value::F64(b)) => {
+                normalize_f64(*a).to_bits() == normalize_f64(*b).to_bits()
+            }
+            (Value::I32(a), Value::I32(b)) => a == b,
+            (Value::I64(a), Value::I64(b)) => a == b,
+            (Value::U32(a), Value::U32(b)) => a == b,
+            (Value::U64(a), Value::U64(b)) => a == b,
+            (Value::I8(a), Value::I8(b)) => a == b,
+            (Value::U8(a), Value::U8(b)) => a == b,
+            (Value::I16(a), Value::I16(b)) => a == b,
+            (Value::U16(a), Value::U16(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            (Value::Binary(a), Value::Binary(b)) => a == b,
+            (Value::TimeStamp(a), Value::TimeStamp(b)) => a == b,
+            (Value::Id(a), Value::Id(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        let by_rank = rank(self).cmp(&rank(other));
+        if by_rank != Ordering::Equal {
+            return by_rank;
+        }
+
+        match (self, other) {
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Map(a), Value::Map(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) => a.cmp(b),
+            (Value::Binary(a), Value::Binary(b)) => a.cmp(b),
+            (Value::Id(a), Value::Id(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::TimeStamp(a), Value::TimeStamp(b)) => a.cmp(b),
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (a, b) => match (Num::of(a), Num::of(b)) {
+                (Some(x), Some(y)) => x
+                    .cmp(y)
+                    .then_with(|| (a.element_type() as u8).cmp(&(b.element_type() as u8))),
+                _ => unreachable!(),
+            },
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.element_type() as u8).hash(state);
+
+        match self {
+            Value::F32(v) => normalize_f32(*v).to_bits().hash(state),
+            Value::F64(v) => normalize_f64(*v).to_bits().hash(state),
+            Value::I32(v) => v.hash(state),
+            Value::I64(v) => v.hash(state),
+            Value::U32(v) => v.hash(state),
+            Value::U64(v) => v.hash(state),
+            Value::I8(v) => v.hash(state),
+            Value::U8(v) => v.hash(state),
+            Value::I16(v) => v.hash(state),
+            Value::U16(v) => v.hash(state),
+            Value::String(v) => v.hash(state),
+            Value::Array(v) => v.hash(state),
+            Value::Map(v) => v.hash(state),
+            Value::Bool(v) => v.hash(state),
+            Value::Null => (),
+            Value::Binary(v) => v.hash(state),
+            Value::TimeStamp(v) => v.hash(state),
+            Value::Id(v) => v.hash(state),
+        }
+    }
+}
+
+impl Map {
+    fn sorted_entries(&self) -> Vec<(&String, &Value)> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+}
+
+impl Ord for Map {
+    fn cmp(&self, other: &Map) -> Ordering {
+        self.sorted_entries().cmp(&other.sorted_entries())
+    }
+}
+
+impl PartialOrd for Map {
+    fn partial_cmp(&self, other: &Map) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Map {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted_entries().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::collections::BTreeSet;
+    use alloc::vec;
+
+    use crate::value::{Binary, TimeStamp, Value};
+    use crate::{Id, a, m};
+
+    #[test]
+    fn nan_equality() {
+        assert_eq!(Value::F32(f32::NAN), Value::F32(f32::NAN));
+        assert_eq!(Value::F64(f64::NAN), Value::F64(-f64::NAN));
+        assert_eq!(Value::F64(-0.0), Value::F64(0.0));
+        assert_ne!(Value::F32(f32::NAN), Value::F64(f64::NAN));
+        assert!(Value::F64(f64::NAN) > Value::F64(f64::INFINITY));
+    }
+
+    #[test]
+    fn cross_type_precedence() {
+        let sorted = vec![
+            Value::Null,
+            Value::I8(-1),
+            Value::U64(0),
+            Value::F32(0.5),
+            Value::I32(1),
+            Value::I64(1),
+            Value::String("a".into()),
+            Value::Map(m! {"a": 1}),
+            Value::Array(a![1]),
+            Value::Binary(Binary(vec![1])),
+            Value::Id(Id::zero()),
+            Value::Bool(false),
+            Value::TimeStamp(TimeStamp(0)),
+        ];
+
+        let mut shuffled = sorted.clone();
+        shuffled.reverse();
+        shuffled.sort();
+
+        assert_eq!(shuffled, sorted);
+    }
+
+    #[test]
+    fn wide_numbers() {
+        assert!(Value::U64(u64::MAX) > Value::I64(i64::MAX));
+        assert!(Value::U64(u64::MAX) < Value::F64(1e20));
+        assert!(Value::I64(i64::MIN) > Value::F64(f64::NEG_INFINITY));
+        assert!(Value::I64(2) > Value::F64(1.5));
+        assert!(Value::I64(-2) < Value::F64(-1.5));
+        assert!(Value::F64(-0.0) < Value::I64(0));
+        assert!(Value::I64(-1) < Value::F64(-0.5));
+        assert!(Value::F64(1.0) < Value::U8(1));
+        assert!(Value::I32(1) < Value::I64(1));
+    }
+
+    #[test]
+    fn map_order_ignores_insertion_order() {
+        let m1 = m! {"a": 1, "b": 2};
+        let m2 = m! {"b": 2, "a": 1};
+
+        assert_eq!(m1, m2);
+        assert_eq!(m1.cmp(&m2), core::cmp::Ordering::Equal);
+
+        let set: BTreeSet<Value> = [m1.into(), m2.into()].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hash() {
+        use std::collections::HashSet;
+        use std::vec::Vec;
+
+        let values: Vec<Value> = vec![
+            Value::F64(f64::NAN),
+            Value::F64(-f64::NAN),
+            Value::F32(0.0),
+            Value::F32(-0.0),
+            Value::Map(m! {"a": 1, "b": 2}),
+            Value::Map(m! {"b": 2, "a": 1}),
+            Value::I32(1),
+            Value::I64(1),
+        ];
+
+        let set: HashSet<Value> = values.into_iter().collect();
+        assert_eq!(set.len(), 5);
+    }
+}
@@ -0,0 +1,610 @@
+//! Redact
+//!
+//! Mask fields of a document by path pattern before it is logged or handed
+//! to a third party.
+//!
+//! A pattern is a list of segments separated by `.`. A segment is either a
+//! literal map key (or array index), `*` which matches exactly one segment,
+//! or `**` which matches any number of segments, including none.
+//!
+//! ```
+//! use nson::m;
+//! use nson::redact::{Redaction, Redactor};
+//!
+//! let redactor = Redactor::new()
+//!     .rule("**.password", Redaction::Null)
+//!     .rule("auth.token", Redaction::Remove);
+//!
+//! let mut doc = m! {
+//!     "user": {"name": "dan", "password": "123456"},
+//!     "auth": {"token": "abc", "kind": "bearer"},
+//! };
+//!
+//! redactor.redact_map(&mut doc);
+//!
+//! assert_eq!(doc, m! {
+//!     "user": {"name": "dan", "password": null},
+//!     "auth": {"kind": "bearer"},
+//! });
+//! ```
+
+use core::fmt;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::array::Array;
+use crate::decode::{DecodeError, DecodeResult};
+use crate::encode::encode_value;
+use crate::map::Map;
+use crate::spec::{self, DataType};
+use crate::value::Value;
+
+/// What to do with a value whose path matches a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Redaction {
+    /// Replace the value with `Null`.
+    Null,
+    /// Replace the value with a fixed marker.
+    Replace(Value),
+    /// Replace the value with a hex string of the 64-bit FNV-1a hash of its
+    /// encoded bytes, so equal secrets can still be correlated.
+    ///
+    /// This is for correlation only, it does not protect the value: the hash
+    /// is unkeyed and fast, so anyone can hash a dictionary of guesses and
+    /// recover low-entropy secrets such as passwords, PINs or short tokens.
+    /// Use `Null`, `Replace` or `Remove` for those.
+    ///
+    /// A value too large to encode is replaced with `Null` instead.
+    Hash,
+    /// Remove the entry altogether.
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Any,
+    AnyDepth,
+}
+
+/// A parsed path pattern such as `*.password` or `auth.token`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Pattern {
+        let segments = pattern
+            .split('.')
+            .map(|s| match s {
+                "*" => Segment::Any,
+                "**" => Segment::AnyDepth,
+                _ => Segment::Key(s.to_string()),
+            })
+            .collect();
+
+        Pattern { segments }
+    }
+
+    /// Returns true if `path` is matched by this pattern.
+    pub fn matches<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        matches(&self.segments, path)
+    }
+
+    /// Returns true if some path below `path` could be matched by this pattern.
+    fn may_match_below<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        may_match_below(&self.segments, path)
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(pattern: &str) -> Pattern {
+        Pattern::new(pattern)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                write!(fmt, ".")?;
+            }
+
+            match segment {
+                Segment::Key(key) => write!(fmt, "{}", key)?,
+                Segment::Any => write!(fmt, "*")?,
+                Segment::AnyDepth => write!(fmt, "**")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn matches<S: AsRef<str>>(segments: &[Segment], path: &[S]) -> bool {
+    match segments.split_first() {
+        None => path.is_empty(),
+        Some((Segment::AnyDepth, rest)) => {
+            matches(rest, path) || (!path.is_empty() && matches(segments, &path[1..]))
+        }
+        Some((Segment::Any, rest)) => !path.is_empty() && matches(rest, &path[1..]),
+        Some((Segment::Key(key), rest)) => {
+            !path.is_empty() && path[0].as_ref() == key && matches(rest, &path[1..])
+        }
+    }
+}
+
+fn may_match_below<S: AsRef<str>>(segments: &[Segment], path: &[S]) -> bool {
+    match segments.split_first() {
+        None => false,
+        Some((Segment::AnyDepth, _)) => true,
+        Some(_) if path.is_empty() => true,
+        Some((Segment::Any, rest)) => may_match_below(rest, &path[1..]),
+        Some((Segment::Key(key), rest)) => {
+            path[0].as_ref() == key && may_match_below(rest, &path[1..])
+        }
+    }
+}
+
+/// Applies [`Redaction`]s to values whose path matches a [`Pattern`].
+///
+/// Rules are checked in insertion order and the first match wins. A matched
+/// value is not descended into.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    rules: Vec<(Pattern, Redaction)>,
+}
+
+impl Redactor {
+    pub fn new() -> Redactor {
+        Redactor { rules: Vec::new() }
+    }
+
+    /// Add a rule.
+    pub fn rule(mut self, pattern: impl Into<Pattern>, redaction: Redaction) -> Redactor {
+        self.add_rule(pattern, redaction);
+        self
+    }
+
+    /// Add a rule.
+    pub fn add_rule(&mut self, pattern: impl Into<Pattern>, redaction: Redaction) {
+        self.rules.push((pattern.into(), redaction));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn redaction_for<S: AsRef<str>>(&self, path: &[S]) -> Option<&Redaction> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(_, redaction)| redaction)
+    }
+
+    fn may_match_below<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        self.rules
+            .iter()
+            .any(|(pattern, _)| pattern.may_match_below(path))
+    }
+
+    /// Redact a value in place.
+    pub fn redact(&self, value: &mut Value) {
+        let mut path = Vec::new();
+        self.redact_value(value, &mut path);
+    }
+
+    /// Redact a map in place.
+    pub fn redact_map(&self, map: &mut Map) {
+        let mut path = Vec::new();
+        self.redact_map_at(map, &mut path);
+    }
+
+    /// Redact an array in place.
+    pub fn redact_array(&self, array: &mut Array) {
+        let mut path = Vec::new();
+        self.redact_array_at(array, &mut path);
+    }
+
+    fn redact_value(&self, value: &mut Value, path: &mut Vec<String>) {
+        match value {
+            Value::Map(map) => self.redact_map_at(map, path),
+            Value::Array(array) => self.redact_array_at(array, path),
+            _ => (),
+        }
+    }
+
+    fn redact_map_at(&self, map: &mut Map, path: &mut Vec<String>) {
+        map.retain(|key, value| {
+            path.push(key.clone());
+            let keep = self.redact_entry(value, path);
+            path.pop();
+            keep
+        });
+    }
+
+    fn redact_array_at(&self, array: &mut Array, path: &mut Vec<String>) {
+        let mut index = 0;
+        array.retain_mut(|value| {
+            path.push(index.to_string());
+            index += 1;
+            let keep = self.redact_entry(value, path);
+            path.pop();
+            keep
+        });
+    }
+
+    // Returns false if the entry must be removed.
+    fn redact_entry(&self, value: &mut Value, path: &mut Vec<String>) -> bool {
+        match self.redaction_for(path) {
+            Some(Redaction::Remove) => return false,
+            Some(Redaction::Null) => *value = Value::Null,
+            Some(Redaction::Replace(marker)) => *value = marker.clone(),
+            Some(Redaction::Hash) => {
+                let mut buf = Vec::new();
+                // Encoding into a Vec only fails for oversized values, a
+                // partial encoding would hash alike for different values.
+                *value = match encode_value(&mut buf, value) {
+                    Ok(()) => hash_value(&buf),
+                    Err(_) => Value::Null,
+                };
+            }
+            None => {
+                if self.may_match_below(path) {
+                    self.redact_value(value, path);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Redact an encoded map directly, without decoding it into a [`Map`].
+    ///
+    /// Subtrees that no rule can reach are copied verbatim. Length prefixes
+    /// of the enclosing maps and arrays are rewritten to match the output.
+    pub fn redact_bytes(&self, bytes: &[u8]) -> DecodeResult<Vec<u8>> {
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut path = Vec::new();
+        self.redact_container_bytes(bytes, &mut out, &mut path, true)?;
+        Ok(out)
+    }
+
+    // `bytes` starts at the length prefix of a map (or array) and contains
+    // exactly that container.
+    fn redact_container_bytes(
+        &self,
+        bytes: &[u8],
+        out: &mut Vec<u8>,
+        path: &mut Vec<String>,
+        is_map: bool,
+    ) -> DecodeResult<()> {
+        let len = container_len(bytes)?;
+        let bytes = &bytes[..len];
+
+        let start = out.len();
+        out.extend_from_slice(&[0; 4]);
+
+        let mut pos = 4;
+        let mut index = 0usize;
+
+        loop {
+            let entry_start = pos;
+
+            let key = if is_map {
+                let key_len = *bytes.get(pos).ok_or_else(eof)? as usize;
+                pos += 1;
+
+                if key_len == 0 {
+                    break;
+                }
+
+                let key = bytes.get(pos..pos + key_len - 1).ok_or_else(eof)?;
+                pos += key_len - 1;
+
+                core::str::from_utf8(key)
+                    .map_err(|e| DecodeError::Unknown(e.to_string()))?
+                    .to_string()
+            } else {
+                if *bytes.get(pos).ok_or_else(eof)? == 0 {
+                    pos += 1;
+                    break;
+                }
+
+                let key = index.to_string();
+                index += 1;
+                key
+            };
+
+            let tag_pos = pos;
+            let tag = *bytes.get(pos).ok_or_else(eof)?;
+            pos += 1;
+
+            let payload_len = value_len(tag, &bytes[pos..])?;
+            let end = pos + payload_len;
+            let value = &bytes[tag_pos..end];
+
+            path.push(key);
+
+            match self.redaction_for(path) {
+                Some(Redaction::Remove) => (),
+                Some(redaction) => {
+                    out.extend_from_slice(&bytes[entry_start..tag_pos]);
+
+                    let replacement = match redaction {
+                        Redaction::Replace(marker) => marker.clone(),
+                        Redaction::Hash => hash_value(value),
+                        _ => Value::Null,
+                    };
+
+                    encode_value(out, &replacement)
+                        .map_err(|e| DecodeError::Unknown(e.to_string()))?;
+                }
+                None => {
+                    if (tag == spec::MAP || tag == spec::ARRAY) && self.may_match_below(path) {
+                        out.extend_from_slice(&bytes[entry_start..pos]);
+                        self.redact_container_bytes(&bytes[pos..end], out, path, tag == spec::MAP)?;
+                    } else {
+                        out.extend_from_slice(&bytes[entry_start..end]);
+                    }
+                }
+            }
+
+            path.pop();
+
+            pos = end;
+        }
+
+        out.push(0);
+
+        if pos != len {
+            return Err(DecodeError::InvalidLength(
+                len,
+                format!("container ended after {} bytes", pos),
+            ));
+        }
+
+        let new_len = (out.len() - start) as u32;
+        out[start..start + 4].copy_from_slice(&new_len.to_le_bytes());
+
+        Ok(())
+    }
+
+    /// Wrap a value so that its `Debug` and `Display` output is redacted.
+    pub fn display<'a, T: Redact + Clone>(&'a self, value: &'a T) -> Redacted<'a, T> {
+        Redacted {
+            redactor: self,
+            value,
+        }
+    }
+}
+
+fn eof() -> DecodeError {
+    DecodeError::Unknown("unexpected end of buffer".to_string())
+}
+
+fn read_len(bytes: &[u8]) -> DecodeResult<usize> {
+    let buf = bytes.get(..4).ok_or_else(eof)?;
+    Ok(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize)
+}
+
+fn container_len(bytes: &[u8]) -> DecodeResult<usize> {
+    let len = read_len(bytes)?;
+
+    if len < crate::MIN_NSON_SIZE as usize || len > crate::MAX_NSON_SIZE as usize {
+        return Err(DecodeError::InvalidLength(
+            len,
+            format!("Invalid container length of {}", len),
+        ));
+    }
+
+    if len > bytes.len() {
+        return Err(eof());
+    }
+
+    Ok(len)
+}
+
+// Size in bytes of the value payload that follows `tag`.
+fn value_len(tag: u8, bytes: &[u8]) -> DecodeResult<usize> {
+    let len = match DataType::from(tag) {
+        Some(DataType::Null) => 0,
+        Some(DataType::Bool | DataType::I8 | DataType::U8) => 1,
+        Some(DataType::I16 | DataType::U16) => 2,
+        Some(DataType::F32 | DataType::I32 | DataType::U32) => 4,
+        Some(DataType::F64 | DataType::I64 | DataType::U64 | DataType::TimeStamp) => 8,
+        Some(DataType::Id) => 12,
+        Some(DataType::String | DataType::Binary) => {
+            let len = read_len(bytes)?;
+
+            if len < 4 || len > crate::MAX_NSON_SIZE as usize {
+                return Err(DecodeError::InvalidLength(
+                    len,
+                    format!("Invalid value length of {}", len),
+                ));
+            }
+
+            len
+        }
+        Some(DataType::Map | DataType::Array) => container_len(bytes)?,
+        None => return Err(DecodeError::UnrecognizedElementType(tag)),
+    };
+
+    if len > bytes.len() {
+        return Err(eof());
+    }
+
+    Ok(len)
+}
+
+fn hash_value(encoded: &[u8]) -> Value {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = encoded
+        .iter()
+        .fold(OFFSET, |hash, b| (hash ^ *b as u64).wrapping_mul(PRIME));
+
+    Value::String(format!("{:016x}", hash))
+}
+
+/// Types that a [`Redactor`] can be applied to.
+pub trait Redact {
+    fn redact(&mut self, redactor: &Redactor);
+}
+
+impl Redact for Value {
+    fn redact(&mut self, redactor: &Redactor) {
+        redactor.redact(self)
+    }
+}
+
+impl Redact for Map {
+    fn redact(&mut self, redactor: &Redactor) {
+        redactor.redact_map(self)
+    }
+}
+
+impl Redact for Array {
+    fn redact(&mut self, redactor: &Redactor) {
+        redactor.redact_array(self)
+    }
+}
+
+/// A value formatted through a [`Redactor`], see [`Redactor::display`].
+pub struct Redacted<'a, T: ?Sized> {
+    redactor: &'a Redactor,
+    value: &'a T,
+}
+
+impl<T: Redact + Clone> Redacted<'_, T> {
+    fn redacted(&self) -> T {
+        let mut value = self.value.clone();
+        value.redact(self.redactor);
+        value
+    }
+}
+
+impl<T: Redact + Clone + fmt::Debug> fmt::Debug for Redacted<'_, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.redacted(), fmt)
+    }
+}
+
+impl<T: Redact + Clone + fmt::Display> fmt::Display for Redacted<'_, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.redacted(), fmt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::format;
+    use alloc::string::ToString;
+
+    use super::{Pattern, Redaction, Redactor};
+    use crate::map::Map;
+    use crate::value::Value;
+    use crate::{a, m};
+
+    #[test]
+    fn pattern() {
+        let p = Pattern::new("*.password");
+        assert!(p.matches(&["user", "password"]));
+        assert!(!p.matches(&["password"]));
+        assert!(!p.matches(&["a", "b", "password"]));
+
+        let p = Pattern::new("**.password");
+        assert!(p.matches(&["password"]));
+        assert!(p.matches(&["a", "b", "password"]));
+        assert!(!p.matches(&["a", "password", "b"]));
+
+        assert_eq!(p.to_string(), "**.password");
+    }
+
+    fn document() -> Map {
+        m! {
+            "name": "dan",
+            "auth": {"token": "abc", "kind": "bearer"},
+            "users": [
+                {"name": "a", "password": "1"},
+                {"name": "b", "password": "2"},
+            ],
+            "keys": ["k1", "k2"],
+        }
+    }
+
+    fn redactor() -> Redactor {
+        Redactor::new()
+            .rule("users.*.password", Redaction::Hash)
+            .rule("auth.token", Redaction::Replace("***".into()))
+            .rule("keys.0", Redaction::Remove)
+            .rule("name", Redaction::Null)
+    }
+
+    #[test]
+    fn redact_map() {
+        let mut doc = document();
+        redactor().redact_map(&mut doc);
+
+        assert!(doc.is_null("name"));
+        assert_eq!(
+            doc.get_map("auth").unwrap().get_str("token").unwrap(),
+            "***"
+        );
+        assert_eq!(doc.get_array("keys").unwrap(), &a!["k2"]);
+
+        let users = doc.get_array("users").unwrap();
+        let p1 = users[0].as_map().unwrap().get_str("password").unwrap();
+        let p2 = users[1].as_map().unwrap().get_str("password").unwrap();
+        assert_eq!(p1.len(), 16);
+        assert_ne!(p1, p2);
+        assert_eq!(users[0].as_map().unwrap().get_str("name").unwrap(), "a");
+    }
+
+    #[test]
+    fn hash_oversized() {
+        let blob = crate::Binary(alloc::vec![0; crate::MAX_NSON_SIZE as usize]);
+        let mut doc = m! {"blob": blob};
+        Redactor::new()
+            .rule("blob", Redaction::Hash)
+            .redact_map(&mut doc);
+
+        assert!(doc.is_null("blob"));
+    }
+
+    #[test]
+    fn redact_bytes() {
+        let doc = document();
+        let bytes = doc.to_bytes().unwrap();
+
+        let redacted = redactor().redact_bytes(&bytes).unwrap();
+
+        let mut expected = doc;
+        redactor().redact_map(&mut expected);
+
+        assert_eq!(Map::from_bytes(&redacted).unwrap(), expected);
+        assert_eq!(redacted, expected.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn redact_bytes_truncated() {
+        let bytes = document().to_bytes().unwrap();
+        assert!(redactor().redact_bytes(&bytes[..bytes.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn display() {
+        let redactor = Redactor::new().rule("**.secret", Redaction::Remove);
+        let value = Value::Map(m! {"a": {"secret": 1, "b": 2}});
+
+        assert_eq!(format!("{}", redactor.display(&value)), "{a: {b: 2}}");
+        assert_eq!(
+            format!("{:?}", redactor.display(&value)),
+            format!("{:?}", Value::Map(m! {"a": {"b": 2}}))
+        );
+    }
+}
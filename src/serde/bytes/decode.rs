@@ -0,0 +1,971 @@
+//! Streaming deserializer
+//!
+//! [`BytesDecoder`] reads wire bytes directly instead of decoding a
+//! [`Value`] first. Reading from a slice with [`SliceInput`] hands out
+//! strings and bytes that borrow from it, so `&'de str`, `&'de [u8]` and
+//! `#[serde(borrow)]` fields are zero-copy. [`ReaderInput`] reads from any
+//! reader and hands out owned data instead.
+//!
+//! Unlike decoding into a `Map` first, duplicate keys are passed through as
+//! they come, so a struct rejects them as a duplicate field.
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+#[cfg(not(feature = "std"))]
+use crate::io::{self, Read};
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, IgnoredAny, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+
+use crate::decode::{DecodeError, DecodeResult};
+use crate::id::Id;
+use crate::serde::EXTENDED;
+use crate::serde::decode::Decoder;
+use crate::spec::DataType;
+use crate::value::{TimeStamp, Value, escape_key};
+
+/// Bytes read from an [`Input`], either borrowed from it or copied out.
+#[derive(Clone)]
+pub enum Bytes<'de> {
+    Borrowed(&'de [u8]),
+    Owned(Vec<u8>),
+}
+
+impl<'de> Bytes<'de> {
+    fn visit_str<V: Visitor<'de>>(self, visitor: V) -> DecodeResult<V::Value> {
+        match self {
+            Bytes::Borrowed(bytes) => match core::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => Err(String::from_utf8(bytes.to_vec()).unwrap_err().into()),
+            },
+            Bytes::Owned(bytes) => visitor.visit_string(String::from_utf8(bytes)?),
+        }
+    }
+
+    fn as_str(&self) -> DecodeResult<&str> {
+        let bytes = match self {
+            Bytes::Borrowed(bytes) => bytes,
+            Bytes::Owned(bytes) => bytes.as_slice(),
+        };
+
+        core::str::from_utf8(bytes)
+            .map_err(|_| String::from_utf8(bytes.to_vec()).unwrap_err().into())
+    }
+
+    fn visit_bytes<V: Visitor<'de>>(self, visitor: V) -> DecodeResult<V::Value> {
+        match self {
+            Bytes::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Bytes::Owned(bytes) => visitor.visit_byte_buf(bytes),
+        }
+    }
+}
+
+/// Where a [`BytesDecoder`] reads from.
+pub trait Input<'de> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> DecodeResult<()>;
+
+    fn read_bytes(&mut self, len: usize) -> DecodeResult<Bytes<'de>>;
+
+    fn skip(&mut self, len: usize) -> DecodeResult<()>;
+}
+
+fn unexpected_eof() -> DecodeError {
+    #[cfg(feature = "std")]
+    let err = io::Error::from(io::ErrorKind::UnexpectedEof);
+    #[cfg(not(feature = "std"))]
+    let err = io::Error::UnexpectedEof;
+
+    DecodeError::IoError(err)
+}
+
+/// Reads from a slice, borrowing strings and bytes from it.
+pub struct SliceInput<'de> {
+    slice: &'de [u8],
+}
+
+impl<'de> SliceInput<'de> {
+    pub fn new(slice: &'de [u8]) -> SliceInput<'de> {
+        SliceInput { slice }
+    }
+
+    /// The bytes after the value that has been read.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.slice
+    }
+
+    fn take(&mut self, len: usize) -> DecodeResult<&'de [u8]> {
+        if len > self.slice.len() {
+            return Err(unexpected_eof());
+        }
+
+        let (head, tail) = self.slice.split_at(len);
+        self.slice = tail;
+        Ok(head)
+    }
+}
+
+impl<'de> Input<'de> for SliceInput<'de> {
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> DecodeResult<()> {
+        buf.copy_from_slice(self.take(buf.len())?);
+        Ok(())
+    }
+
+    #[inline]
+    fn read_bytes(&mut self, len: usize) -> DecodeResult<Bytes<'de>> {
+        self.take(len).map(Bytes::Borrowed)
+    }
+
+    #[inline]
+    fn skip(&mut self, len: usize) -> DecodeResult<()> {
+        self.take(len).map(|_| ())
+    }
+}
+
+/// Reads from a reader, copying strings and bytes out of it.
+pub struct ReaderInput<R> {
+    reader: R,
+}
+
+impl<R: Read> ReaderInput<R> {
+    pub fn new(reader: R) -> ReaderInput<R> {
+        ReaderInput { reader }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<'de, R: Read> Input<'de> for ReaderInput<R> {
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> DecodeResult<()> {
+        self.reader.read_exact(buf).map_err(From::from)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> DecodeResult<Bytes<'de>> {
+        let mut buf = alloc::vec![0u8; len];
+        self.reader.read_exact(&mut buf)?;
+        Ok(Bytes::Owned(buf))
+    }
+
+    fn skip(&mut self, len: usize) -> DecodeResult<()> {
+        let mut buf = [0u8; 64];
+        let mut remaining = len;
+
+        while remaining > 0 {
+            let n = remaining.min(buf.len());
+            self.reader.read_exact(&mut buf[..n])?;
+            remaining -= n;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serde Deserializer over wire bytes
+pub struct BytesDecoder<I> {
+    input: I,
+    tag: Option<DataType>,
+}
+
+impl<'de> BytesDecoder<SliceInput<'de>> {
+    pub fn from_slice(slice: &'de [u8]) -> Self {
+        BytesDecoder::new(SliceInput::new(slice))
+    }
+}
+
+impl<R: Read> BytesDecoder<ReaderInput<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        BytesDecoder::new(ReaderInput::new(reader))
+    }
+}
+
+impl<'de, I: Input<'de>> BytesDecoder<I> {
+    pub fn new(input: I) -> BytesDecoder<I> {
+        BytesDecoder { input, tag: None }
+    }
+
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+
+    #[inline]
+    fn read<const N: usize>(&mut self) -> DecodeResult<[u8; N]> {
+        let mut buf = [0; N];
+        self.input.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> DecodeResult<u8> {
+        self.read::<1>().map(|b| b[0])
+    }
+
+    #[inline]
+    fn read_u32(&mut self) -> DecodeResult<u32> {
+        self.read().map(u32::from_le_bytes)
+    }
+
+    /// Take the tag of the next value.
+    fn next_tag(&mut self) -> DecodeResult<DataType> {
+        if let Some(tag) = self.tag.take() {
+            return Ok(tag);
+        }
+
+        let tag = self.read_u8()?;
+        DataType::from(tag).ok_or(DecodeError::UnrecognizedElementType(tag))
+    }
+
+    /// Read the length of a string, binary, map or array, as `decode` does.
+    fn read_len(&mut self, tag: DataType) -> DecodeResult<u32> {
+        let (min, name) = match tag {
+            DataType::String => (crate::MIN_NSON_SIZE - 1, "string"),
+            DataType::Binary => (crate::MIN_NSON_SIZE - 1, "binary"),
+            DataType::Map => (crate::MIN_NSON_SIZE, "map"),
+            _ => (crate::MIN_NSON_SIZE, "array"),
+        };
+
+        let len = self.read_u32()?;
+
+        if len < min || len > crate::MAX_NSON_SIZE {
+            return Err(DecodeError::InvalidLength(
+                len as usize,
+                format!("Invalid {} length of {}", name, len),
+            ));
+        }
+
+        Ok(len)
+    }
+
+    fn read_payload(&mut self, tag: DataType) -> DecodeResult<Bytes<'de>> {
+        let len = self.read_len(tag)?;
+        self.input.read_bytes(len as usize - 4)
+    }
+
+    /// Read the key of the next map entry, `None` at the end of the map.
+    fn read_key(&mut self) -> DecodeResult<Option<Bytes<'de>>> {
+        let len = self.read_u8()?;
+        if len == 0 {
+            return Ok(None);
+        }
+
+        self.input.read_bytes(len as usize - 1).map(Some)
+    }
+
+    fn skip_value(&mut self, tag: DataType) -> DecodeResult<()> {
+        let len = match tag {
+            DataType::Null => 0,
+            DataType::Bool | DataType::I8 | DataType::U8 => 1,
+            DataType::I16 | DataType::U16 => 2,
+            DataType::F32 | DataType::I32 | DataType::U32 => 4,
+            DataType::F64 | DataType::I64 | DataType::U64 | DataType::TimeStamp => 8,
+            DataType::Id => 12,
+            _ => self.read_len(tag)? as usize - 4,
+        };
+
+        self.input.skip(len)
+    }
+
+    /// Visit the elements of an array, skipping any the visitor leaves.
+    fn visit_seq<V: Visitor<'de>>(&mut self, visitor: V) -> DecodeResult<V::Value> {
+        let mut seq = SeqDecoder {
+            de: self,
+            index: 0,
+            done: false,
+        };
+
+        let value = visitor.visit_seq(&mut seq)?;
+        while !seq.done {
+            seq.next_element::<IgnoredAny>()?;
+        }
+
+        Ok(value)
+    }
+
+    /// Visit the entries of a map, skipping any the visitor leaves.
+    fn visit_map<V: Visitor<'de>>(&mut self, visitor: V, escape: bool) -> DecodeResult<V::Value> {
+        let mut map = MapDecoder {
+            de: self,
+            done: false,
+            key: None,
+            escape,
+        };
+
+        let value = visitor.visit_map(&mut map)?;
+        if map.key.is_some() {
+            map.next_value::<IgnoredAny>()?;
+        }
+        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+
+        Ok(value)
+    }
+
+    /// Visit a map, escaping its keys or not, and anything else as
+    /// `deserialize_any` does.
+    fn visit_map_or_any<V: Visitor<'de>>(
+        &mut self,
+        visitor: V,
+        escape: bool,
+    ) -> DecodeResult<V::Value> {
+        match self.next_tag()? {
+            DataType::Map => {
+                self.read_len(DataType::Map)?;
+                self.visit_map(visitor, escape)
+                    .map_err(|e| e.found_in(DataType::Map))
+            }
+            tag => {
+                self.tag = Some(tag);
+                self.deserialize_any(visitor)
+            }
+        }
+    }
+
+    /// Visit a value that serde sees as an extended map, such as `$tim`.
+    fn visit_extended<V: Visitor<'de>>(value: Value, visitor: V) -> DecodeResult<V::Value> {
+        Decoder::new(value)
+            .deserialize_any(visitor)
+            .map_err(DecodeError::Serde)
+    }
+}
+
+macro_rules! forward_to_any {
+    ($($name:ident ( $( $arg:ident : $ty:ty ),* );)*) => {
+        $(
+            #[inline]
+            fn $name<V>(self, $(_: $ty,)* visitor: V) -> DecodeResult<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.deserialize_any(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, I: Input<'de>> Deserializer<'de> for &mut BytesDecoder<I> {
+    type Error = DecodeError;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tag = self.next_tag()?;
+
+        match tag {
+            DataType::F32 => visitor.visit_f32(f32::from_le_bytes(self.read()?)),
+            DataType::F64 => visitor.visit_f64(f64::from_le_bytes(self.read()?)),
+            DataType::I32 => visitor.visit_i32(i32::from_le_bytes(self.read()?)),
+            DataType::I64 => visitor.visit_i64(i64::from_le_bytes(self.read()?)),
+            DataType::U32 => visitor.visit_u32(u32::from_le_bytes(self.read()?)),
+            DataType::U64 => visitor.visit_u64(u64::from_le_bytes(self.read()?)),
+            DataType::I8 => visitor.visit_i8(i8::from_le_bytes(self.read()?)),
+            DataType::U8 => visitor.visit_u8(self.read_u8()?),
+            DataType::I16 => visitor.visit_i16(i16::from_le_bytes(self.read()?)),
+            DataType::U16 => visitor.visit_u16(u16::from_le_bytes(self.read()?)),
+            DataType::String => self.read_payload(DataType::String)?.visit_str(visitor),
+            DataType::Binary => self.read_payload(DataType::Binary)?.visit_bytes(visitor),
+            DataType::Array => {
+                self.read_len(DataType::Array)?;
+                self.visit_seq(visitor)
+            }
+            DataType::Map => {
+                self.read_len(DataType::Map)?;
+                self.visit_map(visitor, true)
+            }
+            DataType::Bool => visitor.visit_bool(self.read_u8()? != 0),
+            DataType::Null => visitor.visit_unit(),
+            DataType::TimeStamp => {
+                let ts = TimeStamp(u64::from_le_bytes(self.read()?));
+                BytesDecoder::<I>::visit_extended(Value::TimeStamp(ts), visitor)
+            }
+            DataType::Id => {
+                let id = Id::with_bytes(self.read()?);
+                BytesDecoder::<I>::visit_extended(Value::Id(id), visitor)
+            }
+        }
+        .map_err(|e| e.found_in(tag))
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_tag()? {
+            DataType::Null => visitor.visit_none(),
+            tag => {
+                self.tag = Some(tag);
+                visitor.visit_some(self)
+            }
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.next_tag()? {
+            DataType::String => {
+                let variant = self.read_payload(DataType::String)?;
+                visitor.visit_enum(EnumDecoder {
+                    de: self,
+                    variant,
+                    unit: true,
+                })
+            }
+            DataType::Map => {
+                self.read_len(DataType::Map)?;
+
+                let variant = self.read_key()?.ok_or_else(|| {
+                    crate::serde::DecodeErrorKind::SyntaxError("expected a variant name".into())
+                })?;
+
+                visitor.visit_enum(EnumDecoder {
+                    de: self,
+                    variant,
+                    unit: false,
+                })
+            }
+            tag => {
+                self.skip_value(tag)?;
+                Err(crate::serde::DecodeErrorKind::InvalidType("an enum".into()).into())
+            }
+        }
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == EXTENDED {
+            return self.visit_map_or_any(visitor, true);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_map_or_any(visitor, false)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tag = self.next_tag()?;
+        self.skip_value(tag)?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit_map_or_any(visitor, false)
+    }
+
+    forward_to_any! {
+        deserialize_bool();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_unit();
+        deserialize_seq();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_tuple(len: usize);
+        deserialize_identifier();
+    }
+}
+
+struct SeqDecoder<'a, I> {
+    de: &'a mut BytesDecoder<I>,
+    index: usize,
+    done: bool,
+}
+
+impl<'de, I: Input<'de>> SeqAccess<'de> for SeqDecoder<'_, I> {
+    type Error = DecodeError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> DecodeResult<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.done {
+            return Ok(None);
+        }
+
+        let tag = self.de.read_u8()?;
+        if tag == 0 {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let tag = DataType::from(tag).ok_or(DecodeError::UnrecognizedElementType(tag))?;
+        self.de.tag = Some(tag);
+        self.index += 1;
+
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|e| e.within(&(self.index - 1).to_string()))
+    }
+}
+
+struct MapDecoder<'a, 'de, I> {
+    de: &'a mut BytesDecoder<I>,
+    done: bool,
+    /// A key has been read but not its value.
+    key: Option<Bytes<'de>>,
+    /// Escape keys with `escape_key`, for `Value` and `Map` but not other
+    /// maps and structs.
+    escape: bool,
+}
+
+impl<'de, I: Input<'de>> MapAccess<'de> for MapDecoder<'_, 'de, I> {
+    type Error = DecodeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> DecodeResult<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.done {
+            return Ok(None);
+        }
+
+        match self.de.read_key()? {
+            Some(mut key) => {
+                self.key = Some(key.clone());
+                if self.escape
+                    && let Cow::Owned(escaped) = escape_key(key.as_str()?)
+                {
+                    key = Bytes::Owned(escaped.into_bytes());
+                }
+                seed.deserialize(KeyDecoder { key }).map(Some)
+            }
+            None => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> DecodeResult<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let key = self.key.take();
+        seed.deserialize(&mut *self.de)
+            .map_err(|e| match key.as_ref().map(Bytes::as_str) {
+                Some(Ok(key)) => e.within(key),
+                _ => e,
+            })
+    }
+}
+
+/// A map key, which is always a string. Integer, bool, `Id`, `TimeStamp`
+/// and `Binary` keys are parsed from their text form.
+struct KeyDecoder<'de> {
+    key: Bytes<'de>,
+}
+
+impl KeyDecoder<'_> {
+    fn key_str(&self) -> DecodeResult<&str> {
+        self.key.as_str()
+    }
+}
+
+impl<'de> Deserializer<'de> for KeyDecoder<'de> {
+    type Error = DecodeError;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.key.visit_str(visitor)
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self)
+    }
+
+    parse_key! {
+        deserialize_bool => visit_bool: bool;
+        deserialize_i8 => visit_i8: i8;
+        deserialize_i16 => visit_i16: i16;
+        deserialize_i32 => visit_i32: i32;
+        deserialize_i64 => visit_i64: i64;
+        deserialize_u8 => visit_u8: u8;
+        deserialize_u16 => visit_u16: u16;
+        deserialize_u32 => visit_u32: u32;
+        deserialize_u64 => visit_u64: u64;
+    }
+
+    forward_to_any! {
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_unit();
+        deserialize_seq();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_map();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_tuple(len: usize);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+}
+
+impl<'de> EnumAccess<'de> for KeyDecoder<'de> {
+    type Error = DecodeError;
+    type Variant = UnitOnly;
+
+    fn variant_seed<V>(self, seed: V) -> DecodeResult<(V::Value, UnitOnly)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self).map(|value| (value, UnitOnly))
+    }
+}
+
+/// The variant of an enum written as a plain string.
+struct UnitOnly;
+
+impl<'de> VariantAccess<'de> for UnitOnly {
+    type Error = DecodeError;
+
+    fn unit_variant(self) -> DecodeResult<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> DecodeResult<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Err(crate::serde::DecodeErrorKind::EndOfStream.into())
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(crate::serde::DecodeErrorKind::EndOfStream.into())
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(crate::serde::DecodeErrorKind::EndOfStream.into())
+    }
+}
+
+/// An enum written as a string, or as a map with a single entry.
+struct EnumDecoder<'a, 'de, I> {
+    de: &'a mut BytesDecoder<I>,
+    variant: Bytes<'de>,
+    unit: bool,
+}
+
+impl<'a, 'de, I: Input<'de>> EnumAccess<'de> for EnumDecoder<'a, 'de, I> {
+    type Error = DecodeError;
+    type Variant = VariantDecoder<'a, I>;
+
+    fn variant_seed<V>(self, seed: V) -> DecodeResult<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(KeyDecoder { key: self.variant })?;
+
+        Ok((
+            value,
+            VariantDecoder {
+                de: self.de,
+                unit: self.unit,
+            },
+        ))
+    }
+}
+
+struct VariantDecoder<'a, I> {
+    de: &'a mut BytesDecoder<I>,
+    unit: bool,
+}
+
+impl<'de, I: Input<'de>> VariantDecoder<'_, I> {
+    fn value_tag(&mut self) -> DecodeResult<DataType> {
+        if self.unit {
+            return Err(crate::serde::DecodeErrorKind::EndOfStream.into());
+        }
+
+        self.de.next_tag()
+    }
+
+    /// The variant map must not have a second entry.
+    fn end(&mut self) -> DecodeResult<()> {
+        match self.de.read_key()? {
+            None => Ok(()),
+            Some(_) => Err(crate::serde::DecodeErrorKind::InvalidType(
+                "a single key:value pair".into(),
+            )
+            .into()),
+        }
+    }
+}
+
+impl<'de, I: Input<'de>> VariantAccess<'de> for VariantDecoder<'_, I> {
+    type Error = DecodeError;
+
+    fn unit_variant(mut self) -> DecodeResult<()> {
+        if self.unit {
+            return Ok(());
+        }
+
+        de::Deserialize::deserialize(&mut *self.de).map(|IgnoredAny| ())?;
+        self.end()
+    }
+
+    fn newtype_variant_seed<T>(mut self, seed: T) -> DecodeResult<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let tag = self.value_tag()?;
+        self.de.tag = Some(tag);
+
+        let value = seed.deserialize(&mut *self.de)?;
+        self.end()?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(mut self, _len: usize, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.value_tag()? != DataType::Array {
+            return Err(crate::serde::DecodeErrorKind::InvalidType("a tuple".into()).into());
+        }
+
+        let value = if self.de.read_len(DataType::Array)? == crate::MIN_NSON_SIZE {
+            self.de.read_u8()?;
+            visitor.visit_unit::<DecodeError>()?
+        } else {
+            self.de.visit_seq(visitor)?
+        };
+
+        self.end()?;
+        Ok(value)
+    }
+
+    fn struct_variant<V>(
+        mut self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.value_tag()? != DataType::Map {
+            return Err(crate::serde::DecodeErrorKind::InvalidType("a struct".into()).into());
+        }
+
+        self.de.read_len(DataType::Map)?;
+        let value = self.de.visit_map(visitor, false)?;
+
+        self.end()?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::borrow::Cow;
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::decode::{from_bytes, from_reader, from_slice};
+    use crate::encode::to_vec;
+    use crate::id::Id;
+    use crate::value::{Binary, TimeStamp, Value};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum E {
+        Unit,
+        New(i8),
+        Tuple(u16, f32),
+        Struct { x: String },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Owned {
+        a: i32,
+        b: Option<u64>,
+        c: Option<String>,
+        #[serde(with = "serde_bytes")]
+        d: Vec<u8>,
+        enums: Vec<E>,
+        map: BTreeMap<String, f64>,
+        tuple: (bool, char),
+        id: Id,
+        time: TimeStamp,
+        bin: Binary,
+        value: Value,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Borrowed<'a> {
+        name: &'a str,
+        #[serde(with = "serde_bytes")]
+        data: &'a [u8],
+        tags: Vec<&'a str>,
+        #[serde(borrow)]
+        label: Cow<'a, str>,
+    }
+
+    fn owned() -> Owned {
+        Owned {
+            a: -1,
+            b: None,
+            c: Some("c".to_string()),
+            d: vec![1, 2, 3],
+            enums: vec![
+                E::Unit,
+                E::New(3),
+                E::Tuple(4, 5.0),
+                E::Struct { x: "y".to_string() },
+            ],
+            map: [("k".to_string(), 1.5)].into_iter().collect(),
+            tuple: (true, 'x'),
+            id: Id::new_raw(1, 2, 3),
+            time: TimeStamp(123),
+            bin: Binary(vec![4, 5]),
+            value: crate::m! {"a": [1, "b", null], "c": {"d": 1u8}}.into(),
+        }
+    }
+
+    #[test]
+    fn same_as_value_path() {
+        let bytes = to_vec(&owned()).unwrap();
+
+        let expected: Owned = from_bytes(&bytes).unwrap();
+        assert_eq!(expected, owned());
+
+        assert_eq!(from_slice::<Owned>(&bytes).unwrap(), expected);
+        assert_eq!(from_reader::<_, Owned>(&bytes[..]).unwrap(), expected);
+    }
+
+    #[test]
+    fn borrow_from_slice() {
+        let value = Borrowed {
+            name: "sensor",
+            data: &[1, 2, 3],
+            tags: vec!["a", "b"],
+            label: "label".into(),
+        };
+
+        let bytes = to_vec(&value).unwrap();
+        let decoded: Borrowed = from_slice(&bytes).unwrap();
+
+        assert_eq!(decoded, value);
+        assert!(bytes.as_ptr_range().contains(&decoded.name.as_ptr()));
+        assert!(bytes.as_ptr_range().contains(&decoded.data.as_ptr()));
+        assert!(bytes.as_ptr_range().contains(&decoded.tags[0].as_ptr()));
+        assert!(matches!(decoded.label, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn errors() {
+        let bytes = to_vec(&owned()).unwrap();
+
+        assert!(from_slice::<Owned>(&bytes[..bytes.len() - 1]).is_err());
+        assert!(from_reader::<_, Owned>(&bytes[..10]).is_err());
+        assert!(from_slice::<E>(&to_vec(&1i32).unwrap()).is_err());
+        assert!(from_slice::<Owned>(&[0x32, 1, 0, 0, 0, 0]).is_err());
+    }
+}
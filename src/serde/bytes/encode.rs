@@ -0,0 +1,802 @@
+//! Streaming serializer
+//!
+//! [`BytesEncoder`] writes wire bytes directly instead of building a
+//! [`Value`] first. The length of every map and array is written as a
+//! placeholder and patched once the container is closed, so the output is
+//! the same as `to_nson(&value)?.to_bytes()`.
+//!
+//! The only difference is with duplicate keys, which a `Map` collapses but
+//! this encoder writes as they come. These can only come from a hand-written
+//! `Serialize` impl or a `#[serde(flatten)]` collision.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use serde::ser::{
+    Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+
+use crate::array::Array;
+use crate::encode::{self, EncodeError, EncodeResult};
+use crate::id::Id;
+use crate::map::Map;
+use crate::serde::EXTENDED;
+use crate::spec::DataType;
+use crate::value::{Binary, Value, unescape_key};
+
+pub struct BytesEncoder<'a> {
+    output: &'a mut Vec<u8>,
+    /// Inside the `EXTENDED` newtype, so a map is in the extended form.
+    extended: bool,
+}
+
+impl<'a> BytesEncoder<'a> {
+    pub fn new(output: &'a mut Vec<u8>) -> BytesEncoder<'a> {
+        BytesEncoder {
+            output,
+            extended: false,
+        }
+    }
+
+    #[inline]
+    fn tag(&mut self, tag: DataType) {
+        self.output.push(tag as u8);
+    }
+
+    fn scalar(mut self, tag: DataType, payload: &[u8]) -> EncodeResult<()> {
+        self.tag(tag);
+        self.output.extend_from_slice(payload);
+        Ok(())
+    }
+
+    /// Open a map or array, returning the position of its tag.
+    fn open(&mut self, tag: DataType) -> usize {
+        let start = self.output.len();
+        self.tag(tag);
+        self.output.extend_from_slice(&[0; 4]);
+        start
+    }
+
+    /// Open a `{variant: ...}` wrapper and the container inside it.
+    fn open_variant(&mut self, variant: &str, tag: DataType) -> EncodeResult<(usize, usize)> {
+        let outer = self.open(DataType::Map);
+        encode::write_key(self.output, variant)?;
+        let inner = self.open(tag);
+        Ok((outer, inner))
+    }
+}
+
+/// Close the map or array opened at `start`.
+fn close(output: &mut [u8], start: usize) -> EncodeResult<()> {
+    let len = output.len() - start - 1;
+
+    if len > crate::MAX_NSON_SIZE as usize {
+        let desc = if output[start] == DataType::Map as u8 {
+            "map len must < MAX_NSON_SIZE"
+        } else {
+            "array len must < MAX_NSON_SIZE"
+        };
+
+        return Err(EncodeError::InvalidValueLen(len, desc.into()));
+    }
+
+    output[start + 1..start + 5].copy_from_slice(&(len as u32).to_le_bytes());
+    Ok(())
+}
+
+fn close_vec(output: &mut Vec<u8>, start: usize) -> EncodeResult<()> {
+    output.push(0);
+    close(output, start)
+}
+
+/// Close a struct or map, turning a `single` `$bin`, `$tim` or `$mid` entry
+/// back into the value it stands for, as `Value::from_extended_map` does.
+fn close_map(output: &mut Vec<u8>, start: usize, single: bool) -> EncodeResult<()> {
+    output.push(0);
+
+    if single && let Some(value) = extended(&output[start + 5..output.len() - 1]) {
+        output.truncate(start);
+        return encode::encode_value(output, &value);
+    }
+
+    close(output, start)
+}
+
+/// Decode the single entry of an extended map.
+fn extended(entry: &[u8]) -> Option<Value> {
+    let key_len = *entry.first()? as usize - 1;
+    let key = entry.get(1..1 + key_len)?;
+    let tag = *entry.get(1 + key_len)?;
+    let payload = entry.get(2 + key_len..)?;
+
+    let string = || -> Option<&str> {
+        if tag != DataType::String as u8 {
+            return None;
+        }
+        core::str::from_utf8(payload.get(4..)?).ok()
+    };
+
+    match key {
+        b"$bin" => {
+            let bin = const_hex::decode(string()?.as_bytes()).ok()?;
+            Some(Value::Binary(Binary(bin)))
+        }
+        b"$tim" if tag == DataType::U64 as u8 => {
+            let u = u64::from_le_bytes(payload.try_into().ok()?);
+            Some(Value::TimeStamp(u.into()))
+        }
+        b"$mid" => Id::with_string(string()?).ok().map(Value::Id),
+        _ => None,
+    }
+}
+
+impl<'a> Serializer for BytesEncoder<'a> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    type SerializeSeq = ArraySerializer<'a>;
+    type SerializeTuple = ArraySerializer<'a>;
+    type SerializeTupleStruct = ArraySerializer<'a>;
+    type SerializeTupleVariant = ArraySerializer<'a>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = MapSerializer<'a>;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn serialize_bool(self, value: bool) -> EncodeResult<()> {
+        self.scalar(DataType::Bool, &[value as u8])
+    }
+
+    #[inline]
+    fn serialize_i8(self, value: i8) -> EncodeResult<()> {
+        self.scalar(DataType::I8, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_u8(self, value: u8) -> EncodeResult<()> {
+        self.scalar(DataType::U8, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_i16(self, value: i16) -> EncodeResult<()> {
+        self.scalar(DataType::I16, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_u16(self, value: u16) -> EncodeResult<()> {
+        self.scalar(DataType::U16, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_i32(self, value: i32) -> EncodeResult<()> {
+        self.scalar(DataType::I32, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_u32(self, value: u32) -> EncodeResult<()> {
+        self.scalar(DataType::U32, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_i64(self, value: i64) -> EncodeResult<()> {
+        self.scalar(DataType::I64, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_u64(self, value: u64) -> EncodeResult<()> {
+        self.scalar(DataType::U64, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_f32(self, value: f32) -> EncodeResult<()> {
+        self.scalar(DataType::F32, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_f64(self, value: f64) -> EncodeResult<()> {
+        self.scalar(DataType::F64, &value.to_le_bytes())
+    }
+
+    #[inline]
+    fn serialize_char(self, value: char) -> EncodeResult<()> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    fn serialize_str(mut self, value: &str) -> EncodeResult<()> {
+        self.tag(DataType::String);
+        encode::write_string(self.output, value)
+    }
+
+    #[inline]
+    fn serialize_bytes(mut self, value: &[u8]) -> EncodeResult<()> {
+        self.tag(DataType::Binary);
+        encode::write_bytes(self.output, value)
+    }
+
+    #[inline]
+    fn serialize_none(self) -> EncodeResult<()> {
+        self.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> EncodeResult<()> {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> EncodeResult<()> {
+        self.scalar(DataType::Null, &[])
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> EncodeResult<()> {
+        self.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> EncodeResult<()> {
+        self.serialize_str(variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> EncodeResult<()> {
+        value.serialize(BytesEncoder {
+            output: self.output,
+            extended: name == EXTENDED,
+        })
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> EncodeResult<()> {
+        let start = self.open(DataType::Map);
+        encode::write_key(self.output, variant)?;
+        value.serialize(BytesEncoder::new(self.output))?;
+        close_vec(self.output, start)
+    }
+
+    #[inline]
+    fn serialize_seq(mut self, _len: Option<usize>) -> EncodeResult<Self::SerializeSeq> {
+        let start = self.open(DataType::Array);
+        Ok(ArraySerializer {
+            output: self.output,
+            start,
+            variant: None,
+        })
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> EncodeResult<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> EncodeResult<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> EncodeResult<Self::SerializeTupleVariant> {
+        let (outer, start) = self.open_variant(variant, DataType::Array)?;
+        Ok(ArraySerializer {
+            output: self.output,
+            start,
+            variant: Some(outer),
+        })
+    }
+
+    #[inline]
+    fn serialize_map(mut self, _len: Option<usize>) -> EncodeResult<Self::SerializeMap> {
+        let start = self.open(DataType::Map);
+        Ok(MapSerializer {
+            output: self.output,
+            start,
+            count: 0,
+            extended: self.extended,
+            escaped: false,
+            variant: None,
+        })
+    }
+
+    #[inline]
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> EncodeResult<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        mut self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> EncodeResult<Self::SerializeStructVariant> {
+        let (outer, start) = self.open_variant(variant, DataType::Map)?;
+        Ok(MapSerializer {
+            output: self.output,
+            start,
+            count: 0,
+            extended: false,
+            escaped: false,
+            variant: Some(outer),
+        })
+    }
+}
+
+pub struct ArraySerializer<'a> {
+    output: &'a mut Vec<u8>,
+    start: usize,
+    variant: Option<usize>,
+}
+
+impl ArraySerializer<'_> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
+        value.serialize(BytesEncoder::new(self.output))
+    }
+
+    fn close(self) -> EncodeResult<()> {
+        close_vec(self.output, self.start)?;
+
+        match self.variant {
+            Some(outer) => close_vec(self.output, outer),
+            None => Ok(()),
+        }
+    }
+}
+
+impl SerializeSeq for ArraySerializer<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> EncodeResult<()> {
+        self.close()
+    }
+}
+
+impl SerializeTuple for ArraySerializer<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> EncodeResult<()> {
+        self.close()
+    }
+}
+
+impl SerializeTupleStruct for ArraySerializer<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> EncodeResult<()> {
+        self.close()
+    }
+}
+
+impl SerializeTupleVariant for ArraySerializer<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> EncodeResult<()> {
+        self.close()
+    }
+}
+
+pub struct MapSerializer<'a> {
+    output: &'a mut Vec<u8>,
+    start: usize,
+    count: usize,
+    /// Keys are escaped and a single `$bin`, `$tim` or `$mid` entry is a
+    /// marker, for `Map` and `Value` only.
+    extended: bool,
+    /// A key was unescaped, so a single entry is user data even if it
+    /// reads as `$tim`.
+    escaped: bool,
+    variant: Option<usize>,
+}
+
+impl MapSerializer<'_> {
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> EncodeResult<()> {
+        encode::write_key(self.output, key)?;
+        value.serialize(BytesEncoder::new(self.output))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn close(self) -> EncodeResult<()> {
+        let single = self.extended && self.count == 1 && !self.escaped;
+        close_map(self.output, self.start, single)?;
+
+        match self.variant {
+            Some(outer) => close_vec(self.output, outer),
+            None => Ok(()),
+        }
+    }
+}
+
+impl SerializeMap for MapSerializer<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> EncodeResult<()> {
+        key.serialize(KeyEncoder {
+            output: self.output,
+            escaped: self.extended.then_some(&mut self.escaped),
+        })
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
+        value.serialize(BytesEncoder::new(self.output))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn end(self) -> EncodeResult<()> {
+        self.close()
+    }
+}
+
+impl SerializeStruct for MapSerializer<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> EncodeResult<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> EncodeResult<()> {
+        self.close()
+    }
+}
+
+impl SerializeStructVariant for MapSerializer<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> EncodeResult<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> EncodeResult<()> {
+        self.close()
+    }
+}
+
+/// Writes a map key. Keys are text, so integers and bools are written in
+/// decimal and `true`/`false`, and the serializer is human readable so `Id`,
+/// `TimeStamp` and `Binary` write their text form. In the extended form,
+/// escaped keys such as `$$tim` lose one `$` and set `escaped`.
+struct KeyEncoder<'a> {
+    output: &'a mut Vec<u8>,
+    escaped: Option<&'a mut bool>,
+}
+
+fn invalid_key<T>(value: impl Into<Value>) -> EncodeResult<T> {
+    Err(crate::serde::EncodeError::InvalidMapKeyType(value.into()).into())
+}
+
+impl Serializer for KeyEncoder<'_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    type SerializeSeq = Impossible<(), EncodeError>;
+    type SerializeTuple = Impossible<(), EncodeError>;
+    type SerializeTupleStruct = Impossible<(), EncodeError>;
+    type SerializeTupleVariant = Impossible<(), EncodeError>;
+    type SerializeMap = Impossible<(), EncodeError>;
+    type SerializeStruct = Impossible<(), EncodeError>;
+    type SerializeStructVariant = Impossible<(), EncodeError>;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+
+    fn serialize_bool(self, value: bool) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i8(self, value: i8) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u8(self, value: u8) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i16(self, value: i16) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u16(self, value: u16) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i32(self, value: i32) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u32(self, value: u32) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i64(self, value: i64) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u64(self, value: u64) -> EncodeResult<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_f32(self, value: f32) -> EncodeResult<()> {
+        invalid_key(value)
+    }
+
+    fn serialize_f64(self, value: f64) -> EncodeResult<()> {
+        invalid_key(value)
+    }
+
+    fn serialize_char(self, value: char) -> EncodeResult<()> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> EncodeResult<()> {
+        let Some(escaped) = self.escaped else {
+            return encode::write_key(self.output, value);
+        };
+
+        let unescaped = unescape_key(value);
+        if unescaped.len() < value.len() {
+            *escaped = true;
+        }
+
+        encode::write_key(self.output, unescaped)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> EncodeResult<()> {
+        invalid_key(Binary(value.into()))
+    }
+
+    fn serialize_none(self) -> EncodeResult<()> {
+        invalid_key(Value::Null)
+    }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> EncodeResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> EncodeResult<()> {
+        invalid_key(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> EncodeResult<()> {
+        invalid_key(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> EncodeResult<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> EncodeResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> EncodeResult<()> {
+        invalid_key(Map::new())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> EncodeResult<Self::SerializeSeq> {
+        invalid_key(Array::new())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> EncodeResult<Self::SerializeTuple> {
+        invalid_key(Array::new())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> EncodeResult<Self::SerializeTupleStruct> {
+        invalid_key(Array::new())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> EncodeResult<Self::SerializeTupleVariant> {
+        invalid_key(Map::new())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> EncodeResult<Self::SerializeMap> {
+        invalid_key(Map::new())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> EncodeResult<Self::SerializeStruct> {
+        invalid_key(Map::new())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> EncodeResult<Self::SerializeStructVariant> {
+        invalid_key(Map::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use serde::Serialize;
+
+    use crate::encode::{to_nson, to_vec};
+    use crate::id::Id;
+    use crate::m;
+    use crate::map::Map;
+    use crate::value::{Binary, TimeStamp, Value};
+
+    fn assert_same<T: Serialize>(value: &T) {
+        let expected = to_nson(value).unwrap().to_bytes().unwrap();
+        assert_eq!(to_vec(value).unwrap(), expected);
+    }
+
+    #[derive(Serialize)]
+    struct Inner {
+        a: i32,
+        b: Option<u64>,
+        #[serde(with = "serde_bytes")]
+        c: Vec<u8>,
+    }
+
+    #[derive(Serialize)]
+    enum E {
+        Unit,
+        New(i8),
+        Tuple(u16, f32),
+        Struct { x: String },
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        inner: Inner,
+        list: Vec<Inner>,
+        tuple: (bool, char),
+        enums: Vec<E>,
+        map: BTreeMap<String, f64>,
+        id: Id,
+        time: TimeStamp,
+        bin: Binary,
+        value: Value,
+        empty: Map,
+    }
+
+    #[test]
+    fn same_as_value_path() {
+        let inner = || Inner {
+            a: -1,
+            b: None,
+            c: vec![1, 2, 3],
+        };
+
+        let outer = Outer {
+            inner: inner(),
+            list: vec![inner(), inner()],
+            tuple: (true, 'x'),
+            enums: vec![
+                E::Unit,
+                E::New(3),
+                E::Tuple(4, 5.0),
+                E::Struct { x: "y".to_string() },
+            ],
+            map: [("k".to_string(), 1.5)].into_iter().collect(),
+            id: Id::new_raw(1, 2, 3),
+            time: TimeStamp(123),
+            bin: Binary(vec![4, 5]),
+            value: m! {"a": [1, "b", null], "c": {"d": 1u8}}.into(),
+            empty: Map::new(),
+        };
+
+        assert_same(&outer);
+        assert_same(&1i32);
+        assert_same(&"str");
+        assert_same(&vec![Id::new_raw(4, 5, 6)]);
+        assert_same(&m! {"$tim": 5u64});
+        assert_same(&m! {"$tim": 5i32});
+        assert_same(&m! {"$mid": "not an id"});
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let map: BTreeMap<(i32, i32), i32> = [((1, 2), 3)].into_iter().collect();
+        assert!(to_vec(&map).is_err());
+
+        let map: BTreeMap<i32, i32> = [(1, 2)].into_iter().collect();
+        assert_same(&map);
+
+        let map: BTreeMap<String, i32> = [(String::new(), 2)].into_iter().collect();
+        assert!(to_vec(&map).is_err());
+    }
+}
@@ -0,0 +1,10 @@
+//! Serde straight to and from wire bytes
+//!
+//! [`BytesEncoder`] and [`BytesDecoder`] work on the encoded form directly,
+//! without going through a [`Value`](crate::Value) tree.
+
+mod decode;
+mod encode;
+
+pub use decode::{Bytes, BytesDecoder, Input, ReaderInput, SliceInput};
+pub use encode::{ArraySerializer, BytesEncoder, MapSerializer};
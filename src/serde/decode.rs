@@ -1,58 +1,64 @@
 use core::fmt;
 use core::marker::PhantomData;
 use core::result;
+use core::slice;
 
+use alloc::borrow::Cow;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
 
+use serde::de::value::BorrowedStrDeserializer;
 use serde::de::{
-    self, Deserialize, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess,
-    VariantAccess, Visitor,
+    self, Deserialize, DeserializeSeed, Deserializer, EnumAccess, IgnoredAny, IntoDeserializer,
+    MapAccess, SeqAccess, VariantAccess, Visitor,
 };
 use serde::de::{Error, Expected, Unexpected};
 
+use base64::{Engine, engine::general_purpose};
 use indexmap::IndexMap;
 
 use crate::array::Array;
 use crate::id::Id;
-use crate::map::{IntoIter, Map};
-use crate::value::{Binary, TimeStamp, Value};
+use crate::map::{IntoIter, Iter, Map};
+use crate::spec::DataType;
+use crate::value::{Binary, TimeStamp, Value, escape_key};
 
-use super::DecodeError;
 use super::DecodeResult;
+use super::EXTENDED;
+use super::{DecodeError, DecodeErrorKind};
 
 impl de::Error for DecodeError {
     fn custom<T: fmt::Display>(msg: T) -> DecodeError {
-        DecodeError::Unknown(msg.to_string())
+        DecodeErrorKind::Unknown(msg.to_string()).into()
     }
 
     fn invalid_type(_unexp: Unexpected, exp: &dyn Expected) -> DecodeError {
-        DecodeError::InvalidType(exp.to_string())
+        DecodeErrorKind::InvalidType(exp.to_string()).into()
     }
 
     fn invalid_value(_unexp: Unexpected, exp: &dyn Expected) -> DecodeError {
-        DecodeError::InvalidValue(exp.to_string())
+        DecodeErrorKind::InvalidValue(exp.to_string()).into()
     }
 
     fn invalid_length(len: usize, exp: &dyn Expected) -> DecodeError {
-        DecodeError::InvalidLength(len, exp.to_string())
+        DecodeErrorKind::InvalidLength(len, exp.to_string()).into()
     }
 
     fn unknown_variant(variant: &str, _expected: &'static [&'static str]) -> DecodeError {
-        DecodeError::UnknownVariant(variant.to_string())
+        DecodeErrorKind::UnknownVariant(variant.to_string()).into()
     }
 
     fn unknown_field(field: &str, _expected: &'static [&'static str]) -> DecodeError {
-        DecodeError::UnknownField(field.to_string())
+        DecodeErrorKind::UnknownField(field.to_string()).into()
     }
 
     fn missing_field(field: &'static str) -> DecodeError {
-        DecodeError::ExpectedField(field)
+        DecodeErrorKind::ExpectedField(field).into()
     }
 
     fn duplicate_field(field: &'static str) -> DecodeError {
-        DecodeError::DuplicatedField(field)
+        DecodeErrorKind::DuplicatedField(field).into()
     }
 }
 
@@ -62,14 +68,39 @@ impl<'de> Deserialize<'de> for Map {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_map(ValueVisitor).and_then(|nson| {
-            if let Value::Map(map) = nson {
-                Ok(map)
-            } else {
+        deserializer.deserialize_newtype_struct(EXTENDED, ExtendedMapVisitor)
+    }
+}
+
+/// Reads a `Map` in the extended form, directly from our decoders or
+/// through the newtype from other formats.
+struct ExtendedMapVisitor;
+
+impl<'de> Visitor<'de> for ExtendedMapVisitor {
+    type Value = Map;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expecting a map")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Map, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+
+    fn visit_map<V>(self, visitor: V) -> Result<Map, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        match ValueVisitor.visit_map(visitor)? {
+            Value::Map(map) => Ok(map),
+            nson => {
                 let err = format!("expected map, found extended JSON data type: {}", nson);
                 Err(de::Error::invalid_type(Unexpected::Map, &&*err))
             }
-        })
+        }
     }
 }
 
@@ -304,67 +335,70 @@ impl<'de> Visitor<'de> for MapVisitor {
     }
 }
 
-/// Serde Decoder
-pub struct Decoder {
-    value: Option<Value>,
+/// Options for [`Decoder`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecoderOptions {
+    deny_unknown_fields: bool,
 }
 
-impl Decoder {
-    pub fn new(value: Value) -> Decoder {
-        Decoder { value: Some(value) }
+impl DecoderOptions {
+    pub fn new() -> DecoderOptions {
+        DecoderOptions::default()
     }
-}
 
-macro_rules! forward_to_deserialize {
-    ($(
-        $name:ident ( $( $arg:ident : $ty:ty ),* );
-    )*) => {
-        $(
-            forward_to_deserialize!{
-                func: $name ( $( $arg: $ty ),* );
-            }
-        )*
-    };
+    /// Reject map keys that are not fields of the struct being decoded, as
+    /// if every struct had `#[serde(deny_unknown_fields)]`.
+    pub fn deny_unknown_fields(mut self, deny: bool) -> DecoderOptions {
+        self.deny_unknown_fields = deny;
+        self
+    }
 
-    (func: deserialize_enum ( $( $arg:ident : $ty:ty ),* );) => {
-        fn deserialize_enum<V>(
-            self,
-            $(_: $ty,)*
-            _visitor: V,
-        ) -> ::core::result::Result<V::Value, Self::Error>
-            where V: ::serde::de::Visitor<'de>
-        {
-            Err(::serde::de::Error::custom("unexpected Enum"))
+    fn check_fields(&self, map: &Map, fields: &'static [&'static str]) -> DecodeResult<()> {
+        if !self.deny_unknown_fields {
+            return Ok(());
         }
-    };
 
-    (func: $name:ident ( $( $arg:ident : $ty:ty ),* );) => {
-        #[inline]
-        fn $name<V>(
-            self,
-            $(_: $ty,)*
-            visitor: V,
-        ) -> ::core::result::Result<V::Value, Self::Error>
-            where V: ::serde::de::Visitor<'de>
-        {
-            self.deserialize_any(visitor)
+        match map.keys().find(|key| !fields.contains(&key.as_str())) {
+            Some(key) => {
+                let err: DecodeError = DecodeErrorKind::UnknownField(key.clone()).into();
+                Err(err.found_in(DataType::Map))
+            }
+            None => Ok(()),
         }
-    };
+    }
 }
 
-impl<'de> Deserializer<'de> for Decoder {
-    type Error = DecodeError;
+/// Serde Decoder
+pub struct Decoder {
+    value: Option<Value>,
+    options: DecoderOptions,
+}
 
-    #[inline]
-    fn deserialize_any<V>(mut self, visitor: V) -> DecodeResult<V::Value>
+impl Decoder {
+    pub fn new(value: Value) -> Decoder {
+        Decoder::with_options(value, DecoderOptions::default())
+    }
+
+    pub fn with_options(value: Value, options: DecoderOptions) -> Decoder {
+        Decoder {
+            value: Some(value),
+            options,
+        }
+    }
+
+    /// Visit the value, escaping map keys with `escape_key` for `Value` and
+    /// `Map`, not for other maps and structs.
+    fn visit<'de, V>(mut self, visitor: V, escape: bool) -> DecodeResult<V::Value>
     where
         V: Visitor<'de>,
     {
         let value = match self.value.take() {
             Some(value) => value,
-            None => return Err(DecodeError::EndOfStream),
+            None => return Err(DecodeErrorKind::EndOfStream.into()),
         };
 
+        let found = value.element_type();
+
         match value {
             Value::F32(v) => visitor.visit_f32(v),
             Value::F64(v) => visitor.visit_f64(v),
@@ -381,7 +415,9 @@ impl<'de> Deserializer<'de> for Decoder {
                 let len = v.len();
                 visitor.visit_seq(SeqDecoder {
                     iter: v.into_iter(),
+                    index: 0,
                     len,
+                    options: self.options,
                 })
             }
             Value::Map(v) => {
@@ -390,6 +426,8 @@ impl<'de> Deserializer<'de> for Decoder {
                     iter: v.into_iter(),
                     value: None,
                     len,
+                    escape,
+                    options: self.options,
                 })
             }
             Value::Bool(v) => visitor.visit_bool(v),
@@ -402,9 +440,66 @@ impl<'de> Deserializer<'de> for Decoder {
                     iter: message.into_iter(),
                     value: None,
                     len,
+                    escape: false,
+                    options: self.options,
                 })
             }
         }
+        .map_err(|e| e.found_in(found))
+    }
+}
+
+macro_rules! forward_to_deserialize {
+    ($(
+        $name:ident ( $( $arg:ident : $ty:ty ),* );
+    )*) => {
+        $(
+            forward_to_deserialize!{
+                func: $name ( $( $arg: $ty ),* );
+            }
+        )*
+    };
+
+    (func: deserialize_enum ( $( $arg:ident : $ty:ty ),* );) => {
+        fn deserialize_enum<V>(
+            self,
+            $(_: $ty,)*
+            _visitor: V,
+        ) -> ::core::result::Result<V::Value, Self::Error>
+            where V: ::serde::de::Visitor<'de>
+        {
+            Err(::serde::de::Error::custom("unexpected Enum"))
+        }
+    };
+
+    (func: $name:ident ( $( $arg:ident : $ty:ty ),* );) => {
+        #[inline]
+        fn $name<V>(
+            self,
+            $(_: $ty,)*
+            visitor: V,
+        ) -> ::core::result::Result<V::Value, Self::Error>
+            where V: ::serde::de::Visitor<'de>
+        {
+            self.deserialize_any(visitor)
+        }
+    };
+}
+
+impl<'de> Deserializer<'de> for Decoder {
+    type Error = DecodeError;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit(visitor, true)
     }
 
     #[inline]
@@ -415,7 +510,7 @@ impl<'de> Deserializer<'de> for Decoder {
         match self.value {
             Some(Value::Null) => visitor.visit_none(),
             Some(_) => visitor.visit_some(self),
-            None => Err(DecodeError::EndOfStream),
+            None => Err(DecodeErrorKind::EndOfStream.into()),
         }
     }
 
@@ -432,16 +527,22 @@ impl<'de> Deserializer<'de> for Decoder {
         let value = match self.value.take() {
             Some(Value::Map(value)) => value,
             Some(Value::String(variant)) => {
-                return visitor.visit_enum(EnumDecoder {
-                    val: Value::String(variant),
-                    decoder: VariantDecoder { val: None },
-                });
+                return visitor
+                    .visit_enum(EnumDecoder {
+                        val: Value::String(variant),
+                        decoder: VariantDecoder {
+                            val: None,
+                            options: self.options,
+                        },
+                    })
+                    .map_err(|e| e.found_in(DataType::String));
             }
-            Some(_) => {
-                return Err(DecodeError::InvalidType("expected an enum".to_string()));
+            Some(value) => {
+                let err: DecodeError = DecodeErrorKind::InvalidType("an enum".to_string()).into();
+                return Err(err.found_in(value.element_type()));
             }
             None => {
-                return Err(DecodeError::EndOfStream);
+                return Err(DecodeErrorKind::EndOfStream.into());
             }
         };
 
@@ -450,34 +551,90 @@ impl<'de> Deserializer<'de> for Decoder {
         let (variant, value) = match iter.next() {
             Some(v) => v,
             None => {
-                return Err(DecodeError::SyntaxError(
-                    "expected a variant name".to_string(),
-                ))
+                return Err(
+                    DecodeErrorKind::SyntaxError("expected a variant name".to_string()).into(),
+                );
             }
         };
 
         // enums are encoded in json as maps with a single key:value pair
         match iter.next() {
-            Some(_) => Err(DecodeError::InvalidType(
-                "expected a single key:value pair".to_string(),
-            )),
-            None => visitor.visit_enum(EnumDecoder {
-                val: Value::String(variant),
-                decoder: VariantDecoder { val: Some(value) },
-            }),
+            Some(_) => {
+                Err(DecodeErrorKind::InvalidType("a single key:value pair".to_string()).into())
+            }
+            None => visitor
+                .visit_enum(EnumDecoder {
+                    val: Value::String(variant),
+                    decoder: VariantDecoder {
+                        val: Some(value),
+                        options: self.options,
+                    },
+                })
+                .map_err(|e| e.found_in(DataType::Map)),
         }
     }
 
     #[inline]
-    fn deserialize_newtype_struct<V>(
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == EXTENDED {
+            return self.visit(visitor, true);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.visit(visitor, false)
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(mut self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // `Vec<u8>` written with `EncoderOptions::bytes_as_binary`
+        match self.value.take() {
+            Some(Value::Binary(bytes)) => {
+                let len = bytes.0.len();
+                let elements: vec::Vec<Value> = bytes.0.into_iter().map(Value::U8).collect();
+                visitor
+                    .visit_seq(SeqDecoder {
+                        iter: elements.into_iter(),
+                        index: 0,
+                        len,
+                        options: self.options,
+                    })
+                    .map_err(|e| e.found_in(DataType::Binary))
+            }
+            value => {
+                self.value = value;
+                self.deserialize_any(visitor)
+            }
+        }
+    }
+
+    #[inline]
+    fn deserialize_struct<V>(
         self,
         _name: &'static str,
+        fields: &'static [&'static str],
         visitor: V,
     ) -> DecodeResult<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        if let Some(Value::Map(map)) = &self.value {
+            self.options.check_fields(map, fields)?;
+        }
+
+        self.visit(visitor, false)
     }
 
     forward_to_deserialize! {
@@ -496,12 +653,9 @@ impl<'de> Deserializer<'de> for Decoder {
         deserialize_str();
         deserialize_string();
         deserialize_unit();
-        deserialize_seq();
         deserialize_bytes();
-        deserialize_map();
         deserialize_unit_struct(name: &'static str);
         deserialize_tuple_struct(name: &'static str, len: usize);
-        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
         deserialize_tuple(len: usize);
         deserialize_identifier();
         deserialize_ignored_any();
@@ -529,6 +683,7 @@ impl<'de> EnumAccess<'de> for EnumDecoder {
 
 struct VariantDecoder {
     val: Option<Value>,
+    options: DecoderOptions,
 }
 
 impl<'de> VariantAccess<'de> for VariantDecoder {
@@ -545,7 +700,8 @@ impl<'de> VariantAccess<'de> for VariantDecoder {
     where
         T: DeserializeSeed<'de>,
     {
-        let dec = Decoder::new(self.val.take().ok_or(DecodeError::EndOfStream)?);
+        let value = self.val.take().ok_or(DecodeErrorKind::EndOfStream)?;
+        let dec = Decoder::with_options(value, self.options);
         seed.deserialize(dec)
     }
 
@@ -553,46 +709,58 @@ impl<'de> VariantAccess<'de> for VariantDecoder {
     where
         V: Visitor<'de>,
     {
-        if let Value::Array(fields) = self.val.take().ok_or(DecodeError::EndOfStream)? {
+        if let Value::Array(fields) = self.val.take().ok_or(DecodeErrorKind::EndOfStream)? {
             let de = SeqDecoder {
                 len: fields.len(),
                 iter: fields.into_iter(),
+                index: 0,
+                options: self.options,
             };
             de.deserialize_any(visitor)
         } else {
-            Err(DecodeError::InvalidType("expected a tuple".to_string()))
+            Err(DecodeErrorKind::InvalidType("a tuple".to_string()).into())
         }
     }
 
     fn struct_variant<V>(
         mut self,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> DecodeResult<V::Value>
     where
         V: Visitor<'de>,
     {
-        if let Value::Map(fields) = self.val.take().ok_or(DecodeError::EndOfStream)? {
+        if let Value::Map(map) = self.val.take().ok_or(DecodeErrorKind::EndOfStream)? {
+            self.options.check_fields(&map, fields)?;
             let de = MapDecoder {
-                len: fields.len(),
-                iter: fields.into_iter(),
+                len: map.len(),
+                iter: map.into_iter(),
                 value: None,
+                escape: false,
+                options: self.options,
             };
             de.deserialize_any(visitor)
         } else {
-            Err(DecodeError::InvalidType("expected a struct".to_string()))
+            Err(DecodeErrorKind::InvalidType("a struct".to_string()).into())
         }
     }
 }
 
 struct SeqDecoder {
     iter: vec::IntoIter<Value>,
+    index: usize,
     len: usize,
+    options: DecoderOptions,
 }
 
 impl<'de> Deserializer<'de> for SeqDecoder {
     type Error = DecodeError;
 
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     #[inline]
     fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
     where
@@ -648,10 +816,11 @@ impl<'de> SeqAccess<'de> for SeqDecoder {
             None => Ok(None),
             Some(value) => {
                 self.len -= 1;
-                let de = Decoder::new(value);
+                self.index += 1;
+                let de = Decoder::with_options(value, self.options);
                 match seed.deserialize(de) {
                     Ok(value) => Ok(Some(value)),
-                    Err(err) => Err(err),
+                    Err(err) => Err(err.within(&(self.index - 1).to_string())),
                 }
             }
         }
@@ -664,8 +833,10 @@ impl<'de> SeqAccess<'de> for SeqDecoder {
 
 struct MapDecoder {
     iter: IntoIter<String, Value>,
-    value: Option<Value>,
+    value: Option<(String, Value)>,
     len: usize,
+    escape: bool,
+    options: DecoderOptions,
 }
 
 impl<'de> MapAccess<'de> for MapDecoder {
@@ -678,12 +849,18 @@ impl<'de> MapAccess<'de> for MapDecoder {
         match self.iter.next() {
             Some((key, value)) => {
                 self.len -= 1;
-                self.value = Some(value);
-
-                let de = Decoder::new(Value::String(key));
+                self.value = Some((key.clone(), value));
+
+                let key = match escape_key(&key) {
+                    Cow::Owned(escaped) if self.escape => escaped,
+                    _ => key,
+                };
+                let de = KeyDecoder {
+                    key: Cow::Owned(key),
+                };
                 match seed.deserialize(de) {
                     Ok(val) => Ok(Some(val)),
-                    Err(e) => Err(e),
+                    Err(e) => Err(e.found_in(DataType::String)),
                 }
             }
             None => Ok(None),
@@ -694,9 +871,9 @@ impl<'de> MapAccess<'de> for MapDecoder {
     where
         V: DeserializeSeed<'de>,
     {
-        let value = self.value.take().ok_or(DecodeError::EndOfStream)?;
-        let de = Decoder::new(value);
-        seed.deserialize(de)
+        let (key, value) = self.value.take().ok_or(DecodeErrorKind::EndOfStream)?;
+        let de = Decoder::with_options(value, self.options);
+        seed.deserialize(de).map_err(|e| e.within(&key))
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -707,6 +884,11 @@ impl<'de> MapAccess<'de> for MapDecoder {
 impl<'de> Deserializer<'de> for MapDecoder {
     type Error = DecodeError;
 
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     #[inline]
     fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
     where
@@ -747,41 +929,640 @@ impl<'de> Deserializer<'de> for MapDecoder {
     }
 }
 
-impl<'de> Deserialize<'de> for TimeStamp {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        match Value::deserialize(deserializer)? {
-            Value::TimeStamp(ts) => {
-                let ts = ts.0.to_le();
+/// A map key. Keys are text, so `Id`, `TimeStamp` and `Binary` keys are
+/// parsed from their text form, and integer and bool keys from decimal and
+/// `true`/`false`.
+struct KeyDecoder<'de> {
+    key: Cow<'de, str>,
+}
 
-                Ok(TimeStamp(ts))
-            }
-            _ => Err(D::Error::custom("expecting TimeStamp")),
-        }
+impl KeyDecoder<'_> {
+    fn key_str(&self) -> DecodeResult<&str> {
+        Ok(&self.key)
     }
 }
 
-impl<'de> Deserialize<'de> for Id {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+impl<'de> Deserializer<'de> for KeyDecoder<'de> {
+    type Error = DecodeError;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
     where
-        D: Deserializer<'de>,
+        V: Visitor<'de>,
     {
-        match Value::deserialize(deserializer)? {
-            Value::Id(id) => Ok(id),
-            _ => Err(D::Error::custom("expecting Id")),
+        match self.key {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
         }
     }
-}
 
-impl<'de> Deserialize<'de> for Binary {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> DecodeResult<V::Value>
     where
-        D: Deserializer<'de>,
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
     {
+        visitor.visit_enum(self.key.into_owned().into_deserializer())
+    }
+
+    parse_key! {
+        deserialize_bool => visit_bool: bool;
+        deserialize_i8 => visit_i8: i8;
+        deserialize_i16 => visit_i16: i16;
+        deserialize_i32 => visit_i32: i32;
+        deserialize_i64 => visit_i64: i64;
+        deserialize_u8 => visit_u8: u8;
+        deserialize_u16 => visit_u16: u16;
+        deserialize_u32 => visit_u32: u32;
+        deserialize_u64 => visit_u64: u64;
+    }
+
+    forward_to_deserialize! {
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_unit();
+        deserialize_seq();
+        deserialize_bytes();
+        deserialize_map();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_tuple(len: usize);
+        deserialize_identifier();
+        deserialize_ignored_any();
+        deserialize_byte_buf();
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DecodeError> for Value {
+    type Deserializer = Decoder;
+
+    fn into_deserializer(self) -> Decoder {
+        Decoder::new(self)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DecodeError> for &'de Value {
+    type Deserializer = &'de Value;
+
+    fn into_deserializer(self) -> &'de Value {
+        self
+    }
+}
+
+/// Borrowing counterpart of `Decoder`, which hands out `&'de str` and
+/// `&'de [u8]` instead of cloning.
+impl<'de> Deserializer<'de> for &'de Value {
+    type Error = DecodeError;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match *self {
+            Value::F32(v) => visitor.visit_f32(v),
+            Value::F64(v) => visitor.visit_f64(v),
+            Value::I32(v) => visitor.visit_i32(v),
+            Value::I64(v) => visitor.visit_i64(v),
+            Value::U32(v) => visitor.visit_u32(v),
+            Value::U64(v) => visitor.visit_u64(v),
+            Value::I8(v) => visitor.visit_i8(v),
+            Value::U8(v) => visitor.visit_u8(v),
+            Value::I16(v) => visitor.visit_i16(v),
+            Value::U16(v) => visitor.visit_u16(v),
+            Value::String(ref v) => visitor.visit_borrowed_str(v),
+            Value::Array(ref v) => v.deserialize_any(visitor),
+            Value::Map(ref v) => v.deserialize_any(visitor),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Null => visitor.visit_unit(),
+            Value::Binary(ref v) => visitor.visit_borrowed_bytes(&v.0),
+            Value::TimeStamp(_) | Value::Id(_) => {
+                Decoder::new(self.clone()).deserialize_any(visitor)
+            }
+        }
+        .map_err(|e| e.found_in(self.element_type()))
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match *self {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match *self {
+            Value::Map(ref map) => map.deserialize_enum(name, variants, visitor),
+            Value::String(ref variant) => visitor.visit_enum(RefEnumDecoder {
+                variant,
+                value: None,
+            }),
+            _ => {
+                let err: DecodeError = DecodeErrorKind::InvalidType("an enum".to_string()).into();
+                Err(err.found_in(self.element_type()))
+            }
+        }
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == EXTENDED {
+            return self.deserialize_any(visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match *self {
+            Value::Map(ref map) => map.deserialize_map(visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    #[inline]
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match *self {
+            Value::Map(ref map) => map.deserialize_struct(name, fields, visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize! {
+        deserialize_bool();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_unit();
+        deserialize_seq();
+        deserialize_bytes();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_tuple(len: usize);
+        deserialize_identifier();
+        deserialize_ignored_any();
+        deserialize_byte_buf();
+    }
+}
+
+impl<'de> Deserializer<'de> for &'de Map {
+    type Error = DecodeError;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(RefMapDecoder {
+            iter: self.iter(),
+            value: None,
+            escape: true,
+        })
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(RefMapDecoder {
+            iter: self.iter(),
+            value: None,
+            escape: false,
+        })
+    }
+
+    #[inline]
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut iter = self.iter();
+
+        let (variant, value) = match iter.next() {
+            Some(v) => v,
+            None => {
+                return Err(
+                    DecodeErrorKind::SyntaxError("expected a variant name".to_string()).into(),
+                );
+            }
+        };
+
+        match iter.next() {
+            Some(_) => {
+                Err(DecodeErrorKind::InvalidType("a single key:value pair".to_string()).into())
+            }
+            None => visitor.visit_enum(RefEnumDecoder {
+                variant,
+                value: Some(value),
+            }),
+        }
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == EXTENDED {
+            return self.deserialize_any(visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize! {
+        deserialize_bool();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_unit();
+        deserialize_seq();
+        deserialize_bytes();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_tuple(len: usize);
+        deserialize_identifier();
+        deserialize_ignored_any();
+        deserialize_byte_buf();
+    }
+}
+
+impl<'de> Deserializer<'de> for &'de Array {
+    type Error = DecodeError;
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(RefSeqDecoder {
+            iter: self.iter(),
+            index: 0,
+        })
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(DecodeErrorKind::InvalidType("an enum".to_string()).into())
+    }
+
+    forward_to_deserialize! {
+        deserialize_bool();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_unit();
+        deserialize_seq();
+        deserialize_bytes();
+        deserialize_map();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_tuple(len: usize);
+        deserialize_identifier();
+        deserialize_ignored_any();
+        deserialize_byte_buf();
+    }
+}
+
+struct RefEnumDecoder<'de> {
+    variant: &'de str,
+    value: Option<&'de Value>,
+}
+
+impl<'de> EnumAccess<'de> for RefEnumDecoder<'de> {
+    type Error = DecodeError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> DecodeResult<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant =
+            seed.deserialize(BorrowedStrDeserializer::<DecodeError>::new(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for RefEnumDecoder<'de> {
+    type Error = DecodeError;
+
+    fn unit_variant(self) -> DecodeResult<()> {
+        match self.value {
+            None => Ok(()),
+            Some(val) => IgnoredAny::deserialize(val).map(|_| ()),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> DecodeResult<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.value.ok_or(DecodeErrorKind::EndOfStream)?)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value.ok_or(DecodeErrorKind::EndOfStream)? {
+            Value::Array(fields) if fields.is_empty() => visitor.visit_unit(),
+            Value::Array(fields) => fields.deserialize_any(visitor),
+            _ => Err(DecodeErrorKind::InvalidType("a tuple".to_string()).into()),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> DecodeResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value.ok_or(DecodeErrorKind::EndOfStream)? {
+            Value::Map(map) => map.deserialize_struct("", fields, visitor),
+            _ => Err(DecodeErrorKind::InvalidType("a struct".to_string()).into()),
+        }
+    }
+}
+
+struct RefSeqDecoder<'de> {
+    iter: slice::Iter<'de, Value>,
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for RefSeqDecoder<'de> {
+    type Error = DecodeError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> DecodeResult<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => {
+                self.index += 1;
+                seed.deserialize(value)
+                    .map(Some)
+                    .map_err(|e| e.within(&(self.index - 1).to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct RefMapDecoder<'de> {
+    iter: Iter<'de, String, Value>,
+    value: Option<(&'de str, &'de Value)>,
+    escape: bool,
+}
+
+impl<'de> MapAccess<'de> for RefMapDecoder<'de> {
+    type Error = DecodeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> DecodeResult<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                let key = if self.escape {
+                    escape_key(key)
+                } else {
+                    Cow::Borrowed(key.as_str())
+                };
+                seed.deserialize(KeyDecoder { key })
+                    .map(Some)
+                    .map_err(|e| e.found_in(DataType::String))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> DecodeResult<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (key, value) = self.value.take().ok_or(DecodeErrorKind::EndOfStream)?;
+        seed.deserialize(value).map_err(|e| e.within(key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeStamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let human_readable = deserializer.is_human_readable();
+
+        match Value::deserialize(deserializer)? {
+            Value::TimeStamp(ts) => {
+                let ts = ts.0.to_le();
+
+                Ok(TimeStamp(ts))
+            }
+            Value::String(s) if human_readable => TimeStamp::from_rfc3339(&s)
+                .ok_or_else(|| D::Error::custom("expecting an RFC 3339 TimeStamp")),
+            _ => Err(D::Error::custom("expecting TimeStamp")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let human_readable = deserializer.is_human_readable();
+
+        match Value::deserialize(deserializer)? {
+            Value::Id(id) => Ok(id),
+            Value::String(s) if human_readable => {
+                Id::with_string(&s).map_err(|_| D::Error::custom("expecting a hex Id"))
+            }
+            _ => Err(D::Error::custom("expecting Id")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Binary {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let human_readable = deserializer.is_human_readable();
+
         match Value::deserialize(deserializer)? {
             Value::Binary(v) => Ok(v),
+            Value::String(s) if human_readable => general_purpose::STANDARD
+                .decode(s)
+                .map(Binary)
+                .map_err(|_| D::Error::custom("expecting a base64 Binary")),
             _ => Err(D::Error::custom("expecting Binary")),
         }
     }
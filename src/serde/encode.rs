@@ -1,5 +1,7 @@
 use alloc::string::{String, ToString};
 
+use base64::{Engine, engine::general_purpose};
+
 use serde::ser::{
     Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
     SerializeTupleStruct, SerializeTupleVariant, Serializer,
@@ -8,11 +10,12 @@ use serde::ser::{
 use crate::array::Array;
 use crate::id::Id;
 use crate::map::Map;
-use crate::value::{Binary, TimeStamp, Value};
+use crate::value::{Binary, TimeStamp, Value, escape_key};
 
-use super::to_nson;
+use super::EXTENDED;
 use super::EncodeError;
 use super::EncodeResult;
+use super::to_nson_with;
 
 impl Serialize for Map {
     #[inline]
@@ -20,9 +23,35 @@ impl Serialize for Map {
     where
         S: Serializer,
     {
-        let mut map = serializer.serialize_map(Some(self.len()))?;
-        for (k, v) in self {
-            map.serialize_entry(k, v)?;
+        serializer.serialize_newtype_struct(
+            EXTENDED,
+            &Extended {
+                map: self,
+                escape: true,
+            },
+        )
+    }
+}
+
+/// The entries of a map in the extended form, keys escaped unless it is
+/// the single entry of a marker.
+struct Extended<'a> {
+    map: &'a Map,
+    escape: bool,
+}
+
+impl Serialize for Extended<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.map.len()))?;
+        for (k, v) in self.map {
+            if self.escape {
+                map.serialize_entry(&escape_key(k), v)?;
+            } else {
+                map.serialize_entry(k, v)?;
+            }
         }
         map.end()
     }
@@ -53,18 +82,123 @@ impl Serialize for Value {
             Value::Binary(ref bytes) => serializer.serialize_bytes(&bytes.0),
             _ => {
                 let msg = self.to_extended_map();
-                msg.serialize(serializer)
+                serializer.serialize_newtype_struct(
+                    EXTENDED,
+                    &Extended {
+                        map: &msg,
+                        escape: false,
+                    },
+                )
             }
         }
     }
 }
 
+/// How integers are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntegerWidth {
+    /// Keep the width of the Rust type, a `u32` is always a `U32`.
+    #[default]
+    Keep,
+    /// Use the smallest integer of the same signedness that holds the value,
+    /// `300u64` becomes a `U16`.
+    Compact,
+}
+
+/// How unit enum variants are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnitVariant {
+    /// The variant name as a `String`.
+    #[default]
+    String,
+    /// A map with the variant name as its only key and `Null` as value.
+    Map,
+}
+
+/// Options for [`Encoder`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncoderOptions {
+    skip_nulls: bool,
+    integers: IntegerWidth,
+    unit_variants: UnitVariant,
+    bytes_as_binary: bool,
+}
+
+impl EncoderOptions {
+    pub fn new() -> EncoderOptions {
+        EncoderOptions::default()
+    }
+
+    /// Omit struct fields and map entries that serialize to `Null`, such as
+    /// `None`, instead of writing them.
+    pub fn skip_nulls(mut self, skip: bool) -> EncoderOptions {
+        self.skip_nulls = skip;
+        self
+    }
+
+    pub fn integers(mut self, width: IntegerWidth) -> EncoderOptions {
+        self.integers = width;
+        self
+    }
+
+    pub fn unit_variants(mut self, repr: UnitVariant) -> EncoderOptions {
+        self.unit_variants = repr;
+        self
+    }
+
+    /// Write non-empty sequences of `u8`, such as `Vec<u8>`, as `Binary`
+    /// without going through `serde_bytes`. An empty one stays an empty
+    /// `Array`, which reads back as an empty `Vec<u8>` all the same.
+    pub fn bytes_as_binary(mut self, binary: bool) -> EncoderOptions {
+        self.bytes_as_binary = binary;
+        self
+    }
+}
+
 #[derive(Default)]
-pub struct Encoder;
+pub struct Encoder {
+    options: EncoderOptions,
+}
 
 impl Encoder {
     pub fn new() -> Encoder {
-        Encoder
+        Encoder::default()
+    }
+
+    pub fn with_options(options: EncoderOptions) -> Encoder {
+        Encoder { options }
+    }
+
+    fn signed(&self, value: i64, keep: Value) -> Value {
+        if self.options.integers == IntegerWidth::Keep {
+            return keep;
+        }
+
+        if let Ok(v) = i8::try_from(value) {
+            Value::I8(v)
+        } else if let Ok(v) = i16::try_from(value) {
+            Value::I16(v)
+        } else if let Ok(v) = i32::try_from(value) {
+            Value::I32(v)
+        } else {
+            Value::I64(value)
+        }
+    }
+
+    fn unsigned(&self, value: u64, keep: Value) -> Value {
+        if self.options.integers == IntegerWidth::Keep {
+            return keep;
+        }
+
+        if let Ok(v) = u8::try_from(value) {
+            Value::U8(v)
+        } else if let Ok(v) = u16::try_from(value) {
+            Value::U16(v)
+        } else if let Ok(v) = u32::try_from(value) {
+            Value::U32(v)
+        } else {
+            Value::U64(value)
+        }
     }
 }
 
@@ -80,6 +214,11 @@ impl Serializer for Encoder {
     type SerializeStruct = StructSerializer;
     type SerializeStructVariant = StructVariantSerializer;
 
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     #[inline]
     fn serialize_bool(self, value: bool) -> EncodeResult<Value> {
         Ok(Value::Bool(value))
@@ -87,42 +226,42 @@ impl Serializer for Encoder {
 
     #[inline]
     fn serialize_i8(self, value: i8) -> EncodeResult<Value> {
-        Ok(Value::I8(value))
+        Ok(self.signed(value as i64, Value::I8(value)))
     }
 
     #[inline]
     fn serialize_u8(self, value: u8) -> EncodeResult<Value> {
-        Ok(Value::U8(value))
+        Ok(self.unsigned(value as u64, Value::U8(value)))
     }
 
     #[inline]
     fn serialize_i16(self, value: i16) -> EncodeResult<Value> {
-        Ok(Value::I16(value))
+        Ok(self.signed(value as i64, Value::I16(value)))
     }
 
     #[inline]
     fn serialize_u16(self, value: u16) -> EncodeResult<Value> {
-        Ok(Value::U16(value))
+        Ok(self.unsigned(value as u64, Value::U16(value)))
     }
 
     #[inline]
     fn serialize_i32(self, value: i32) -> EncodeResult<Value> {
-        Ok(Value::I32(value))
+        Ok(self.signed(value as i64, Value::I32(value)))
     }
 
     #[inline]
     fn serialize_u32(self, value: u32) -> EncodeResult<Value> {
-        Ok(Value::U32(value))
+        Ok(self.unsigned(value as u64, Value::U32(value)))
     }
 
     #[inline]
     fn serialize_i64(self, value: i64) -> EncodeResult<Value> {
-        Ok(Value::I64(value))
+        Ok(self.signed(value, Value::I64(value)))
     }
 
     #[inline]
     fn serialize_u64(self, value: u64) -> EncodeResult<Value> {
-        Ok(Value::U64(value))
+        Ok(self.unsigned(value, Value::U64(value)))
     }
 
     #[inline]
@@ -178,16 +317,26 @@ impl Serializer for Encoder {
         _variant_index: u32,
         variant: &'static str,
     ) -> EncodeResult<Value> {
-        Ok(Value::String(variant.to_string()))
+        match self.options.unit_variants {
+            UnitVariant::String => Ok(Value::String(variant.to_string())),
+            UnitVariant::Map => {
+                let mut unit_variant = Map::new();
+                unit_variant.insert(variant, Value::Null);
+                Ok(Value::Map(unit_variant))
+            }
+        }
     }
 
     #[inline]
     fn serialize_newtype_struct<T: Serialize + ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> EncodeResult<Value> {
-        value.serialize(self)
+        match value.serialize(self)? {
+            Value::Map(map) if name == EXTENDED => Ok(Value::from_extended_map(map)),
+            value => Ok(value),
+        }
     }
 
     #[inline]
@@ -199,7 +348,7 @@ impl Serializer for Encoder {
         value: &T,
     ) -> EncodeResult<Value> {
         let mut newtype_variant = Map::new();
-        newtype_variant.insert(variant, to_nson(value)?);
+        newtype_variant.insert(variant, to_nson_with(value, self.options)?);
         Ok(Value::Map(newtype_variant))
     }
 
@@ -207,6 +356,7 @@ impl Serializer for Encoder {
     fn serialize_seq(self, len: Option<usize>) -> EncodeResult<Self::SerializeSeq> {
         Ok(ArraySerializer {
             inner: Array::with_capacity(len.unwrap_or(0)),
+            options: self.options,
         })
     }
 
@@ -214,6 +364,7 @@ impl Serializer for Encoder {
     fn serialize_tuple(self, len: usize) -> EncodeResult<Self::SerializeTuple> {
         Ok(TupleSerializer {
             inner: Array::with_capacity(len),
+            options: self.options,
         })
     }
 
@@ -225,6 +376,7 @@ impl Serializer for Encoder {
     ) -> EncodeResult<Self::SerializeTupleStruct> {
         Ok(TupleStructSerializer {
             inner: Array::with_capacity(len),
+            options: self.options,
         })
     }
 
@@ -239,6 +391,7 @@ impl Serializer for Encoder {
         Ok(TupleVariantSerializer {
             inner: Array::with_capacity(len),
             name: variant,
+            options: self.options,
         })
     }
 
@@ -247,6 +400,7 @@ impl Serializer for Encoder {
         Ok(MapSerializer {
             inner: Map::new(),
             next_key: None,
+            options: self.options,
         })
     }
 
@@ -256,7 +410,10 @@ impl Serializer for Encoder {
         _name: &'static str,
         _len: usize,
     ) -> EncodeResult<Self::SerializeStruct> {
-        Ok(StructSerializer { inner: Map::new() })
+        Ok(StructSerializer {
+            inner: Map::new(),
+            options: self.options,
+        })
     }
 
     #[inline]
@@ -270,12 +427,14 @@ impl Serializer for Encoder {
         Ok(StructVariantSerializer {
             name: variant,
             inner: Map::new(),
+            options: self.options,
         })
     }
 }
 
 pub struct ArraySerializer {
     inner: Array,
+    options: EncoderOptions,
 }
 
 impl SerializeSeq for ArraySerializer {
@@ -283,17 +442,34 @@ impl SerializeSeq for ArraySerializer {
     type Error = EncodeError;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
-        self.inner.push(to_nson(value)?);
+        self.inner.push(to_nson_with(value, self.options)?);
         Ok(())
     }
 
     fn end(self) -> EncodeResult<Value> {
+        if self.options.bytes_as_binary
+            && !self.inner.is_empty()
+            && self.inner.iter().all(|v| matches!(v, Value::U8(_)))
+        {
+            let bytes = self
+                .inner
+                .into_iter()
+                .map(|v| match v {
+                    Value::U8(b) => b,
+                    _ => unreachable!(),
+                })
+                .collect();
+
+            return Ok(Value::Binary(Binary(bytes)));
+        }
+
         Ok(Value::Array(self.inner))
     }
 }
 
 pub struct TupleSerializer {
     inner: Array,
+    options: EncoderOptions,
 }
 
 impl SerializeTuple for TupleSerializer {
@@ -301,7 +477,7 @@ impl SerializeTuple for TupleSerializer {
     type Error = EncodeError;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
-        self.inner.push(to_nson(value)?);
+        self.inner.push(to_nson_with(value, self.options)?);
         Ok(())
     }
 
@@ -312,6 +488,7 @@ impl SerializeTuple for TupleSerializer {
 
 pub struct TupleStructSerializer {
     inner: Array,
+    options: EncoderOptions,
 }
 
 impl SerializeTupleStruct for TupleStructSerializer {
@@ -319,7 +496,7 @@ impl SerializeTupleStruct for TupleStructSerializer {
     type Error = EncodeError;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
-        self.inner.push(to_nson(value)?);
+        self.inner.push(to_nson_with(value, self.options)?);
         Ok(())
     }
 
@@ -331,6 +508,7 @@ impl SerializeTupleStruct for TupleStructSerializer {
 pub struct TupleVariantSerializer {
     inner: Array,
     name: &'static str,
+    options: EncoderOptions,
 }
 
 impl SerializeTupleVariant for TupleVariantSerializer {
@@ -338,7 +516,7 @@ impl SerializeTupleVariant for TupleVariantSerializer {
     type Error = EncodeError;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
-        self.inner.push(to_nson(value)?);
+        self.inner.push(to_nson_with(value, self.options)?);
         Ok(())
     }
 
@@ -352,6 +530,7 @@ impl SerializeTupleVariant for TupleVariantSerializer {
 pub struct MapSerializer {
     inner: Map,
     next_key: Option<String>,
+    options: EncoderOptions,
 }
 
 impl SerializeMap for MapSerializer {
@@ -359,26 +538,51 @@ impl SerializeMap for MapSerializer {
     type Error = EncodeError;
 
     fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> EncodeResult<()> {
-        self.next_key = match to_nson(&key)? {
-            Value::String(s) => Some(s),
-            other => return Err(EncodeError::InvalidMapKeyType(other)),
-        };
+        self.next_key = Some(map_key(to_nson_with(key, self.options)?)?);
         Ok(())
     }
 
     fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> EncodeResult<()> {
         let key = self.next_key.take().unwrap_or_default();
-        self.inner.insert(key, to_nson(&value)?);
+        let value = to_nson_with(value, self.options)?;
+        if !(self.options.skip_nulls && value == Value::Null) {
+            self.inner.insert(key, value);
+        }
         Ok(())
     }
 
     fn end(self) -> EncodeResult<Value> {
-        Ok(Value::from_extended_map(self.inner))
+        Ok(Value::Map(self.inner))
     }
 }
 
+/// Keys are text: integers and bools are written in decimal and
+/// `true`/`false`, and `Id`, `TimeStamp` and `Binary` the way text formats
+/// write them.
+fn map_key(key: Value) -> EncodeResult<String> {
+    let key = match key {
+        Value::String(s) => s,
+        Value::I8(v) => v.to_string(),
+        Value::U8(v) => v.to_string(),
+        Value::I16(v) => v.to_string(),
+        Value::U16(v) => v.to_string(),
+        Value::I32(v) => v.to_string(),
+        Value::U32(v) => v.to_string(),
+        Value::I64(v) => v.to_string(),
+        Value::U64(v) => v.to_string(),
+        Value::Bool(v) => v.to_string(),
+        Value::Id(id) => id.to_hex(),
+        Value::TimeStamp(ts) => ts.to_rfc3339(),
+        Value::Binary(bytes) => general_purpose::STANDARD.encode(&bytes.0),
+        other => return Err(EncodeError::InvalidMapKeyType(other)),
+    };
+
+    Ok(key)
+}
+
 pub struct StructSerializer {
     inner: Map,
+    options: EncoderOptions,
 }
 
 impl SerializeStruct for StructSerializer {
@@ -390,18 +594,22 @@ impl SerializeStruct for StructSerializer {
         key: &'static str,
         value: &T,
     ) -> EncodeResult<()> {
-        self.inner.insert(key, to_nson(value)?);
+        let value = to_nson_with(value, self.options)?;
+        if !(self.options.skip_nulls && value == Value::Null) {
+            self.inner.insert(key, value);
+        }
         Ok(())
     }
 
     fn end(self) -> EncodeResult<Value> {
-        Ok(Value::from_extended_map(self.inner))
+        Ok(Value::Map(self.inner))
     }
 }
 
 pub struct StructVariantSerializer {
     inner: Map,
     name: &'static str,
+    options: EncoderOptions,
 }
 
 impl SerializeStructVariant for StructVariantSerializer {
@@ -413,49 +621,64 @@ impl SerializeStructVariant for StructVariantSerializer {
         key: &'static str,
         value: &T,
     ) -> EncodeResult<()> {
-        self.inner.insert(key, to_nson(value)?);
+        let value = to_nson_with(value, self.options)?;
+        if !(self.options.skip_nulls && value == Value::Null) {
+            self.inner.insert(key, value);
+        }
         Ok(())
     }
 
     fn end(self) -> EncodeResult<Value> {
-        let var = Value::from_extended_map(self.inner);
-
         let mut struct_variant = Map::new();
-        struct_variant.insert(self.name, var);
+        struct_variant.insert(self.name, self.inner);
 
         Ok(Value::Map(struct_variant))
     }
 }
 
+/// An RFC 3339 string in text formats.
 impl Serialize for TimeStamp {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(&self.to_rfc3339());
+        }
+
         let value = Value::TimeStamp(*self);
         value.serialize(serializer)
     }
 }
 
+/// A hex string in text formats.
 impl Serialize for Id {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(&self.to_hex());
+        }
+
         let value = Value::Id(*self);
         value.serialize(serializer)
     }
 }
 
+/// A base64 string in text formats.
 impl Serialize for Binary {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let value = Value::Binary(self.clone());
-        value.serialize(serializer)
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(&general_purpose::STANDARD.encode(&self.0));
+        }
+
+        serializer.serialize_bytes(&self.0)
     }
 }
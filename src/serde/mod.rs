@@ -10,11 +10,42 @@ use serde::ser::{self, Serialize};
 use crate::Value;
 use crate::spec::DataType;
 
+/// Map keys are always strings, so key deserializers parse integer and
+/// bool keys back from them, as serde_json does. Expects a `key_str`
+/// method on the deserializer.
+macro_rules! parse_key {
+    ($($name:ident => $visit:ident: $ty:ty;)*) => {
+        $(
+            fn $name<V>(self, visitor: V) -> ::core::result::Result<V::Value, Self::Error>
+            where
+                V: ::serde::de::Visitor<'de>,
+            {
+                let key = self.key_str()?;
+                match key.parse::<$ty>() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(::serde::de::Error::invalid_type(
+                        ::serde::de::Unexpected::Str(key),
+                        &visitor,
+                    )),
+                }
+            }
+        )*
+    };
+}
+
+/// Newtype struct name that `Map`, and `Value` for its `$tim` and `$mid`
+/// markers, go through. Other formats see through it, our encoders and
+/// decoders take it to mean the map inside is in the extended form: keys
+/// escaped with `escape_key` and a single marker entry standing for a typed
+/// value. Any other map keeps its keys as they are.
+pub(crate) const EXTENDED: &str = "$nson::Extended";
+
+pub mod bytes;
 pub mod decode;
 pub mod encode;
 
-use decode::Decoder;
-use encode::Encoder;
+pub use decode::{Decoder, DecoderOptions};
+pub use encode::{Encoder, EncoderOptions, IntegerWidth, UnitVariant};
 
 impl ser::Serialize for DataType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -46,14 +77,49 @@ pub fn to_nson<T: Serialize + ?Sized>(value: &T) -> EncodeResult<Value> {
     value.serialize(ser)
 }
 
+/// Serialize with the given options instead of the defaults.
+pub fn to_nson_with<T: Serialize + ?Sized>(
+    value: &T,
+    options: EncoderOptions,
+) -> EncodeResult<Value> {
+    let ser = Encoder::with_options(options);
+    value.serialize(ser)
+}
+
 pub fn from_nson<'de, T: Deserialize<'de>>(value: Value) -> DecodeResult<T> {
     let de = Decoder::new(value);
     Deserialize::deserialize(de)
 }
 
+/// Deserialize with the given options instead of the defaults.
+pub fn from_nson_with<'de, T: Deserialize<'de>>(
+    value: Value,
+    options: DecoderOptions,
+) -> DecodeResult<T> {
+    let de = Decoder::with_options(value, options);
+    Deserialize::deserialize(de)
+}
+
+/// Deserialize from a borrowed value, without cloning it.
+pub fn from_nson_ref<'de, T: Deserialize<'de>>(value: &'de Value) -> DecodeResult<T> {
+    Deserialize::deserialize(value)
+}
+
+/// Error raised while deserializing from a `Value`.
+///
+/// Besides the kind of failure, it records where in the value it happened:
+/// `path` holds the dotted map keys and array indexes leading to the
+/// offending element, and `found` its `DataType`.
+#[derive(Debug)]
+pub struct DecodeError {
+    kind: DecodeErrorKind,
+    path: String,
+    found: Option<DataType>,
+}
+
 #[derive(Debug)]
 #[non_exhaustive]
-pub enum DecodeError {
+pub enum DecodeErrorKind {
     ExpectedField(&'static str),
     UnknownField(String),
     SyntaxError(String),
@@ -66,23 +132,84 @@ pub enum DecodeError {
     Unknown(String),
 }
 
+impl DecodeError {
+    pub fn kind(&self) -> &DecodeErrorKind {
+        &self.kind
+    }
+
+    /// Dotted path to the element that failed, empty for the root.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Type of the element that failed, if known.
+    pub fn found(&self) -> Option<DataType> {
+        self.found
+    }
+
+    /// Prefix the path with `segment`, used when the error comes from a
+    /// nested value.
+    pub(crate) fn within(mut self, segment: &str) -> DecodeError {
+        if self.path.is_empty() {
+            self.path = segment.into();
+        } else {
+            self.path.insert(0, '.');
+            self.path.insert_str(0, segment);
+        }
+
+        self
+    }
+
+    /// Record the type of the element that failed, unless a nested value
+    /// already did.
+    pub(crate) fn found_in(mut self, found: DataType) -> DecodeError {
+        if self.found.is_none() {
+            self.found = Some(found);
+        }
+
+        self
+    }
+}
+
+impl From<DecodeErrorKind> for DecodeError {
+    fn from(kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            kind,
+            path: String::new(),
+            found: None,
+        }
+    }
+}
+
 impl fmt::Display for DecodeError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            DecodeError::ExpectedField(field_type) => {
+        if !self.path.is_empty() {
+            write!(fmt, "{}: ", self.path)?;
+        }
+
+        match self.kind {
+            DecodeErrorKind::ExpectedField(field_type) => {
                 write!(fmt, "Expected a field of type `{}`", field_type)
             }
-            DecodeError::UnknownField(ref field) => write!(fmt, "Unknown field `{}`", field),
-            DecodeError::SyntaxError(ref inner) => inner.fmt(fmt),
-            DecodeError::EndOfStream => write!(fmt, "End of stream"),
-            DecodeError::InvalidType(ref desc) => desc.fmt(fmt),
-            DecodeError::InvalidLength(ref len, ref desc) => {
+            DecodeErrorKind::UnknownField(ref field) => write!(fmt, "Unknown field `{}`", field),
+            DecodeErrorKind::SyntaxError(ref inner) => inner.fmt(fmt),
+            DecodeErrorKind::EndOfStream => write!(fmt, "End of stream"),
+            DecodeErrorKind::InvalidType(ref desc) => match self.found {
+                Some(found) => write!(fmt, "expected {}, found {:?}", desc, found),
+                None => write!(fmt, "expected {}", desc),
+            },
+            DecodeErrorKind::InvalidLength(ref len, ref desc) => {
                 write!(fmt, "Expecting length {}, {}", len, desc)
             }
-            DecodeError::DuplicatedField(ref field) => write!(fmt, "Duplicated field `{}`", field),
-            DecodeError::UnknownVariant(ref var) => write!(fmt, "Unknown variant `{}`", var),
-            DecodeError::InvalidValue(ref desc) => desc.fmt(fmt),
-            DecodeError::Unknown(ref inner) => inner.fmt(fmt),
+            DecodeErrorKind::DuplicatedField(ref field) => {
+                write!(fmt, "Duplicated field `{}`", field)
+            }
+            DecodeErrorKind::UnknownVariant(ref var) => write!(fmt, "Unknown variant `{}`", var),
+            DecodeErrorKind::InvalidValue(ref desc) => match self.found {
+                Some(found) => write!(fmt, "invalid value, expected {}, found {:?}", desc, found),
+                None => write!(fmt, "invalid value, expected {}", desc),
+            },
+            DecodeErrorKind::Unknown(ref inner) => inner.fmt(fmt),
         }
     }
 }
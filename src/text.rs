@@ -0,0 +1,678 @@
+//! Text
+//!
+//! A notation for values that keeps every NSON type, for fixtures, logs and
+//! tools. It is what `Display` prints and what `FromStr` reads:
+//!
+//! ```text
+//! {temp: 21.5f32, id: Id("016f9dbd9df7f7dc9c86d573"), ts: TS(1700000000), raw: b"0a0b", n: 3u8}
+//! ```
+//!
+//! - Numbers take a Rust suffix: `3u8`, `-4i64`, `1.5f32`. Without one, an
+//!   integer is `I32` and a number with a `.` or an exponent is `F64`, as
+//!   in the [`m!`](crate::m) macro. Non-finite floats are `NaN`, `inf` and
+//!   `-inf`, with the same suffixes.
+//! - Strings are double quoted, with `\"`, `\\`, `\n`, `\r`, `\t`, `\0` and
+//!   `\u{..}` escapes.
+//! - `null`, `true` and `false` as in JSON.
+//! - `b"0a0b"` is a binary in hex, `TS(1700000000)` a timestamp and
+//!   `Id("016f9dbd9df7f7dc9c86d573")` an id.
+//! - Arrays are `[1, 2]`, maps `{key: 1, "other key": 2}`. Keys made of
+//!   ASCII letters, digits, `_` and `$` need no quotes. A trailing comma is
+//!   allowed.
+//!
+//! The alternate flag, `{:#}`, prints one entry per line with indentation.
+//!
+//! ```
+//! use nson::{m, Map, Value};
+//!
+//! let map = m! {"n": 3u8, "list": [1, "a"]};
+//! assert_eq!(map.to_string(), r#"{n: 3u8, list: [1, "a"]}"#);
+//! assert_eq!(format!("{:#}", map), "{\n    n: 3u8,\n    list: [\n        1,\n        \"a\"\n    ]\n}");
+//! assert_eq!(map.to_string().parse::<Map>().unwrap(), map);
+//!
+//! let value: Value = "TS(5)".parse().unwrap();
+//! assert_eq!(value, Value::TimeStamp(nson::TimeStamp(5)));
+//! ```
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+use core::str::FromStr;
+
+use crate::array::Array;
+use crate::id::Id;
+use crate::json_text::MAX_DEPTH;
+use crate::map::Map;
+use crate::value::{Binary, TimeStamp, Value};
+
+/// Error returned by `FromStr`, `offset` is the byte where reading stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl core::error::Error for Error {}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Write `value` in the text notation, one entry per line from `indent`
+/// levels deep when `indent` is `Some`.
+pub(crate) fn write_value<W: Write>(
+    writer: &mut W,
+    value: &Value,
+    indent: Option<usize>,
+) -> fmt::Result {
+    match value {
+        Value::F32(f) if f.is_finite() => write!(writer, "{:?}f32", f),
+        Value::F64(f) if f.is_finite() => write!(writer, "{:?}", f),
+        Value::F32(f) => write_non_finite(writer, *f as f64, "f32"),
+        Value::F64(f) => write_non_finite(writer, *f, ""),
+        Value::I32(i) => write!(writer, "{}", i),
+        Value::I64(i) => write!(writer, "{}i64", i),
+        Value::U32(u) => write!(writer, "{}u32", u),
+        Value::U64(u) => write!(writer, "{}u64", u),
+        Value::I8(i) => write!(writer, "{}i8", i),
+        Value::U8(u) => write!(writer, "{}u8", u),
+        Value::I16(i) => write!(writer, "{}i16", i),
+        Value::U16(u) => write!(writer, "{}u16", u),
+        Value::String(s) => write_str(writer, s),
+        Value::Array(array) => write_array(writer, array, indent),
+        Value::Map(map) => write_map(writer, map, indent),
+        Value::Bool(b) => write!(writer, "{}", b),
+        Value::Null => writer.write_str("null"),
+        Value::Binary(bin) => write!(writer, "b\"{}\"", const_hex::encode(&bin.0)),
+        Value::TimeStamp(t) => write!(writer, "TS({})", t.0),
+        Value::Id(id) => write!(writer, "Id(\"{}\")", id),
+    }
+}
+
+pub(crate) fn write_array<W: Write>(
+    writer: &mut W,
+    array: &Array,
+    indent: Option<usize>,
+) -> fmt::Result {
+    if array.is_empty() {
+        return writer.write_str("[]");
+    }
+
+    writer.write_char('[')?;
+    for (i, value) in array.iter().enumerate() {
+        separator(writer, i, indent.map(|n| n + 1))?;
+        write_value(writer, value, indent.map(|n| n + 1))?;
+    }
+    separator(writer, usize::MAX, indent)?;
+    writer.write_char(']')
+}
+
+pub(crate) fn write_map<W: Write>(writer: &mut W, map: &Map, indent: Option<usize>) -> fmt::Result {
+    if map.is_empty() {
+        return writer.write_str("{}");
+    }
+
+    writer.write_char('{')?;
+    for (i, (key, value)) in map.iter().enumerate() {
+        separator(writer, i, indent.map(|n| n + 1))?;
+
+        if !key.is_empty() && key.bytes().all(is_key_byte) {
+            writer.write_str(key)?;
+        } else {
+            write_str(writer, key)?;
+        }
+        writer.write_str(": ")?;
+
+        write_value(writer, value, indent.map(|n| n + 1))?;
+    }
+    separator(writer, usize::MAX, indent)?;
+    writer.write_char('}')
+}
+
+/// Write what comes before entry `index` of a container, or before its end
+/// for `usize::MAX`.
+fn separator<W: Write>(writer: &mut W, index: usize, indent: Option<usize>) -> fmt::Result {
+    match indent {
+        Some(depth) => {
+            if index != 0 && index != usize::MAX {
+                writer.write_char(',')?;
+            }
+            writer.write_char('\n')?;
+            for _ in 0..depth {
+                writer.write_str("    ")?;
+            }
+            Ok(())
+        }
+        None if index != 0 && index != usize::MAX => writer.write_str(", "),
+        None => Ok(()),
+    }
+}
+
+fn write_non_finite<W: Write>(writer: &mut W, f: f64, suffix: &str) -> fmt::Result {
+    if f.is_nan() {
+        write!(writer, "NaN{}", suffix)
+    } else if f < 0.0 {
+        write!(writer, "-inf{}", suffix)
+    } else {
+        write!(writer, "inf{}", suffix)
+    }
+}
+
+fn write_str<W: Write>(writer: &mut W, s: &str) -> fmt::Result {
+    writer.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => writer.write_str("\\\"")?,
+            '\\' => writer.write_str("\\\\")?,
+            '\n' => writer.write_str("\\n")?,
+            '\r' => writer.write_str("\\r")?,
+            '\t' => writer.write_str("\\t")?,
+            '\0' => writer.write_str("\\0")?,
+            c if c.is_control() => write!(writer, "\\u{{{:x}}}", c as u32)?,
+            c => writer.write_char(c)?,
+        }
+    }
+    writer.write_char('"')
+}
+
+fn is_key_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$'
+}
+
+impl FromStr for Value {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Value> {
+        let mut parser = Parser { text, offset: 0 };
+        let value = parser.value(0)?;
+        parser.end()?;
+        Ok(value)
+    }
+}
+
+impl FromStr for Map {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Map> {
+        let mut parser = Parser { text, offset: 0 };
+        parser.whitespace();
+        if parser.peek() != Some(b'{') {
+            return Err(parser.error("expected a map"));
+        }
+        let map = parser.map(1)?;
+        parser.end()?;
+        Ok(map)
+    }
+}
+
+impl FromStr for Array {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Array> {
+        let mut parser = Parser { text, offset: 0 };
+        parser.whitespace();
+        if parser.peek() != Some(b'[') {
+            return Err(parser.error("expected an array"));
+        }
+        let array = parser.array(1)?;
+        parser.end()?;
+        Ok(array)
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> Error {
+        Error {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.offset).copied()
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.offset..]
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.offset += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str, message: &'static str) -> Result<()> {
+        self.whitespace();
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.whitespace();
+        if self.offset < self.text.len() {
+            return Err(self.error("trailing characters"));
+        }
+        Ok(())
+    }
+
+    /// Read a value inside `depth` maps and arrays.
+    fn value(&mut self, depth: usize) -> Result<Value> {
+        self.whitespace();
+
+        match self.peek() {
+            Some(b'{' | b'[') if depth == MAX_DEPTH => Err(self.error("nested too deeply")),
+            Some(b'{') => self.map(depth + 1).map(Value::Map),
+            Some(b'[') => self.array(depth + 1).map(Value::Array),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => self.word(),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn word(&mut self) -> Result<Value> {
+        if self.eat("b\"") {
+            self.offset -= 1;
+            let start = self.offset;
+            let hex = self.string()?;
+            return Binary::from_hex(&hex)
+                .map(Value::Binary)
+                .map_err(|_| Error {
+                    offset: start,
+                    message: "invalid hex",
+                });
+        }
+
+        if self.eat("Id(") {
+            self.whitespace();
+            let start = self.offset;
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a string"));
+            }
+            let id = Id::with_string(&self.string()?).map_err(|_| Error {
+                offset: start,
+                message: "invalid id",
+            })?;
+            self.expect(")", "expected `)`")?;
+            return Ok(Value::Id(id));
+        }
+
+        if self.eat("TS(") {
+            self.whitespace();
+            let start = self.offset;
+            let len = self.rest().bytes().take_while(u8::is_ascii_digit).count();
+            let ts = self.rest()[..len].parse().map_err(|_| Error {
+                offset: start,
+                message: "invalid timestamp",
+            })?;
+            self.offset += len;
+            self.expect(")", "expected `)`")?;
+            return Ok(Value::TimeStamp(TimeStamp(ts)));
+        }
+
+        if self.eat("NaN") || self.eat("inf") {
+            return self.number_from(self.offset - 3);
+        }
+
+        for (word, value) in [
+            ("null", Value::Null),
+            ("true", Value::Bool(true)),
+            ("false", Value::Bool(false)),
+        ] {
+            if self.eat(word) {
+                return Ok(value);
+            }
+        }
+
+        Err(self.error("expected a value"))
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Array> {
+        self.offset += 1;
+        let mut array = Array::new();
+
+        loop {
+            self.whitespace();
+            if self.eat("]") {
+                return Ok(array);
+            }
+
+            array.push(self.value(depth)?);
+
+            self.whitespace();
+            if self.eat("]") {
+                return Ok(array);
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected `,` or `]`"));
+            }
+        }
+    }
+
+    fn map(&mut self, depth: usize) -> Result<Map> {
+        self.offset += 1;
+        let mut map = Map::new();
+
+        loop {
+            self.whitespace();
+            if self.eat("}") {
+                return Ok(map);
+            }
+
+            let key = self.key()?;
+            self.expect(":", "expected `:`")?;
+            map.insert(key, self.value(depth)?);
+
+            self.whitespace();
+            if self.eat("}") {
+                return Ok(map);
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected `,` or `}`"));
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String> {
+        if self.peek() == Some(b'"') {
+            return self.string();
+        }
+
+        let len = self.rest().bytes().take_while(|&b| is_key_byte(b)).count();
+        if len == 0 {
+            return Err(self.error("expected a key"));
+        }
+
+        let key = self.rest()[..len].into();
+        self.offset += len;
+        Ok(key)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.offset += 1;
+        let mut out = String::new();
+
+        loop {
+            let mut chars = self.rest().chars();
+            let c = chars.next().ok_or(self.error("unterminated string"))?;
+            self.offset += c.len_utf8();
+
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let c = match self.rest().chars().next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some('u') => {
+                            self.offset += 1;
+                            out.push(self.unicode()?);
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    self.offset += 1;
+                    out.push(c);
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    /// Read the `{..}` of a `\u{..}` escape.
+    fn unicode(&mut self) -> Result<char> {
+        let start = self.offset;
+        let invalid = Error {
+            offset: start,
+            message: "invalid unicode escape",
+        };
+
+        let digits = self
+            .rest()
+            .strip_prefix('{')
+            .and_then(|rest| rest.split_once('}'))
+            .map(|(digits, _)| digits)
+            .ok_or(invalid.clone())?;
+
+        let c = u32::from_str_radix(digits, 16)
+            .ok()
+            .filter(|_| (1..=6).contains(&digits.len()))
+            .and_then(char::from_u32)
+            .ok_or(invalid)?;
+
+        self.offset += digits.len() + 2;
+        Ok(c)
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.offset;
+        self.eat("-");
+
+        if self.eat("inf") {
+            return self.number_from(start);
+        }
+
+        let digits = |parser: &mut Self| {
+            let len = parser.rest().bytes().take_while(u8::is_ascii_digit).count();
+            parser.offset += len;
+            len
+        };
+
+        if digits(self) == 0 {
+            return Err(self.error("invalid number"));
+        }
+
+        if self.eat(".") && digits(self) == 0 {
+            return Err(self.error("invalid number"));
+        }
+
+        if self.eat("e") || self.eat("E") {
+            if !self.eat("-") {
+                self.eat("+");
+            }
+            if digits(self) == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+
+        self.number_from(start)
+    }
+
+    /// Read the suffix of the number from `start` to here.
+    fn number_from(&mut self, start: usize) -> Result<Value> {
+        let number = &self.text[start..self.offset];
+        let is_float = number.contains(['.', 'e', 'E', 'N', 'i']);
+
+        let len = self
+            .rest()
+            .bytes()
+            .take_while(u8::is_ascii_alphanumeric)
+            .count();
+        let suffix = &self.text[self.offset..self.offset + len];
+        self.offset += len;
+
+        let out_of_range = Error {
+            offset: start,
+            message: "number out of range",
+        };
+
+        macro_rules! integer {
+            ($variant:ident) => {
+                if is_float {
+                    Err(Error {
+                        offset: start,
+                        message: "invalid number",
+                    })
+                } else {
+                    number.parse().map(Value::$variant).or(Err(out_of_range))
+                }
+            };
+        }
+
+        macro_rules! float {
+            ($variant:ident, $ty:ident) => {
+                match number {
+                    "NaN" => Ok(Value::$variant($ty::NAN)),
+                    "inf" => Ok(Value::$variant($ty::INFINITY)),
+                    "-inf" => Ok(Value::$variant($ty::NEG_INFINITY)),
+                    _ => match number.parse::<$ty>() {
+                        Ok(f) if f.is_finite() => Ok(Value::$variant(f)),
+                        _ => Err(out_of_range),
+                    },
+                }
+            };
+        }
+
+        match suffix {
+            "" if is_float => float!(F64, f64),
+            "" | "i32" => integer!(I32),
+            "i64" => integer!(I64),
+            "u32" => integer!(U32),
+            "u64" => integer!(U64),
+            "i8" => integer!(I8),
+            "u8" => integer!(U8),
+            "i16" => integer!(I16),
+            "u16" => integer!(U16),
+            "f32" => float!(F32, f32),
+            "f64" => float!(F64, f64),
+            _ => Err(Error {
+                offset: start + number.len(),
+                message: "invalid number suffix",
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::format;
+    use alloc::string::ToString;
+
+    use super::Error;
+    use crate::fixture::values;
+    use crate::{Array, Binary, Id, Map, TimeStamp, Value, m};
+
+    use crate::vec as __vec;
+
+    #[test]
+    fn round_trip() {
+        let map = values();
+
+        let text = map.to_string();
+        assert_eq!(text.parse::<Map>().unwrap(), map);
+        assert_eq!(text.parse::<Value>().unwrap(), Value::Map(map.clone()));
+
+        let pretty = format!("{:#}", map);
+        assert!(pretty.contains("\n    array: [\n        1,\n"));
+        assert_eq!(pretty.parse::<Map>().unwrap(), map);
+
+        let nan = Value::F32(f32::NAN);
+        assert_eq!(nan.to_string(), "NaNf32");
+        assert!(matches!("NaNf32".parse(), Ok(Value::F32(f)) if f.is_nan()));
+        assert!(matches!("NaN".parse(), Ok(Value::F64(f)) if f.is_nan()));
+    }
+
+    #[test]
+    fn display() {
+        let map = m! {
+            "temp": 21.5f32,
+            "id": Id::with_string("016f9dbd9df7f7dc9c86d573").unwrap(),
+            "ts": TimeStamp(1700000000),
+            "raw": Binary(__vec![10, 11]),
+            "n": 3u8,
+        };
+        let text = r#"{temp: 21.5f32, id: Id("016f9dbd9df7f7dc9c86d573"), ts: TS(1700000000), raw: b"0a0b", n: 3u8}"#;
+        assert_eq!(map.to_string(), text);
+        assert_eq!(Value::Map(map).to_string(), text);
+
+        let array = Array::from_vec(__vec![Value::F64(2.0), Value::I64(-1), Value::Null]);
+        assert_eq!(array.to_string(), "[2.0, -1i64, null]");
+        assert_eq!(Value::String("a\nb".into()).to_string(), r#""a\nb""#);
+        assert_eq!(format!("{:#}", Map::new()), "{}");
+    }
+
+    #[test]
+    fn read() {
+        let value: Value = r#" { a : [ 1 , -2i64 , 3.5 , 4f32, 1e3 , ] , "b c": "\u{e9}\t" , } "#
+            .parse()
+            .unwrap();
+
+        let expected = Value::from(m! {
+            "a": [1i32, -2i64, 3.5f64, 4f32, 1000f64],
+            "b c": "é\t",
+        });
+        assert_eq!(value, expected);
+
+        assert_eq!("[]".parse::<Array>().unwrap(), Array::new());
+        assert_eq!(
+            "-inf".parse::<Value>().unwrap(),
+            Value::F64(f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text: &str| match text.parse::<Value>() {
+            Err(Error { offset, message }) => (offset, message),
+            Ok(value) => panic!("{:?}", value),
+        };
+
+        assert_eq!(error(""), (0, "unexpected end of input"));
+        assert_eq!(error("[1 2]"), (3, "expected `,` or `]`"));
+        assert_eq!(error("{a 1}"), (3, "expected `:`"));
+        assert_eq!(error("{: 1}"), (1, "expected a key"));
+        assert_eq!(error("300u8"), (0, "number out of range"));
+        assert_eq!(error("3000000000"), (0, "number out of range"));
+        assert_eq!(error("1e39f32"), (0, "number out of range"));
+        assert_eq!(error("1.5u8"), (0, "invalid number"));
+        assert_eq!(error("1x"), (1, "invalid number suffix"));
+        assert_eq!(error("1."), (2, "invalid number"));
+        assert_eq!(error("\"abc"), (4, "unterminated string"));
+        assert_eq!(error("\"\\x\""), (2, "invalid escape"));
+        assert_eq!(error("\"\\u{d800}\""), (3, "invalid unicode escape"));
+        assert_eq!(error("b\"0g\""), (1, "invalid hex"));
+        assert_eq!(error("Id(\"01\")"), (3, "invalid id"));
+        assert_eq!(error("TS(-1)"), (3, "invalid timestamp"));
+        assert_eq!(error("nul"), (0, "expected a value"));
+        assert_eq!(error("1 2"), (2, "trailing characters"));
+        assert_eq!(error(&"[".repeat(1_000_000)), (256, "nested too deeply"));
+        assert_eq!(error(&"{a: ".repeat(300)), (256 * 4, "nested too deeply"));
+
+        assert_eq!(
+            "[1]".parse::<Map>().unwrap_err().to_string(),
+            "expected a map at offset 0"
+        );
+        assert_eq!(
+            "{}".parse::<Array>().unwrap_err().message,
+            "expected an array"
+        );
+        assert_eq!(
+            "[".repeat(1_000_000).parse::<Array>().unwrap_err().offset,
+            256
+        );
+    }
+}
@@ -0,0 +1,650 @@
+//! Transcode
+//!
+//! Converts between NSON bytes and JSON text as a stream, without building
+//! a `Value` for the whole document: containers are walked entry by entry
+//! and only scalars, strings and binaries are held in memory.
+//!
+//! The NSON side is a sequence of maps, as written by `Map::to_bytes`. The
+//! JSON side follows the conventions of [`json_text`](crate::json_text).
+//! Reads and writes are small, so wrap files and sockets in a buffered
+//! reader or writer.
+//!
+//! Going from JSON to NSON, each document is built in memory before being
+//! written, since an NSON map starts with its length.
+//!
+//! ```
+//! use nson::json_text::JsonMode;
+//! use nson::{m, transcode};
+//!
+//! let mut nson = m! {"id": 1u8}.to_bytes().unwrap();
+//! nson.extend(m! {"id": 2u8}.to_bytes().unwrap());
+//!
+//! let mut ndjson = Vec::new();
+//! transcode::nson_to_ndjson(&mut nson.as_slice(), &mut ndjson, JsonMode::Canonical).unwrap();
+//! assert_eq!(ndjson, b"{\"id\":{\"$u8\":1}}\n{\"id\":{\"$u8\":2}}\n");
+//!
+//! let mut back = Vec::new();
+//! transcode::ndjson_to_nson(&mut ndjson.as_slice(), &mut back).unwrap();
+//! assert_eq!(back, nson);
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write as _};
+
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+#[cfg(not(feature = "std"))]
+use crate::io::{self, Read, Write};
+
+use crate::decode::{self, DecodeError};
+use crate::encode::{self, EncodeError};
+use crate::json_text::{self, JsonMode, Reader, Source};
+use crate::spec::DataType;
+use crate::value::{Value, unescape_key};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading JSON or writing either format failed.
+    Io(io::Error),
+    /// The NSON input is malformed.
+    Decode(DecodeError),
+    /// The JSON input can not be written as NSON, such as an empty key.
+    Encode(EncodeError),
+    /// The JSON input is malformed, offsets count from the start of the
+    /// stream.
+    Json(json_text::Error),
+}
+
+impl Error {
+    fn within(self, segment: &str) -> Error {
+        match self {
+            Error::Json(err) => Error::Json(err.within(segment)),
+            err => err,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Error {
+        Error::Decode(err)
+    }
+}
+
+impl From<EncodeError> for Error {
+    fn from(err: EncodeError) -> Error {
+        Error::Encode(err)
+    }
+}
+
+impl From<json_text::Error> for Error {
+    fn from(err: json_text::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref inner) => inner.fmt(fmt),
+            Error::Decode(ref inner) => inner.fmt(fmt),
+            Error::Encode(ref inner) => inner.fmt(fmt),
+            Error::Json(ref inner) => inner.fmt(fmt),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref inner) => Some(inner),
+            Error::Decode(ref inner) => Some(inner),
+            Error::Encode(ref inner) => Some(inner),
+            Error::Json(ref inner) => Some(inner),
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Write one NSON map from `reader` as JSON text.
+pub fn nson_to_json(reader: &mut impl Read, writer: &mut impl Write, mode: JsonMode) -> Result<()> {
+    decode::read_map_len(reader)?;
+    map_to_json(reader, &mut Output::new(writer), mode, 1)
+}
+
+/// Write every NSON map from `reader`, up to its end, as one line of JSON
+/// text each. Returns the number of maps.
+pub fn nson_to_ndjson(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    mode: JsonMode,
+) -> Result<usize> {
+    let mut output = Output::new(writer);
+    let mut count = 0;
+
+    while map_len_or_end(reader)? {
+        map_to_json(reader, &mut output, mode, 1)?;
+        output.text(|w| w.write_char('\n'))?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Write one JSON object from `reader` as an NSON map. Anything but
+/// whitespace after it is an error.
+pub fn json_to_nson(reader: &mut impl Read, writer: &mut impl Write) -> Result<()> {
+    let mut json = Reader::new(IoSource::new(reader));
+    let mut buf = Vec::new();
+
+    read_document(&mut json, &mut buf, |json| {
+        json.whitespace();
+        if json.peek().is_some() {
+            return Err(json.error("trailing characters").into());
+        }
+        Ok(())
+    })?;
+
+    writer.write_all(&buf)?;
+    Ok(())
+}
+
+/// Write every line of NDJSON from `reader` as an NSON map. Blank lines are
+/// skipped. Returns the number of maps.
+pub fn ndjson_to_nson(reader: &mut impl Read, writer: &mut impl Write) -> Result<usize> {
+    let mut json = Reader::new(IoSource::new(reader));
+    let mut buf = Vec::new();
+    let mut count = 0;
+
+    loop {
+        json.whitespace();
+        if json.peek().is_none() {
+            json.source().check()?;
+            return Ok(count);
+        }
+
+        buf.clear();
+        read_document(&mut json, &mut buf, |json| {
+            while json.eat(b' ') || json.eat(b'\t') || json.eat(b'\r') {}
+            if !json.eat(b'\n') && json.peek().is_some() {
+                return Err(json.error("expected a new line").into());
+            }
+            Ok(())
+        })?;
+
+        writer.write_all(&buf)?;
+        count += 1;
+    }
+}
+
+/// Read the length of the next map, `false` if the input ends before it.
+fn map_len_or_end(reader: &mut impl Read) -> Result<bool> {
+    let mut len = [0; 4];
+    if reader.read(&mut len[..1])? == 0 {
+        return Ok(false);
+    }
+    reader.read_exact(&mut len[1..])?;
+
+    decode::check_map_len(u32::from_le_bytes(len))?;
+    Ok(true)
+}
+
+/// Adapts an `io` writer to `fmt::Write`, keeping the error `fmt` drops.
+struct Output<'a, W> {
+    writer: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: Write> Output<'a, W> {
+    fn new(writer: &'a mut W) -> Output<'a, W> {
+        Output {
+            writer,
+            error: None,
+        }
+    }
+
+    fn text(&mut self, f: impl FnOnce(&mut Self) -> fmt::Result) -> Result<()> {
+        f(self).map_err(|_| Error::Io(self.error.take().expect("only writing fails")))
+    }
+}
+
+impl<W: Write> fmt::Write for Output<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+/// Write the entries of a map whose length was read, `depth` counting it
+/// and the maps and arrays around it.
+fn map_to_json<W: Write>(
+    reader: &mut impl Read,
+    output: &mut Output<'_, W>,
+    mode: JsonMode,
+    depth: usize,
+) -> Result<()> {
+    output.text(|w| w.write_char('{'))?;
+
+    let mut first = true;
+    while let Some(key) = decode::read_key(reader)? {
+        if !first {
+            output.text(|w| w.write_char(','))?;
+        }
+        first = false;
+
+        output.text(|w| json_text::write_key(w, &key, mode))?;
+        let tag = decode::read_tag(reader)?;
+        value_to_json(reader, output, tag, mode, depth)?;
+    }
+
+    output.text(|w| w.write_char('}'))
+}
+
+/// Write a value inside `depth` maps and arrays.
+fn value_to_json<W: Write>(
+    reader: &mut impl Read,
+    output: &mut Output<'_, W>,
+    tag: DataType,
+    mode: JsonMode,
+    depth: usize,
+) -> Result<()> {
+    match tag {
+        DataType::Map | DataType::Array if depth == json_text::MAX_DEPTH => {
+            Err(DecodeError::Unknown("nested too deeply".to_string()).into())
+        }
+        DataType::Map => {
+            decode::read_map_len(reader)?;
+            map_to_json(reader, output, mode, depth + 1)
+        }
+        DataType::Array => {
+            decode::read_array_len(reader)?;
+            output.text(|w| w.write_char('['))?;
+
+            let mut first = true;
+            loop {
+                let tag = decode::read_u8(reader)?;
+                if tag == 0 {
+                    break;
+                }
+                let tag = DataType::from(tag).ok_or(DecodeError::UnrecognizedElementType(tag))?;
+
+                if !first {
+                    output.text(|w| w.write_char(','))?;
+                }
+                first = false;
+
+                value_to_json(reader, output, tag, mode, depth + 1)?;
+            }
+
+            output.text(|w| w.write_char(']'))
+        }
+        _ => {
+            let value = decode::decode_value_with_tag(reader, tag as u8)?;
+            output.text(|w| json_text::write_with(w, &value, mode))
+        }
+    }
+}
+
+/// Reads bytes from an `io` reader through a buffer. A read error ends the
+/// input and is kept, to be reported instead of the syntax error that
+/// follows.
+struct IoSource<'a, R> {
+    reader: &'a mut R,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    error: Option<io::Error>,
+}
+
+impl<'a, R: Read> IoSource<'a, R> {
+    fn new(reader: &'a mut R) -> IoSource<'a, R> {
+        IoSource {
+            reader,
+            buf: alloc::vec![0; 4096],
+            pos: 0,
+            len: 0,
+            error: None,
+        }
+    }
+
+    fn check(&mut self) -> Result<()> {
+        match self.error.take() {
+            Some(err) => Err(Error::Io(err)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Source for IoSource<'_, R> {
+    fn peek(&mut self) -> Option<u8> {
+        if self.pos == self.len && self.error.is_none() {
+            match self.reader.read(&mut self.buf) {
+                Ok(len) => {
+                    self.pos = 0;
+                    self.len = len;
+                }
+                Err(err) => self.error = Some(err),
+            }
+        }
+
+        self.buf[..self.len].get(self.pos).copied()
+    }
+
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+}
+
+/// Read a JSON object into `buf` as an NSON map, then what must follow it.
+fn read_document<R: Read>(
+    json: &mut Reader<IoSource<'_, R>>,
+    buf: &mut Vec<u8>,
+    end: impl FnOnce(&mut Reader<IoSource<'_, R>>) -> Result<()>,
+) -> Result<()> {
+    let result = document(json, buf).and_then(|()| end(json));
+    json.source().check()?;
+    result
+}
+
+fn document<S: Source>(json: &mut Reader<S>, buf: &mut Vec<u8>) -> Result<()> {
+    json.whitespace();
+    if json.peek() != Some(b'{') {
+        return Err(json.error("expected a map").into());
+    }
+
+    // a document is a map, not a value that a single marker entry stands for
+    if let Some(value) = json_map(json, buf, 1)? {
+        return Err(json_text::Error::InvalidValue {
+            path: String::new(),
+            expected: DataType::Map,
+            found: json_text::to_string(&value),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Write a JSON value inside `depth` maps and arrays, with its tag.
+fn json_value<S: Source>(json: &mut Reader<S>, buf: &mut Vec<u8>, depth: usize) -> Result<()> {
+    json.whitespace();
+
+    let start = buf.len();
+    match json.peek() {
+        Some(b'{' | b'[') if depth == json_text::MAX_DEPTH => {
+            return Err(json.error("nested too deeply").into());
+        }
+        Some(b'{') => {
+            buf.push(DataType::Map as u8);
+            if let Some(value) = json_map(json, buf, depth + 1)? {
+                buf.truncate(start);
+                encode::encode_value(buf, &value)?;
+            }
+        }
+        Some(b'[') => {
+            buf.push(DataType::Array as u8);
+            json_array(json, buf, depth + 1)?;
+        }
+        _ => encode::encode_value(buf, &json.scalar()?)?,
+    }
+
+    Ok(())
+}
+
+/// Write a JSON object as an NSON map, without its tag. A single entry map
+/// that stands for a typed value is not written, its value is returned
+/// instead. `depth` counts the map and the maps and arrays around it.
+fn json_map<S: Source>(
+    json: &mut Reader<S>,
+    buf: &mut Vec<u8>,
+    depth: usize,
+) -> Result<Option<Value>> {
+    json.eat(b'{');
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+
+    json.whitespace();
+    if !json.eat(b'}') {
+        let mut count = 0;
+        let mut marker = None;
+
+        loop {
+            let key = json.key()?;
+            encode::write_key(buf, unescape_key(&key))?;
+
+            let value = buf.len();
+            json_value(json, buf, depth).map_err(|e| e.within(&key))?;
+
+            count += 1;
+            if count == 1 && key.starts_with('$') {
+                marker = Some((key, value));
+            }
+
+            json.whitespace();
+            if json.eat(b'}') {
+                break;
+            }
+            if !json.eat(b',') {
+                return Err(json.error("expected `,` or `}`").into());
+            }
+        }
+
+        if let (1, Some((key, value))) = (count, marker) {
+            let value = decode::decode_value(&mut &buf[value..])?;
+
+            if let Some(value) = json_text::marker(&key, &value) {
+                buf.truncate(start);
+                return Ok(Some(value?));
+            }
+        }
+    }
+
+    buf.push(0);
+    close(buf, start, "map len must < MAX_NSON_SIZE")?;
+    Ok(None)
+}
+
+/// Write a JSON array as an NSON array, without its tag.
+fn json_array<S: Source>(json: &mut Reader<S>, buf: &mut Vec<u8>, depth: usize) -> Result<()> {
+    json.eat(b'[');
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+
+    json.whitespace();
+    if !json.eat(b']') {
+        let mut index = 0usize;
+
+        loop {
+            json_value(json, buf, depth).map_err(|e| e.within(&index.to_string()))?;
+            index += 1;
+
+            json.whitespace();
+            if json.eat(b']') {
+                break;
+            }
+            if !json.eat(b',') {
+                return Err(json.error("expected `,` or `]`").into());
+            }
+        }
+    }
+
+    buf.push(0);
+    close(buf, start, "array len must < MAX_NSON_SIZE")
+}
+
+/// Write the length of the container starting at `start`, now complete.
+fn close(buf: &mut [u8], start: usize, message: &str) -> Result<()> {
+    let len = buf.len() - start;
+    if len > crate::MAX_NSON_SIZE as usize {
+        return Err(EncodeError::InvalidValueLen(len, message.to_string()).into());
+    }
+
+    buf[start..start + 4].copy_from_slice(&(len as u32).to_le_bytes());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use super::{Error, json_to_nson, ndjson_to_nson, nson_to_json, nson_to_ndjson};
+    use crate::fixture::values;
+    use crate::json_text::{self, JsonMode};
+    use crate::spec::DataType;
+    use crate::{Array, Value, m};
+
+    use crate::vec as __vec;
+
+    fn to_json(bytes: &[u8], mode: JsonMode) -> Vec<u8> {
+        let mut json = Vec::new();
+        nson_to_json(&mut &bytes[..], &mut json, mode).unwrap();
+        json
+    }
+
+    fn to_nson(json: &str) -> Result<Vec<u8>, Error> {
+        let mut nson = Vec::new();
+        json_to_nson(&mut json.as_bytes(), &mut nson)?;
+        Ok(nson)
+    }
+
+    #[test]
+    fn round_trip() {
+        // NaN is not in the fixture, the bytes still compare equal
+        let mut map = values();
+        map.insert("nan", f32::NAN);
+        let bytes = map.to_bytes().unwrap();
+
+        for mode in [JsonMode::Canonical, JsonMode::Relaxed, JsonMode::Plain] {
+            let json = to_json(&bytes, mode);
+            let mut expected = String::new();
+            json_text::write_with(&mut expected, &Value::Map(map.clone()), mode).unwrap();
+            assert_eq!(core::str::from_utf8(&json).unwrap(), expected);
+        }
+
+        let json = to_json(&bytes, JsonMode::Canonical);
+        assert_eq!(
+            to_nson(core::str::from_utf8(&json).unwrap()).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn read() {
+        let nson = to_nson(r#" { "a" : [ 1 , {"$u16": 2}, {"$$u16": 2} ] , "b" : {} } "#).unwrap();
+        let expected = m! {
+            "a": [1i32, 2u16, {"$u16": 2i32}],
+            "b": {},
+        };
+        assert_eq!(nson, expected.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn ndjson() {
+        let maps = [m! {"a": 1u8}, m! {}, values()];
+        let mut bytes = Vec::new();
+        for map in &maps {
+            bytes.extend(map.to_bytes().unwrap());
+        }
+
+        let mut ndjson = Vec::new();
+        let count =
+            nson_to_ndjson(&mut bytes.as_slice(), &mut ndjson, JsonMode::Canonical).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(ndjson.iter().filter(|&&b| b == b'\n').count(), 3);
+
+        let mut nson = Vec::new();
+        let count = ndjson_to_nson(&mut ndjson.as_slice(), &mut nson).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(nson, bytes);
+
+        let mut nson = Vec::new();
+        let text = "\n{\"a\":{\"$u8\":1}} \r\n\n  {}\n{\"b\":2}";
+        assert_eq!(ndjson_to_nson(&mut text.as_bytes(), &mut nson).unwrap(), 3);
+
+        let mut expected = m! {"a": 1u8}.to_bytes().unwrap();
+        expected.extend(m! {}.to_bytes().unwrap());
+        expected.extend(m! {"b": 2i32}.to_bytes().unwrap());
+        assert_eq!(nson, expected);
+
+        let mut empty = Vec::new();
+        assert_eq!(
+            nson_to_ndjson(&mut &[][..], &mut empty, JsonMode::Plain).unwrap(),
+            0
+        );
+        assert_eq!(ndjson_to_nson(&mut &b" \n"[..], &mut empty).unwrap(), 0);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let syntax = |text: &str| match to_nson(text) {
+            Err(Error::Json(json_text::Error::Syntax { offset, message })) => (offset, message),
+            other => panic!("{:?}", other),
+        };
+
+        assert_eq!(syntax("[]"), (0, "expected a map"));
+        assert_eq!(syntax(""), (0, "expected a map"));
+        assert_eq!(syntax("{\"a\":[1 2]}"), (8, "expected `,` or `]`"));
+        assert_eq!(syntax("{\"a\":1,}"), (7, "expected a string key"));
+        assert_eq!(syntax("{} {}"), (3, "trailing characters"));
+
+        let deep = "{\"a\":".to_string() + &"[".repeat(1_000_000);
+        assert_eq!(syntax(&deep), (260, "nested too deeply"));
+
+        let err = to_nson(r#"{"a":[1,{"$u8":300}]}"#).unwrap_err();
+        assert_eq!(err.to_string(), "a.1: 300 does not fit into U8");
+
+        match to_nson(r#"{"$u8":1}"#) {
+            Err(Error::Json(json_text::Error::InvalidValue { expected, .. })) => {
+                assert_eq!(expected, DataType::Map)
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let err = to_nson(r#"{"a":[{"":1}]}"#).unwrap_err();
+        assert_eq!(err.to_string(), "a.0: key must not be empty at offset 7");
+        assert_eq!(
+            err.to_string(),
+            json_text::from_str(r#"{"a":[{"":1}]}"#)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let mut out = Vec::new();
+        let err = ndjson_to_nson(&mut &b"{} {}\n"[..], &mut out).unwrap_err();
+        assert_eq!(err.to_string(), "expected a new line at offset 3");
+
+        let bytes = m! {"a": [1u8, 2u8]}.to_bytes().unwrap();
+        let mut json = Vec::new();
+        let err = nson_to_json(
+            &mut &bytes[..bytes.len() - 2],
+            &mut json,
+            JsonMode::Canonical,
+        );
+        assert!(matches!(err, Err(Error::Decode(_))));
+
+        let mut json = Vec::new();
+        let err = nson_to_ndjson(&mut &bytes[..2], &mut json, JsonMode::Canonical);
+        assert!(matches!(err, Err(Error::Io(_))));
+
+        let mut deep = Value::Null;
+        for _ in 0..300 {
+            deep = Value::Array(Array::from_vec(__vec![deep]));
+        }
+        let bytes = m! {"a": deep}.to_bytes().unwrap();
+        let mut json = Vec::new();
+        let err = nson_to_json(&mut &bytes[..], &mut json, JsonMode::Canonical).unwrap_err();
+        assert_eq!(err.to_string(), "nested too deeply");
+    }
+}
@@ -11,7 +11,7 @@ use super::id::Id;
 use super::map::Map;
 use super::spec::DataType;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone)]
 pub enum Value {
     F32(f32),
     F64(f64),
@@ -33,8 +33,6 @@ pub enum Value {
     Id(Id),
 }
 
-impl Eq for Value {}
-
 impl fmt::Debug for Value {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -62,44 +60,11 @@ impl fmt::Debug for Value {
     }
 }
 
+/// Prints the [`text`](crate::text) notation, `FromStr` reads it back.
+/// `{:#}` indents maps and arrays.
 impl fmt::Display for Value {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Value::F32(f) => write!(fmt, "F32({})", f),
-            Value::F64(f) => write!(fmt, "F64({})", f),
-            Value::I32(i) => write!(fmt, "I32({})", i),
-            Value::I64(i) => write!(fmt, "I64({})", i),
-            Value::U32(u) => write!(fmt, "U32({})", u),
-            Value::U64(u) => write!(fmt, "U64({})", u),
-            Value::I8(i) => write!(fmt, "I8({})", i),
-            Value::U8(u) => write!(fmt, "U8({})", u),
-            Value::I16(i) => write!(fmt, "I16({})", i),
-            Value::U16(u) => write!(fmt, "U16({})", u),
-            Value::String(s) => write!(fmt, "String({})", s),
-            Value::Array(vec) => {
-                write!(fmt, "Array[")?;
-
-                let mut first = true;
-                for value in vec.iter() {
-                    if !first {
-                        write!(fmt, ", ")?;
-                    }
-
-                    write!(fmt, "{}", value)?;
-                    first = false;
-                }
-
-                write!(fmt, "]")
-            }
-            Value::Map(o) => write!(fmt, "Map({})", o),
-            Value::Bool(b) => write!(fmt, "{}", b),
-            Value::Null => write!(fmt, "null"),
-            Value::Binary(vec) => write!(fmt, "Binary(0x{})", const_hex::encode(&vec.0)),
-            Value::TimeStamp(t) => {
-                write!(fmt, "TimeStamp({})", t.0)
-            }
-            Value::Id(id) => write!(fmt, "Id({})", id),
-        }
+        crate::text::write_value(fmt, self, fmt.alternate().then_some(0))
     }
 }
 
@@ -459,8 +424,28 @@ impl Value {
         }
     }
 
+    /// Turn a map from serde or JSON back into a value: a single `$bin`,
+    /// `$tim` or `$mid` entry stands for that type, anything else is a map
+    /// whose keys are unescaped with [`unescape_key`].
     #[cfg(feature = "serde")]
     pub(crate) fn from_extended_map(msg: Map) -> Value {
+        if let Some(value) = Value::extended(&msg) {
+            return value;
+        }
+
+        if !msg.keys().any(|key| unescape_key(key).len() < key.len()) {
+            return Value::Map(msg);
+        }
+
+        Value::Map(
+            msg.into_iter()
+                .map(|(key, value)| (unescape_key(&key).into(), value))
+                .collect(),
+        )
+    }
+
+    #[cfg(feature = "serde")]
+    fn extended(msg: &Map) -> Option<Value> {
         if msg.len() == 1 {
             let (key, value) = msg.get_index(0).unwrap();
 
@@ -469,32 +454,91 @@ impl Value {
                     if let Value::String(hex) = value
                         && let Ok(bin) = const_hex::decode(hex.as_bytes())
                     {
-                        return Value::Binary(Binary(bin));
+                        return Some(Value::Binary(Binary(bin)));
                     }
                 }
                 "$tim" => {
                     if let Value::U64(u) = value {
-                        return Value::TimeStamp((*u).into());
+                        return Some(Value::TimeStamp((*u).into()));
                     }
                 }
                 "$mid" => {
                     if let Value::String(hex) = value
                         && let Ok(id) = Id::with_string(hex)
                     {
-                        return id.into();
+                        return Some(id.into());
                     }
                 }
                 _ => (),
             }
         }
 
-        Value::Map(msg)
+        None
+    }
+}
+
+/// Names of the single entry maps that stand for a typed value in serde
+/// and JSON, without their `$`.
+const EXTENDED_NAMES: [&str; 12] = [
+    "bin", "tim", "mid", "f32", "f64", "i64", "u32", "u64", "i8", "u8", "i16", "u16",
+];
+
+/// Number of leading `$` of a key made of them and an extended name, such
+/// as `$tim` or `$$$mid`.
+fn marker_depth(key: &str) -> Option<usize> {
+    let name = key.trim_start_matches('$');
+    let depth = key.len() - name.len();
+
+    if depth > 0 && EXTENDED_NAMES.contains(&name) {
+        Some(depth)
+    } else {
+        None
+    }
+}
+
+/// Escape a map key for serde and JSON, so user data never reads as an
+/// extended value: `$tim` becomes `$$tim`, `$$tim` becomes `$$$tim` and so
+/// on. Other keys, `$set` included, are left alone.
+pub(crate) fn escape_key(key: &str) -> alloc::borrow::Cow<'_, str> {
+    if marker_depth(key).is_some() {
+        let mut escaped = String::with_capacity(key.len() + 1);
+        escaped.push('$');
+        escaped.push_str(key);
+        alloc::borrow::Cow::Owned(escaped)
+    } else {
+        alloc::borrow::Cow::Borrowed(key)
+    }
+}
+
+/// Undo [`escape_key`]. A bare `$tim` is left as is, it only comes from a
+/// marker that did not hold a valid value.
+pub(crate) fn unescape_key(key: &str) -> &str {
+    match marker_depth(key) {
+        Some(depth) if depth > 1 => &key[1..],
+        _ => key,
     }
 }
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
 pub struct Binary(pub Vec<u8>);
 
+impl Binary {
+    /// Decode a hexadecimal string, with or without a `0x` prefix.
+    pub fn from_hex(hex: &str) -> Result<Binary, const_hex::FromHexError> {
+        const_hex::decode(hex).map(Binary)
+    }
+
+    /// Used by the macros to check literal hex strings at compile time.
+    #[doc(hidden)]
+    pub const fn literal_hex(hex: &'static str) -> &'static str {
+        assert!(
+            const_hex::const_check(hex.as_bytes()).is_ok(),
+            "nson binary must be an even length hexadecimal string"
+        );
+        hex
+    }
+}
+
 impl From<Vec<u8>> for Binary {
     fn from(v: Vec<u8>) -> Self {
         Binary(v)
@@ -534,3 +578,136 @@ impl From<TimeStamp> for u64 {
         t.0
     }
 }
+
+impl TimeStamp {
+    /// Format as an RFC 3339 UTC date, such as `2024-11-27T08:00:00Z`.
+    ///
+    /// Years past 9999 take as many digits as they need, which RFC 3339
+    /// does not allow but `from_rfc3339` reads back.
+    pub fn to_rfc3339(&self) -> String {
+        let days = (self.0 / 86400) as i64;
+        let secs = self.0 % 86400;
+        let (year, month, day) = civil_from_days(days);
+
+        alloc::format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+
+    /// Parse an RFC 3339 date. Fractional seconds are truncated, and
+    /// dates before 1970 are rejected. Years past 9999 are read with more
+    /// digits, as `to_rfc3339` writes them.
+    pub fn from_rfc3339(s: &str) -> Option<TimeStamp> {
+        let year_len = s.bytes().position(|c| c == b'-')?;
+        if !(4..=12).contains(&year_len) || (year_len > 4 && s.starts_with('0')) {
+            return None;
+        }
+
+        let year = &s[..year_len];
+        if !year.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let year: i64 = year.parse().ok()?;
+
+        // the rest lines up as if the year had four digits
+        let s = &s[year_len - 4..];
+        let b = s.as_bytes();
+
+        if b.len() < 20
+            || b[4] != b'-'
+            || b[7] != b'-'
+            || !matches!(b[10], b'T' | b't' | b' ')
+            || b[13] != b':'
+            || b[16] != b':'
+        {
+            return None;
+        }
+
+        let num = |range: core::ops::Range<usize>| -> Option<i64> {
+            let digits = s.get(range)?;
+            if !digits.bytes().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            digits.parse().ok()
+        };
+
+        let (month, day) = (num(5..7)?, num(8..10)?);
+        let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+
+        let mut rest = &b[19..];
+        if rest[0] == b'.' {
+            let digits = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 {
+                return None;
+            }
+            rest = &rest[1 + digits..];
+        }
+
+        let offset = match rest {
+            [b'Z' | b'z'] => 0,
+            [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+                let digits = [*h1, *h2, *m1, *m2];
+                if !digits.iter().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                let [h1, h2, m1, m2] = digits.map(|c| (c - b'0') as i64);
+                let offset = (h1 * 10 + h2) * 3600 + (m1 * 10 + m2) * 60;
+                if *sign == b'-' { -offset } else { offset }
+            }
+            _ => return None,
+        };
+
+        let secs = days_from_civil(year, month, day) as i128 * 86400
+            + (hour * 3600 + minute * 60 + second - offset) as i128;
+
+        u64::try_from(secs).ok().map(TimeStamp)
+    }
+}
+
+// Howard Hinnant's algorithms for the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
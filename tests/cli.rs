@@ -0,0 +1,153 @@
+//! The `nson` binary, run with `cargo test --features cli`.
+
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use nson::{Map, m};
+
+/// Run `nson` with `input` on stdin, returning the exit code and stdout.
+fn nson(args: &[&str], input: &[u8]) -> (i32, Vec<u8>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nson"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    (output.status.code().unwrap(), output.stdout)
+}
+
+fn text(args: &[&str], input: &[u8]) -> (i32, String) {
+    let (code, out) = nson(args, input);
+    (code, String::from_utf8(out).unwrap())
+}
+
+fn documents() -> Vec<u8> {
+    let mut bytes = m! {"name": "probe", "temp": 21.5f32, "tags": ["a", "b"]}
+        .to_bytes()
+        .unwrap();
+    bytes.extend(m! {"name": "relay", "on": true}.to_bytes().unwrap());
+    bytes
+}
+
+#[test]
+fn encode_decode() {
+    let (code, bytes) = nson(&["encode"], br#"{"n": {"$u8": 3}, "t": {"$tim": 5}}"#);
+    assert_eq!(code, 0);
+    assert_eq!(
+        Map::from_bytes(&bytes).unwrap(),
+        m! {"n": 3u8, "t": nson::TimeStamp(5)}
+    );
+
+    let (code, bytes) = nson(&["encode", "--from", "text"], b"{n: 3u8, t: TS(5)}");
+    assert_eq!(code, 0);
+    assert_eq!(
+        text(&["decode"], &bytes),
+        (0, "{n: 3u8, t: TS(5)}\n".into())
+    );
+    assert_eq!(
+        text(&["decode", "--to=json", "--mode", "relaxed"], &bytes),
+        (0, "{\"n\":3,\"t\":\"1970-01-01T00:00:05Z\"}\n".into())
+    );
+
+    // one document only
+    let (code, _) = nson(&["decode"], &documents());
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn cat_round_trip() {
+    let (code, lines) = text(&["cat", "--to", "json"], &documents());
+    assert_eq!(code, 0);
+    assert_eq!(lines.lines().count(), 2);
+
+    let (code, bytes) = nson(&["encode", "--lines"], lines.as_bytes());
+    assert_eq!(code, 0);
+    assert_eq!(bytes, documents());
+}
+
+#[test]
+fn get() {
+    assert_eq!(
+        text(&["get", "tags.1"], &documents()),
+        (0, "\"b\"\n".into())
+    );
+    assert_eq!(
+        text(&["get", "name"], &documents()),
+        (0, "\"probe\"\n\"relay\"\n".into())
+    );
+    assert_eq!(text(&["get", "tags.2"], &documents()), (1, "".into()));
+
+    // `\.` is a dot within a key
+    let bytes = m! {"a.b": {"c": 1}, "a": {"b": 2}}.to_bytes().unwrap();
+    assert_eq!(text(&["get", r"a\.b.c"], &bytes), (0, "1\n".into()));
+    assert_eq!(text(&["get", "a.b"], &bytes), (0, "2\n".into()));
+}
+
+#[test]
+fn diff() {
+    let dir = std::env::temp_dir().join(format!("nson-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let a = dir.join("a.txt");
+    let b = dir.join("b.txt");
+    std::fs::write(&a, "{n: 1, list: [1, 2], same: true}").unwrap();
+    std::fs::write(&b, "{n: 1u8, list: [1], same: true, new: null}").unwrap();
+
+    let files = [a.to_str().unwrap(), b.to_str().unwrap()];
+    let (code, out) = text(&["diff", "--from", "text", files[0], files[1]], b"");
+    assert_eq!(code, 1);
+    assert_eq!(out, "- n: 1\n+ n: 1u8\n- list.1: 2\n+ new: null\n");
+
+    let (code, out) = text(&["diff", "--from", "text", files[0], files[0]], b"");
+    assert_eq!((code, out.as_str()), (0, ""));
+
+    // keys with dots are written the way `get` reads them
+    std::fs::write(&a, r#"{"a.b": 1}"#).unwrap();
+    std::fs::write(&b, r#"{"a.b": 2}"#).unwrap();
+    let (code, out) = text(&["diff", "--from", "text", files[0], files[1]], b"");
+    assert_eq!(code, 1);
+    assert_eq!(out, "- a\\.b: 1\n+ a\\.b: 2\n");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn validate() {
+    assert_eq!(
+        text(&["validate"], &documents()),
+        (0, "2 documents ok\n".into())
+    );
+
+    // a bool byte that is neither 0 nor 1
+    let mut bytes = m! {"on": true}.to_bytes().unwrap();
+    let len = bytes.len();
+    bytes[len - 2] = 2;
+    let (code, out) = text(&["validate"], &bytes);
+    assert_eq!(code, 1);
+    assert!(out.contains("re-encoding differs at byte 8"), "{}", out);
+
+    let bytes = m! {"a.b": 1}.to_bytes().unwrap();
+    assert_eq!(text(&["validate"], &bytes).0, 0);
+    assert_eq!(text(&["validate", "--strict-keys"], &bytes).0, 1);
+}
+
+#[test]
+fn dump_and_usage() {
+    let (code, out) = text(&["dump"], &documents());
+    assert_eq!(code, 0);
+    assert!(out.contains("key \"tags\""));
+
+    assert_eq!(nson(&["frob"], b"").0, 2);
+    assert_eq!(nson(&["decode", "--to", "yaml"], b"").0, 2);
+    assert_eq!(nson(&["get"], b"").0, 2);
+
+    // --mode only applies to JSON
+    assert_eq!(nson(&["decode", "--mode", "relaxed"], b"").0, 2);
+    assert_eq!(nson(&["cat", "--to", "nson", "--mode", "plain"], b"").0, 2);
+}
@@ -1,5 +1,7 @@
 //! 扩展类型测试 (I8, U8, I16, U16)
 
+#![allow(clippy::approx_constant, clippy::bool_assert_comparison)]
+
 use nson::{Array, Map, Value, m};
 
 #[test]
@@ -1,5 +1,7 @@
 //! 综合集成测试
 
+#![allow(clippy::approx_constant, clippy::bool_assert_comparison)]
+
 use nson::{Array, Id, Map, TimeStamp, Value, m};
 
 #[test]
@@ -22,7 +24,7 @@ fn test_complete_encode_decode() {
         "l": Value::Null,
         "m": vec![1u8, 2, 3, 4, 5, 6],
         "n": TimeStamp(12345),
-        "p": mid.clone(),
+        "p": mid,
         // 新类型
         "q": 10i8,
         "r": 200u8,
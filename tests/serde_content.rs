@@ -0,0 +1,225 @@
+//! Flatten, untagged and tagged enums go through serde's buffered content,
+//! every `Value` variant must come out of it with its NSON type.
+
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use nson::{Array, Binary, Id, Map, TimeStamp, Value, m};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+fn values() -> Vec<Value> {
+    vec![
+        Value::F32(1.5),
+        Value::F64(2.5),
+        Value::I32(-3),
+        Value::I64(-4),
+        Value::U32(5),
+        Value::U64(6),
+        Value::I8(-7),
+        Value::U8(8),
+        Value::I16(-9),
+        Value::U16(10),
+        Value::String("s".into()),
+        Value::Array(Array::from_vec(vec![
+            Value::U8(1),
+            Value::Id(Id::new_raw(1, 2, 3)),
+        ])),
+        Value::Map(m! {"a": 1u16, "t": TimeStamp(12)}),
+        Value::Bool(true),
+        Value::Null,
+        Value::Binary(Binary(vec![1, 2, 3])),
+        Value::TimeStamp(TimeStamp(1234)),
+        Value::Id(Id::new_raw(4, 5, 6)),
+    ]
+}
+
+fn round_trip<T>(item: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let value = nson::serde::to_nson(item).unwrap();
+    let decoded: T = nson::serde::from_nson(value.clone()).unwrap();
+    assert_eq!(&decoded, item, "from_nson");
+
+    let decoded: T = nson::serde::from_nson_ref(&value).unwrap();
+    assert_eq!(&decoded, item, "from_nson_ref");
+
+    let bytes = nson::to_vec(item).unwrap();
+    assert_eq!(bytes, value.to_bytes().unwrap(), "to_vec");
+    let decoded: T = nson::from_slice(&bytes).unwrap();
+    assert_eq!(&decoded, item, "from_slice");
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Fields {
+    value: Value,
+    id: Id,
+    time: TimeStamp,
+    bin: Binary,
+    owner: Option<Id>,
+    history: Vec<TimeStamp>,
+    #[serde(with = "serde_bytes")]
+    raw: Vec<u8>,
+}
+
+impl Fields {
+    fn new(value: Value) -> Fields {
+        Fields {
+            value,
+            id: Id::new_raw(7, 8, 9),
+            time: TimeStamp(99),
+            bin: Binary(vec![9, 8]),
+            owner: Some(Id::new_raw(1, 2, 3)),
+            history: vec![TimeStamp(1), TimeStamp(2)],
+            raw: vec![7],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Flatten {
+    name: String,
+    #[serde(flatten)]
+    fields: Fields,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct FlattenMap {
+    name: String,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum Untagged {
+    Struct { name: String, fields: Fields },
+    Newtype(Value),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+enum Internal {
+    Struct {
+        value: Value,
+        id: Id,
+        time: TimeStamp,
+    },
+    Newtype(Fields),
+    Unit,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "t", content = "c")]
+enum Adjacent {
+    Struct { value: Value, id: Id },
+    Newtype(Value),
+    Tuple(Value, Id),
+    Unit,
+}
+
+#[test]
+fn flatten() {
+    for value in values() {
+        let item = Flatten {
+            name: "n".into(),
+            fields: Fields::new(value.clone()),
+        };
+        round_trip(&item);
+
+        let nson = nson::serde::to_nson(&item).unwrap();
+        let map = nson.as_map().unwrap();
+        assert_eq!(map.get("value"), Some(&value));
+        assert_eq!(map.get("id"), Some(&Value::Id(Id::new_raw(7, 8, 9))));
+        assert_eq!(map.get("time"), Some(&Value::TimeStamp(TimeStamp(99))));
+    }
+}
+
+#[test]
+fn flatten_map() {
+    let item = FlattenMap {
+        name: "n".into(),
+        extra: values()
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+    };
+    round_trip(&item);
+}
+
+#[test]
+fn untagged() {
+    for value in values() {
+        round_trip(&Untagged::Struct {
+            name: "n".into(),
+            fields: Fields::new(value.clone()),
+        });
+
+        let item = Untagged::Newtype(value.clone());
+        let nson = nson::serde::to_nson(&item).unwrap();
+        assert_eq!(nson, value);
+
+        // A map holding `name` and `fields` would decode as `Struct`, the
+        // rest must keep their type.
+        round_trip(&item);
+    }
+}
+
+#[test]
+fn internally_tagged() {
+    for value in values() {
+        let item = Internal::Struct {
+            value: value.clone(),
+            id: Id::new_raw(1, 1, 1),
+            time: TimeStamp(5),
+        };
+        round_trip(&item);
+
+        let nson = nson::serde::to_nson(&item).unwrap();
+        let map = nson.as_map().unwrap();
+        assert_eq!(map.get_str("type").unwrap(), "Struct");
+        assert_eq!(map.get("value"), Some(&value));
+
+        round_trip(&Internal::Newtype(Fields::new(value)));
+    }
+
+    round_trip(&Internal::Unit);
+}
+
+#[test]
+fn adjacently_tagged() {
+    for value in values() {
+        round_trip(&Adjacent::Struct {
+            value: value.clone(),
+            id: Id::new_raw(2, 2, 2),
+        });
+        round_trip(&Adjacent::Newtype(value.clone()));
+        round_trip(&Adjacent::Tuple(value.clone(), Id::new_raw(3, 3, 3)));
+
+        let nson = nson::serde::to_nson(&Adjacent::Newtype(value.clone())).unwrap();
+        assert_eq!(nson.as_map().unwrap().get("c"), Some(&value));
+    }
+
+    round_trip(&Adjacent::Unit);
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum Typed {
+    Number(u64),
+    Time(TimeStamp),
+    Id(Id),
+    Binary(Binary),
+    Map(Map),
+}
+
+#[test]
+fn untagged_typed() {
+    round_trip(&Typed::Number(5));
+    round_trip(&Typed::Time(TimeStamp(5)));
+    round_trip(&Typed::Id(Id::new_raw(1, 2, 3)));
+    round_trip(&Typed::Binary(Binary(vec![1])));
+    round_trip(&Typed::Map(m! {"a": Binary(vec![1]), "b": TimeStamp(3)}));
+}